@@ -4,17 +4,15 @@
 
 use std::str::FromStr;
 
-use bytes::buf::BufExt;
-use futures::future::ready;
+use futures::future::{join_all, ready};
 use futures::{Future, TryFutureExt};
 use hyper::client::connect::Connect;
 use hyper::{StatusCode, Uri};
 use serde_derive::{Deserialize, Serialize};
 use serde_json;
 
-use crate::client::{Client, ClusterInfo, Response};
-use crate::error::{ApiError, Error};
-use crate::first_ok::first_ok;
+use crate::client::{Client, Response};
+use crate::error::Error;
 
 /// The structure returned by the `GET /v2/auth/enable` endpoint.
 #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
@@ -253,6 +251,38 @@ impl Role {
             None => &[],
         }
     }
+
+    /// Returns true if this role is allowed to read the given key, as etcd decides it.
+    ///
+    /// A permission ending in `*` matches every key that starts with the rest of it, so "/foo/*"
+    /// matches "/foo/bar" and "/foo/bar/baz" but not "/foo" itself. Any other permission only
+    /// matches the key exactly. The root role can read every key.
+    pub fn can_read(&self, key: &str) -> bool {
+        self.is_root() || has_access(self.kv_read_permissions(), key)
+    }
+
+    /// Returns true if this role is allowed to write the given key, as etcd decides it. See
+    /// `Role::can_read` for how permissions are matched against the key.
+    pub fn can_write(&self, key: &str) -> bool {
+        self.is_root() || has_access(self.kv_write_permissions(), key)
+    }
+
+    /// Returns true if this is etcd's built-in root role, which has access to every key.
+    fn is_root(&self) -> bool {
+        self.name == "root"
+    }
+}
+
+/// Returns true if any of the given permission patterns matches the key, using etcd's matching
+/// rules: a trailing `*` matches any suffix, and anything else must match exactly.
+fn has_access(patterns: &[String], key: &str) -> bool {
+    patterns.iter().any(|pattern| {
+        if pattern.ends_with('*') {
+            key.starts_with(&pattern[..pattern.len() - 1])
+        } else {
+            key == pattern
+        }
+    })
 }
 
 /// A list of all roles.
@@ -415,6 +445,23 @@ impl Permission {
     }
 }
 
+/// Changes a user's password, leaving the roles granted to them as they are.
+pub fn change_password<C, N, P>(
+    client: &Client<C>,
+    name: N,
+    new_password: P,
+) -> impl Future<Output = Result<Response<User>, Vec<Error>>> + Send
+where
+    C: Clone + Connect + Sync + Send + 'static,
+    N: Into<String>,
+    P: Into<String>,
+{
+    let mut update = UserUpdate::new(name);
+    update.update_password(new_password);
+
+    update_user(client, update)
+}
+
 /// Creates a new role.
 pub async fn create_role<C>(client: &Client<C>, role: Role) -> Result<Response<Role>, Vec<Error>>
 where
@@ -422,40 +469,29 @@ where
 {
     let http_client = client.http_client().clone();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
-        let body = ready(serde_json::to_string(&role).map_err(Error::from));
+    client
+        .first_ok(false, move |member| {
+            let body = ready(serde_json::to_string(&role).map_err(Error::from));
 
-        let url = build_url(member, &format!("/roles/{}", role.name));
-        let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
+            let url = build_url(member, &format!("/roles/{}", role.name));
+            let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
 
-        let params = futures::future::try_join(uri, body);
+            let params = futures::future::try_join(uri, body);
 
-        let http_client = http_client.clone();
+            let http_client = http_client.clone();
 
-        let response =
-            params.and_then(move |(uri, body)| http_client.put(uri, body).map_err(Error::from));
+            let response = params.and_then(move |(uri, body)| http_client.put(uri, body));
 
-        response.and_then(|response| {
-            let status = response.status();
-            let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::aggregate(response.into_body())
-                .map_ok(BufExt::reader)
-                .err_into();
-
-            body.and_then(move |body| {
-                ready(match status {
-                    StatusCode::OK | StatusCode::CREATED => {
-                        match serde_json::from_reader::<_, Role>(body) {
-                            Ok(data) => Ok(Response { data, cluster_info }),
-                            Err(error) => Err(Error::Serialization(error)),
-                        }
-                    }
-                    status => Err(Error::UnexpectedStatus(status)),
+            response.and_then(|response| {
+                ready(match response.status() {
+                    StatusCode::OK | StatusCode::CREATED => response
+                        .json::<Role>()
+                        .map(|data| response.into_response(data)),
+                    _ => Err(response.api_error_or_status()),
                 })
             })
         })
-    })
-    .await
+        .await
 }
 
 /// Creates a new user.
@@ -465,40 +501,29 @@ where
 {
     let http_client = client.http_client().clone();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
-        let body = ready(serde_json::to_string(&user).map_err(Error::from));
+    client
+        .first_ok(false, move |member| {
+            let body = ready(serde_json::to_string(&user).map_err(Error::from));
 
-        let url = build_url(member, &format!("/users/{}", user.name));
-        let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
+            let url = build_url(member, &format!("/users/{}", user.name));
+            let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
 
-        let params = futures::future::try_join(uri, body);
+            let params = futures::future::try_join(uri, body);
 
-        let http_client = http_client.clone();
+            let http_client = http_client.clone();
 
-        let response =
-            params.and_then(move |(uri, body)| http_client.put(uri, body).map_err(Error::from));
+            let response = params.and_then(move |(uri, body)| http_client.put(uri, body));
 
-        response.and_then(|response| {
-            let status = response.status();
-            let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::aggregate(response.into_body())
-                .map_ok(BufExt::reader)
-                .err_into();
-
-            body.and_then(move |body| {
-                ready(match status {
-                    StatusCode::OK | StatusCode::CREATED => {
-                        match serde_json::from_reader::<_, User>(body) {
-                            Ok(data) => Ok(Response { data, cluster_info }),
-                            Err(error) => Err(Error::Serialization(error)),
-                        }
-                    }
-                    status => Err(Error::UnexpectedStatus(status)),
+            response.and_then(|response| {
+                ready(match response.status() {
+                    StatusCode::OK | StatusCode::CREATED => response
+                        .json::<User>()
+                        .map(|data| response.into_response(data)),
+                    _ => Err(response.api_error_or_status()),
                 })
             })
         })
-    })
-    .await
+        .await
 }
 
 /// Deletes a role.
@@ -513,25 +538,19 @@ where
     let http_client = client.http_client().clone();
     let name = name.into();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
+    client.first_ok(false, move |member| {
         let url = build_url(member, &format!("/roles/{}", name));
         let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
 
         let http_client = http_client.clone();
 
-        let response = uri.and_then(move |uri| http_client.delete(uri).map_err(Error::from));
+        let response = uri.and_then(move |uri| http_client.delete(uri));
 
         response.and_then(|response| {
-            let status = response.status();
-            let cluster_info = ClusterInfo::from(response.headers());
-
-            ready(if status == StatusCode::OK {
-                Ok(Response {
-                    data: (),
-                    cluster_info,
-                })
+            ready(if response.status() == StatusCode::OK {
+                Ok(response.into_response(()))
             } else {
-                Err(Error::UnexpectedStatus(status))
+                Err(response.api_error_or_status())
             })
         })
     })
@@ -546,29 +565,24 @@ where
     let http_client = client.http_client().clone();
     let name = name.into();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
-        let url = build_url(member, &format!("/users/{}", name));
-        let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
-
-        let http_client = http_client.clone();
+    client
+        .first_ok(false, move |member| {
+            let url = build_url(member, &format!("/users/{}", name));
+            let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
 
-        let response = uri.and_then(move |uri| http_client.delete(uri).map_err(Error::from));
+            let http_client = http_client.clone();
 
-        response.and_then(|response| {
-            let status = response.status();
-            let cluster_info = ClusterInfo::from(response.headers());
+            let response = uri.and_then(move |uri| http_client.delete(uri));
 
-            ready(if status == StatusCode::OK {
-                Ok(Response {
-                    data: (),
-                    cluster_info,
+            response.and_then(|response| {
+                ready(if response.status() == StatusCode::OK {
+                    Ok(response.into_response(()))
+                } else {
+                    Err(response.api_error_or_status())
                 })
-            } else {
-                Err(Error::UnexpectedStatus(status))
             })
         })
-    })
-    .await
+        .await
 }
 
 /// Attempts to disable the auth system.
@@ -578,32 +592,24 @@ where
 {
     let http_client = client.http_client().clone();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
-        let url = build_url(member, "/enable");
-        let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
+    client
+        .first_ok(false, move |member| {
+            let url = build_url(member, "/enable");
+            let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
 
-        let http_client = http_client.clone();
+            let http_client = http_client.clone();
 
-        let response = uri.and_then(move |uri| http_client.delete(uri).map_err(Error::from));
+            let response = uri.and_then(move |uri| http_client.delete(uri));
 
-        response.and_then(|response| {
-            let status = response.status();
-            let cluster_info = ClusterInfo::from(response.headers());
-
-            ready(match status {
-                StatusCode::OK => Ok(Response {
-                    data: AuthChange::Changed,
-                    cluster_info,
-                }),
-                StatusCode::CONFLICT => Ok(Response {
-                    data: AuthChange::Unchanged,
-                    cluster_info,
-                }),
-                _ => Err(Error::UnexpectedStatus(status)),
+            response.and_then(|response| {
+                ready(match response.status() {
+                    StatusCode::OK => Ok(response.into_response(AuthChange::Changed)),
+                    StatusCode::CONFLICT => Ok(response.into_response(AuthChange::Unchanged)),
+                    _ => Err(response.api_error_or_status()),
+                })
             })
         })
-    })
-    .await
+        .await
 }
 
 /// Attempts to enable the auth system.
@@ -613,33 +619,24 @@ where
 {
     let http_client = client.http_client().clone();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
-        let url = build_url(member, "/enable");
-        let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
+    client
+        .first_ok(false, move |member| {
+            let url = build_url(member, "/enable");
+            let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
 
-        let http_client = http_client.clone();
+            let http_client = http_client.clone();
 
-        let response =
-            uri.and_then(move |uri| http_client.put(uri, "".to_owned()).map_err(Error::from));
+            let response = uri.and_then(move |uri| http_client.put(uri, "".to_owned()));
 
-        response.and_then(|response| {
-            let status = response.status();
-            let cluster_info = ClusterInfo::from(response.headers());
-
-            ready(match status {
-                StatusCode::OK => Ok(Response {
-                    data: AuthChange::Changed,
-                    cluster_info,
-                }),
-                StatusCode::CONFLICT => Ok(Response {
-                    data: AuthChange::Unchanged,
-                    cluster_info,
-                }),
-                _ => Err(Error::UnexpectedStatus(status)),
+            response.and_then(|response| {
+                ready(match response.status() {
+                    StatusCode::OK => Ok(response.into_response(AuthChange::Changed)),
+                    StatusCode::CONFLICT => Ok(response.into_response(AuthChange::Unchanged)),
+                    _ => Err(response.api_error_or_status()),
+                })
             })
         })
-    })
-    .await
+        .await
 }
 
 /// Get a role.
@@ -654,30 +651,21 @@ where
     let http_client = client.http_client().clone();
     let name = name.into();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
+    client.first_ok(true, move |member| {
         let url = build_url(member, &format!("/roles/{}", name));
         let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
 
         let http_client = http_client.clone();
 
-        let response = uri.and_then(move |uri| http_client.get(uri).map_err(Error::from));
+        let response = uri.and_then(move |uri| http_client.get(uri));
 
         response.and_then(|response| {
-            let status = response.status();
-            let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::aggregate(response.into_body())
-                .map_ok(BufExt::reader)
-                .err_into();
-
-            body.and_then(move |body| {
-                ready(if status == StatusCode::OK {
-                    match serde_json::from_reader::<_, Role>(body) {
-                        Ok(data) => Ok(Response { data, cluster_info }),
-                        Err(error) => Err(Error::Serialization(error)),
-                    }
-                } else {
-                    Err(Error::UnexpectedStatus(status))
-                })
+            ready(if response.status() == StatusCode::OK {
+                response
+                    .json::<Role>()
+                    .map(|data| response.into_response(data))
+            } else {
+                Err(response.api_error_or_status())
             })
         })
     })
@@ -690,38 +678,28 @@ where
 {
     let http_client = client.http_client().clone();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
-        let url = build_url(member, "/roles");
-        let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
+    client
+        .first_ok(true, move |member| {
+            let url = build_url(member, "/roles");
+            let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
 
-        let http_client = http_client.clone();
+            let http_client = http_client.clone();
 
-        let response = uri.and_then(move |uri| http_client.get(uri).map_err(Error::from));
+            let response = uri.and_then(move |uri| http_client.get(uri));
 
-        response.and_then(|response| {
-            let status = response.status();
-            let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::aggregate(response.into_body())
-                .map_ok(BufExt::reader)
-                .err_into();
-
-            body.and_then(move |body| {
-                ready(if status == StatusCode::OK {
-                    match serde_json::from_reader::<_, Roles>(body) {
-                        Ok(roles) => {
-                            let data = roles.roles.unwrap_or_else(|| Vec::with_capacity(0));
-
-                            Ok(Response { data, cluster_info })
-                        }
-                        Err(error) => Err(Error::Serialization(error)),
-                    }
+            response.and_then(|response| {
+                ready(if response.status() == StatusCode::OK {
+                    response.json::<Roles>().map(|roles| {
+                        let data = roles.roles.unwrap_or_else(|| Vec::with_capacity(0));
+
+                        response.into_response(data)
+                    })
                 } else {
-                    Err(Error::UnexpectedStatus(status))
+                    Err(response.api_error_or_status())
                 })
             })
         })
-    })
-    .await
+        .await
 }
 
 /// Get a user.
@@ -733,34 +711,26 @@ where
     let http_client = client.http_client().clone();
     let name = name.into();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
-        let url = build_url(member, &format!("/users/{}", name));
-        let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
+    client
+        .first_ok(true, move |member| {
+            let url = build_url(member, &format!("/users/{}", name));
+            let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
 
-        let http_client = http_client.clone();
+            let http_client = http_client.clone();
 
-        let response = uri.and_then(move |uri| http_client.get(uri).map_err(Error::from));
+            let response = uri.and_then(move |uri| http_client.get(uri));
 
-        response.and_then(|response| {
-            let status = response.status();
-            let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::aggregate(response.into_body())
-                .map_ok(BufExt::reader)
-                .err_into();
-
-            body.and_then(move |body| {
-                ready(if status == StatusCode::OK {
-                    match serde_json::from_reader::<_, UserDetail>(body) {
-                        Ok(data) => Ok(Response { data, cluster_info }),
-                        Err(error) => Err(Error::Serialization(error)),
-                    }
+            response.and_then(|response| {
+                ready(if response.status() == StatusCode::OK {
+                    response
+                        .json::<UserDetail>()
+                        .map(|data| response.into_response(data))
                 } else {
-                    Err(Error::UnexpectedStatus(status))
+                    Err(response.api_error_or_status())
                 })
             })
         })
-    })
-    .await
+        .await
 }
 
 /// Gets all users.
@@ -770,38 +740,73 @@ where
 {
     let http_client = client.http_client().clone();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
-        let url = build_url(member, "/users");
-        let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
+    client
+        .first_ok(true, move |member| {
+            let url = build_url(member, "/users");
+            let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
 
-        let http_client = http_client.clone();
+            let http_client = http_client.clone();
 
-        let response = uri.and_then(move |uri| http_client.get(uri).map_err(Error::from));
+            let response = uri.and_then(move |uri| http_client.get(uri));
 
-        response.and_then(|response| {
-            let status = response.status();
-            let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::aggregate(response.into_body())
-                .map_ok(BufExt::reader)
-                .err_into();
-
-            body.and_then(move |body| {
-                ready(if status == StatusCode::OK {
-                    match serde_json::from_reader::<_, Users>(body) {
-                        Ok(users) => {
-                            let data = users.users.unwrap_or_else(|| Vec::with_capacity(0));
-
-                            Ok(Response { data, cluster_info })
-                        }
-                        Err(error) => Err(Error::Serialization(error)),
-                    }
+            response.and_then(|response| {
+                ready(if response.status() == StatusCode::OK {
+                    response.json::<Users>().map(|users| {
+                        let data = users.users.unwrap_or_else(|| Vec::with_capacity(0));
+
+                        response.into_response(data)
+                    })
                 } else {
-                    Err(Error::UnexpectedStatus(status))
+                    Err(response.api_error_or_status())
                 })
             })
         })
-    })
-    .await
+        .await
+}
+
+/// Grants read access to a resource to each of the given roles.
+///
+/// The roles are updated concurrently. A result is returned for each role, in the same order as
+/// `roles`, so that the failure of one update doesn't prevent the others from being applied.
+pub async fn grant_read_to_roles<C>(
+    client: &Client<C>,
+    key: &str,
+    roles: &[&str],
+) -> Vec<Result<Response<Role>, Vec<Error>>>
+where
+    C: Clone + Connect + Sync + Send + 'static,
+{
+    grant_to_roles(client, key, roles, true, false).await
+}
+
+/// Grants read and write access to a resource to each of the given roles.
+///
+/// The roles are updated concurrently. A result is returned for each role, in the same order as
+/// `roles`, so that the failure of one update doesn't prevent the others from being applied.
+pub async fn grant_readwrite_to_roles<C>(
+    client: &Client<C>,
+    key: &str,
+    roles: &[&str],
+) -> Vec<Result<Response<Role>, Vec<Error>>>
+where
+    C: Clone + Connect + Sync + Send + 'static,
+{
+    grant_to_roles(client, key, roles, true, true).await
+}
+
+/// Grants write access to a resource to each of the given roles.
+///
+/// The roles are updated concurrently. A result is returned for each role, in the same order as
+/// `roles`, so that the failure of one update doesn't prevent the others from being applied.
+pub async fn grant_write_to_roles<C>(
+    client: &Client<C>,
+    key: &str,
+    roles: &[&str],
+) -> Vec<Result<Response<Role>, Vec<Error>>>
+where
+    C: Clone + Connect + Sync + Send + 'static,
+{
+    grant_to_roles(client, key, roles, false, true).await
 }
 
 /// Determines whether or not the auth system is enabled.
@@ -813,36 +818,21 @@ where
 {
     let http_client = client.http_client().clone();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
+    client.first_ok(true, move |member| {
         let url = build_url(member, "/enable");
         let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
 
         let http_client = http_client.clone();
 
-        let response = uri.and_then(move |uri| http_client.get(uri).map_err(Error::from));
+        let response = uri.and_then(move |uri| http_client.get(uri));
 
         response.and_then(|response| {
-            let status = response.status();
-            let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::aggregate(response.into_body())
-                .map_ok(BufExt::reader)
-                .err_into();
-
-            body.and_then(move |body| {
-                ready(if status == StatusCode::OK {
-                    match serde_json::from_reader::<_, AuthStatus>(body) {
-                        Ok(data) => Ok(Response {
-                            data: data.enabled,
-                            cluster_info,
-                        }),
-                        Err(error) => Err(Error::Serialization(error)),
-                    }
-                } else {
-                    match serde_json::from_reader::<_, ApiError>(body) {
-                        Ok(error) => Err(Error::Api(error)),
-                        Err(error) => Err(Error::Serialization(error)),
-                    }
-                })
+            ready(if response.status() == StatusCode::OK {
+                response
+                    .json::<AuthStatus>()
+                    .map(|data| response.into_response(data.enabled))
+            } else {
+                Err(response.api_error_or_status())
             })
         })
     })
@@ -858,39 +848,30 @@ where
 {
     let http_client = client.http_client().clone();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
-        let body = ready(serde_json::to_string(&role).map_err(Error::from));
+    client
+        .first_ok(false, move |member| {
+            let body = ready(serde_json::to_string(&role).map_err(Error::from));
 
-        let url = build_url(member, &format!("/roles/{}", role.name));
-        let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
+            let url = build_url(member, &format!("/roles/{}", role.name));
+            let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
 
-        let params = futures::future::try_join(uri, body);
+            let params = futures::future::try_join(uri, body);
 
-        let http_client = http_client.clone();
+            let http_client = http_client.clone();
 
-        let response =
-            params.and_then(move |(uri, body)| http_client.put(uri, body).map_err(Error::from));
+            let response = params.and_then(move |(uri, body)| http_client.put(uri, body));
 
-        response.and_then(|response| {
-            let status = response.status();
-            let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::aggregate(response.into_body())
-                .map_ok(BufExt::reader)
-                .err_into();
-
-            body.and_then(move |body| {
-                ready(if status == StatusCode::OK {
-                    match serde_json::from_reader::<_, Role>(body) {
-                        Ok(data) => Ok(Response { data, cluster_info }),
-                        Err(error) => Err(Error::Serialization(error)),
-                    }
+            response.and_then(|response| {
+                ready(if response.status() == StatusCode::OK {
+                    response
+                        .json::<Role>()
+                        .map(|data| response.into_response(data))
                 } else {
-                    Err(Error::UnexpectedStatus(status))
+                    Err(response.api_error_or_status())
                 })
             })
         })
-    })
-    .await
+        .await
 }
 
 /// Updates an existing user.
@@ -903,7 +884,7 @@ where
 {
     let http_client = client.http_client().clone();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
+    client.first_ok(false, move |member| {
         let body = ready(serde_json::to_string(&user).map_err(Error::from));
 
         let url = build_url(member, &format!("/users/{}", user.name));
@@ -913,31 +894,95 @@ where
 
         let http_client = http_client.clone();
 
-        let response =
-            params.and_then(move |(uri, body)| http_client.put(uri, body).map_err(Error::from));
+        let response = params.and_then(move |(uri, body)| http_client.put(uri, body));
 
         response.and_then(|response| {
-            let status = response.status();
-            let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::aggregate(response.into_body())
-                .map_ok(BufExt::reader)
-                .err_into();
-
-            body.and_then(move |body| {
-                ready(if status == StatusCode::OK {
-                    match serde_json::from_reader::<_, User>(body) {
-                        Ok(data) => Ok(Response { data, cluster_info }),
-                        Err(error) => Err(Error::Serialization(error)),
-                    }
-                } else {
-                    Err(Error::UnexpectedStatus(status))
-                })
+            ready(if response.status() == StatusCode::OK {
+                response
+                    .json::<User>()
+                    .map(|data| response.into_response(data))
+            } else {
+                Err(response.api_error_or_status())
             })
         })
     })
 }
 
+/// Grants access to a resource to each of the given roles concurrently.
+async fn grant_to_roles<C>(
+    client: &Client<C>,
+    key: &str,
+    roles: &[&str],
+    read: bool,
+    write: bool,
+) -> Vec<Result<Response<Role>, Vec<Error>>>
+where
+    C: Clone + Connect + Sync + Send + 'static,
+{
+    let updates = roles.iter().map(|role| {
+        let mut update = RoleUpdate::new(*role);
+
+        if read {
+            update.grant_kv_read_permission(key);
+        }
+
+        if write {
+            update.grant_kv_write_permission(key);
+        }
+
+        update_role(client, update)
+    });
+
+    join_all(updates).await
+}
+
 /// Constructs the full URL for an API call.
 fn build_url(endpoint: &Uri, path: &str) -> String {
     format!("{}v2/auth{}", endpoint, path)
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{Role, UserUpdate};
+
+    #[test]
+    fn test_password_only_user_update() {
+        let mut update = UserUpdate::new("rkt");
+        update.update_password("secret2");
+
+        assert_eq!(
+            serde_json::to_value(&update).unwrap(),
+            json!({"user": "rkt", "password": "secret2"})
+        );
+    }
+
+    #[test]
+    fn test_role_access() {
+        let mut role = Role::new("rkt");
+        role.grant_kv_read_permission("/rkt/*");
+        role.grant_kv_read_permission("/shared");
+        role.grant_kv_write_permission("/rkt/cache/*");
+
+        assert!(role.can_read("/rkt/foo"));
+        assert!(role.can_read("/rkt/foo/bar"));
+        assert!(role.can_read("/rkt/"));
+        assert!(!role.can_read("/rkt"));
+        assert!(role.can_read("/shared"));
+        assert!(!role.can_read("/shared/foo"));
+        assert!(!role.can_read("/other"));
+
+        assert!(role.can_write("/rkt/cache/foo"));
+        assert!(!role.can_write("/rkt/foo"));
+
+        let mut everything = Role::new("admin");
+        everything.grant_kv_write_permission("*");
+        assert!(everything.can_write("/anything"));
+        assert!(!everything.can_read("/anything"));
+
+        let root = Role::new("root");
+        assert!(root.can_read("/anything"));
+        assert!(root.can_write("/anything"));
+    }
+}
@@ -1,22 +1,36 @@
 //! Contains the etcd client. All API calls are made via the client.
 
-use bytes::buf::BufExt;
-use futures::future::ready;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use futures::future::{join_all, ready, AbortHandle, AbortRegistration, Abortable};
 use futures::prelude::*;
 use futures::stream::FuturesUnordered;
 use http::header::{HeaderMap, HeaderValue};
 use hyper::client::connect::{Connect, HttpConnector};
-use hyper::{Client as Hyper, StatusCode, Uri};
+use hyper::{Client as Hyper, Method, StatusCode, Uri};
 #[cfg(feature = "tls")]
 use hyper_tls::HttpsConnector;
 use log::error;
+#[cfg(feature = "tls")]
+use native_tls::TlsConnector;
 use serde::de::DeserializeOwned;
 use serde_derive::{Deserialize, Serialize};
-use serde_json;
+use tokio::time::delay_for;
 
-use crate::error::{ApiError, Error};
+use crate::connector::PinnedConnector;
+use crate::error::Error;
+use crate::first_ok::{first_ok, first_ok_hedged, first_ok_parallel};
 use crate::http::HttpClient;
+use crate::members;
+use crate::stats::self_stats;
 use crate::version::VersionInfo;
+use crate::watch_registry::{WatchGuard, WatchRegistry};
 
 // header! {
 //     /// The `X-Etcd-Cluster-Id` header.
@@ -42,6 +56,9 @@ const XRAFT_INDEX: &str = "X-Raft-Index";
 // }
 const XRAFT_TERM: &str = "X-Raft-Term";
 
+/// How long the result of checking for a cluster leader is reused before checking again.
+const LEADER_CHECK_TTL: Duration = Duration::from_secs(1);
+
 /// API client for etcd.
 ///
 /// All API calls require a client.
@@ -50,8 +67,21 @@ pub struct Client<C>
 where
     C: Clone + Connect + Sync + Send + 'static,
 {
-    endpoints: Vec<Uri>,
+    allow_no_ttl: bool,
+    concurrency: Concurrency,
+    endpoint_strategy: EndpointStrategy,
+    endpoints: Arc<RwLock<Vec<Uri>>>,
     http_client: HttpClient<C>,
+    leader_check: Arc<Mutex<Option<(Instant, bool)>>>,
+    next_endpoint: Arc<AtomicUsize>,
+    read_your_writes: bool,
+    redact_values: bool,
+    require_leader: bool,
+    retry_policy: RetryPolicy,
+    seed_endpoints: Vec<Uri>,
+    ttl_bounds: (Option<u64>, Option<u64>),
+    watches: Option<Arc<WatchRegistry>>,
+    written_index: Arc<AtomicU64>,
 }
 
 /// A username and password to use for HTTP basic authentication.
@@ -63,6 +93,123 @@ pub struct BasicAuth {
     pub password: String,
 }
 
+/// The credentials to authenticate to the etcd cluster with.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub enum AuthMethod {
+    /// HTTP basic authentication, as used by etcd's own authentication.
+    Basic(BasicAuth),
+    /// A bearer token, for clusters behind an authenticating proxy.
+    Bearer(String),
+    /// No authentication.
+    #[default]
+    None,
+}
+
+impl From<Option<BasicAuth>> for AuthMethod {
+    fn from(basic_auth: Option<BasicAuth>) -> Self {
+        match basic_auth {
+            Some(basic_auth) => AuthMethod::Basic(basic_auth),
+            None => AuthMethod::None,
+        }
+    }
+}
+
+/// Controls how many cluster members an API call is sent to at a time.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Concurrency {
+    /// Members are tried one at a time, in order, until one of them succeeds.
+    #[default]
+    Sequential,
+    /// Up to the given number of members are tried at a time. Whenever a request fails, the next
+    /// member is tried, and as soon as one succeeds, the requests still in flight are cancelled.
+    ///
+    /// This lowers latency when a member is slow or unreachable at the cost of extra load on the
+    /// cluster.
+    Hedged(usize),
+    /// Every member is tried at once, and as soon as one succeeds, the requests still in flight
+    /// are cancelled.
+    ///
+    /// This gives the lowest latency when members are slow or unreachable, at the cost of sending
+    /// every API call to the whole cluster.
+    Parallel,
+}
+
+/// Controls the order in which cluster members are tried for each API call.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum EndpointStrategy {
+    /// Members are tried in the order their endpoints were given to the client, so the first
+    /// member serves every API call while it is healthy.
+    #[default]
+    Ordered,
+    /// Each API call starts with the member after the one the previous call started with,
+    /// spreading the load evenly across the cluster.
+    RoundRobin,
+    /// Members are tried in a random order for each API call.
+    Random,
+}
+
+/// Controls whether an API call is retried after every cluster member has failed, and how long
+/// to wait between attempts.
+///
+/// Only transient failures are retried: connection errors, timeouts, server errors, and etcd
+/// errors caused by Raft or leader elections. An API call is not retried if any member returned
+/// another kind of error, such as a failed comparison or a permission error, since trying again
+/// would fail the same way.
+///
+/// Some transient failures leave it unclear whether etcd applied the request: a timeout, a
+/// connection lost after the request was sent, a server error, or an internal Raft error. These
+/// are only retried for API calls that have the same outcome however many times they are made,
+/// which are reads and sets that don't create the node or compare against it. Other writes, such
+/// as `kv::create`, `kv::create_in_order`, compare-and-swap, and deletes, fail instead, so that a
+/// write that was applied is not applied twice or reported as a failed comparison.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct RetryPolicy {
+    /// The maximum number of times every member is tried, including the first time. A value of
+    /// zero or one disables retries.
+    pub max_attempts: u32,
+    /// The delay before the first retry. The delay doubles for each retry after that.
+    pub base_delay: Duration,
+    /// The maximum delay between attempts.
+    pub max_delay: Duration,
+    /// Whether to wait a random duration between half the delay and the full delay, so that many
+    /// clients failing at once don't retry in lockstep.
+    pub jitter: bool,
+}
+
+impl RetryPolicy {
+    /// Returns the delay before the given retry, counting from one.
+    fn delay(&self, retry: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(retry.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        let delay = self
+            .base_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+
+        if self.jitter {
+            let random = RandomState::new().build_hasher().finish();
+
+            delay / 2 + delay / 2 * (random % 1024) as u32 / 1024
+        } else {
+            delay
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Returns a policy that never retries.
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+            jitter: true,
+        }
+    }
+}
+
 /// A value returned by the health check API endpoint to indicate a healthy cluster member.
 #[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct Health {
@@ -70,6 +217,354 @@ pub struct Health {
     pub health: String,
 }
 
+/// The results of a health check against each etcd member, returned by `Client::cluster_health`.
+#[derive(Debug)]
+pub struct ClusterHealth {
+    /// The result of the health check for each member, in the order the client's endpoints were
+    /// given.
+    pub members: Vec<MemberHealth>,
+}
+
+impl ClusterHealth {
+    /// Returns the number of members that reported themselves healthy.
+    pub fn healthy_count(&self) -> usize {
+        self.members
+            .iter()
+            .filter(|member| member.is_healthy())
+            .count()
+    }
+
+    /// Returns true if more than half of the members reported themselves healthy.
+    ///
+    /// Quorum is computed over the endpoints the client was initialized with, which should be
+    /// every member of the cluster for the result to be meaningful.
+    pub fn is_healthy(&self) -> bool {
+        self.healthy_count() > self.members.len() / 2
+    }
+}
+
+/// The result of a health check against a single etcd member.
+#[derive(Debug)]
+pub struct MemberHealth {
+    /// The endpoint of the member.
+    pub endpoint: Uri,
+    /// The member's response to the health check, or the error that occurred.
+    pub result: Result<Response<Health>, Error>,
+}
+
+impl MemberHealth {
+    /// Returns true if the member responded and reported itself healthy.
+    pub fn is_healthy(&self) -> bool {
+        match self.result {
+            Ok(ref response) => response.data.health == "true",
+            Err(_) => false,
+        }
+    }
+}
+
+/// The version information reported by a single etcd member, returned by
+/// `Client::cluster_versions`.
+#[derive(Debug)]
+pub struct MemberVersion {
+    /// The endpoint of the member.
+    pub endpoint: Uri,
+    /// The member's version information, or the error that occurred.
+    pub result: Result<Response<VersionInfo>, Error>,
+}
+
+/// Builds a `Client` whose underlying `hyper::Client` has custom connection pool and keep-alive
+/// settings, optionally with a custom User-Agent.
+///
+/// This is a shortcut for the common cases of `Client::custom`, where only the connection
+/// settings need to change.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use etcd::ClientBuilder;
+/// use hyper::header::HeaderValue;
+///
+/// let client = ClientBuilder::new(&["http://etcd.example.com:2379"])
+///     .user_agent(HeaderValue::from_static("inventory-service/1.4"))
+///     .pool_max_idle_per_host(16)
+///     .pool_idle_timeout(Some(Duration::from_secs(30)))
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct ClientBuilder {
+    #[cfg(feature = "tls")]
+    accept_invalid_certs: bool,
+    auth: AuthMethod,
+    endpoints: Vec<String>,
+    http2_keep_alive_interval: Option<Duration>,
+    pool_idle_timeout: Option<Option<Duration>>,
+    pool_max_idle_per_host: Option<usize>,
+    user_agent: Option<HeaderValue>,
+}
+
+impl ClientBuilder {
+    /// Constructs a new builder for a client of the given cluster members.
+    ///
+    /// # Parameters
+    ///
+    /// * endpoints: URLs for one or more cluster members. When making an API call, the client will
+    /// make the call to each member in order until it receives a successful response.
+    pub fn new(endpoints: &[&str]) -> Self {
+        ClientBuilder {
+            #[cfg(feature = "tls")]
+            accept_invalid_certs: false,
+            auth: AuthMethod::None,
+            endpoints: endpoints
+                .iter()
+                .map(|&endpoint| endpoint.to_owned())
+                .collect(),
+            http2_keep_alive_interval: None,
+            pool_idle_timeout: None,
+            pool_max_idle_per_host: None,
+            user_agent: None,
+        }
+    }
+
+    /// Sets the credentials to authenticate with.
+    pub fn auth(mut self, auth: AuthMethod) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Sets whether a client built with `ClientBuilder::build_https` accepts any TLS certificate,
+    /// including self-signed and expired ones.
+    ///
+    /// # Warning
+    ///
+    /// This is insecure: anyone able to intercept the connection can impersonate the cluster,
+    /// reading and changing any data sent to it. Only use this against local development clusters.
+    #[cfg(feature = "tls")]
+    pub fn danger_accept_invalid_certs(mut self, accept_invalid_certs: bool) -> Self {
+        self.accept_invalid_certs = accept_invalid_certs;
+        self
+    }
+
+    /// Sets the interval at which HTTP/2 pings are sent to keep connections alive. `None`, the
+    /// default, disables them.
+    pub fn http2_keep_alive_interval(mut self, interval: Option<Duration>) -> Self {
+        self.http2_keep_alive_interval = interval;
+        self
+    }
+
+    /// Sets how long an idle connection is kept open in the pool. `None` keeps idle connections
+    /// open indefinitely. Defaults to hyper's default of 90 seconds.
+    pub fn pool_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum number of idle connections kept open to each cluster member. There is no
+    /// limit by default.
+    ///
+    /// Each watch holds a connection while it waits, so clients with many watches may want to
+    /// limit how many of those connections are kept around once the watches end.
+    pub fn pool_max_idle_per_host(mut self, max_idle: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max_idle);
+        self
+    }
+
+    /// Sets the User-Agent header sent with each request. See `Client::set_user_agent`.
+    pub fn user_agent(mut self, user_agent: HeaderValue) -> Self {
+        self.user_agent = Some(user_agent);
+        self
+    }
+
+    /// Constructs a client using the HTTP protocol.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no endpoints were provided or if any of the endpoints is an invalid URL.
+    pub fn build(self) -> Result<Client<HttpConnector>, Error> {
+        let hyper = self.hyper_builder().build_http();
+
+        self.build_with(hyper)
+    }
+
+    /// Constructs a client using the HTTPS protocol.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no endpoints were provided, if any of the endpoints is an invalid URL, or if the
+    /// TLS connector can't be created.
+    #[cfg(feature = "tls")]
+    pub fn build_https(self) -> Result<Client<HttpsConnector<HttpConnector>>, Error> {
+        let tls = TlsConnector::builder()
+            .danger_accept_invalid_certs(self.accept_invalid_certs)
+            .build()?;
+
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+
+        let connector = HttpsConnector::from((http, tls.into()));
+        let hyper = self.hyper_builder().build(connector);
+
+        self.build_with(hyper)
+    }
+
+    // private
+
+    /// Constructs a client using the given `hyper::Client`.
+    fn build_with<C>(self, hyper: Hyper<C>) -> Result<Client<C>, Error>
+    where
+        C: Clone + Connect + Sync + Send + 'static,
+    {
+        let endpoints: Vec<&str> = self.endpoints.iter().map(String::as_str).collect();
+
+        let mut client = Client::custom(hyper, &endpoints, None)?;
+        client.set_auth(self.auth);
+
+        if let Some(user_agent) = self.user_agent {
+            client.set_user_agent(user_agent);
+        }
+
+        Ok(client)
+    }
+
+    /// Returns a `hyper::Client` builder with the configured connection settings.
+    fn hyper_builder(&self) -> hyper::client::Builder {
+        let mut builder = Hyper::builder();
+        builder.keep_alive(true);
+        builder.http2_keep_alive_interval(self.http2_keep_alive_interval);
+
+        if let Some(timeout) = self.pool_idle_timeout {
+            builder.pool_idle_timeout(timeout);
+        }
+
+        if let Some(max_idle) = self.pool_max_idle_per_host {
+            builder.pool_max_idle_per_host(max_idle);
+        }
+
+        builder
+    }
+}
+
+/// Configuration shared by several clients of the same cluster.
+///
+/// `ClientConfig::build` constructs a client from the configuration, with any connector type that
+/// implements `NewConnector`. `ClientConfig::with_auth` derives the configuration for a client of
+/// the same cluster that authenticates differently, such as a second client authenticated as the
+/// root user.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use etcd::{AuthMethod, BasicAuth, ClientConfig};
+/// use hyper::client::HttpConnector;
+///
+/// let mut config = ClientConfig::new(&["http://etcd.example.com:2379"]);
+/// config.request_timeout = Some(Duration::from_secs(5));
+///
+/// let client = config.build::<HttpConnector>().unwrap();
+/// let root_client = config
+///     .with_auth(AuthMethod::Basic(BasicAuth {
+///         username: "root".to_owned(),
+///         password: "secret".to_owned(),
+///     }))
+///     .build::<HttpConnector>()
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ClientConfig {
+    /// The credentials to authenticate with.
+    pub auth: AuthMethod,
+    /// The order in which cluster members are tried for each API call. See
+    /// `Client::set_endpoint_strategy`.
+    pub endpoint_strategy: EndpointStrategy,
+    /// URLs for one or more cluster members.
+    pub endpoints: Vec<String>,
+    /// The timeout for each request to a cluster member. See `Client::set_request_timeout`.
+    pub request_timeout: Option<Duration>,
+}
+
+impl ClientConfig {
+    /// Constructs a new configuration for clients of the given cluster members, with no
+    /// authentication, the `Ordered` endpoint strategy, and no request timeout.
+    ///
+    /// # Parameters
+    ///
+    /// * endpoints: URLs for one or more cluster members. When making an API call, the client will
+    /// make the call to each member in order until it receives a successful response.
+    pub fn new(endpoints: &[&str]) -> Self {
+        ClientConfig {
+            auth: AuthMethod::None,
+            endpoint_strategy: EndpointStrategy::Ordered,
+            endpoints: endpoints
+                .iter()
+                .map(|&endpoint| endpoint.to_owned())
+                .collect(),
+            request_timeout: None,
+        }
+    }
+
+    /// Returns a copy of this configuration that authenticates with the given credentials
+    /// instead.
+    pub fn with_auth(&self, auth: AuthMethod) -> Self {
+        ClientConfig {
+            auth,
+            ..self.clone()
+        }
+    }
+
+    /// Constructs a client with this configuration, using a new connector of type `C`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no endpoints were provided, if any of the endpoints is an invalid URL, or if the
+    /// connector can't be created.
+    pub fn build<C>(&self) -> Result<Client<C>, Error>
+    where
+        C: NewConnector,
+    {
+        let hyper = Hyper::builder().keep_alive(true).build(C::new_connector()?);
+        let endpoints: Vec<&str> = self.endpoints.iter().map(String::as_str).collect();
+
+        let mut client = Client::custom(hyper, &endpoints, None)?;
+        client.set_auth(self.auth.clone());
+        client.set_endpoint_strategy(self.endpoint_strategy);
+        client.set_request_timeout(self.request_timeout);
+
+        Ok(client)
+    }
+}
+
+/// A connector that `ClientConfig::build` can create for the clients it constructs.
+pub trait NewConnector: Clone + Connect + Sync + Send + 'static {
+    /// Constructs a connector with default settings.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the connector can't be created.
+    fn new_connector() -> Result<Self, Error>;
+}
+
+impl NewConnector for HttpConnector {
+    fn new_connector() -> Result<Self, Error> {
+        Ok(HttpConnector::new())
+    }
+}
+
+#[cfg(feature = "tls")]
+impl NewConnector for HttpsConnector<HttpConnector> {
+    fn new_connector() -> Result<Self, Error> {
+        let tls = TlsConnector::new()?;
+
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+
+        Ok(HttpsConnector::from((http, tls.into())))
+    }
+}
+
 impl Client<HttpConnector> {
     /// Constructs a new client using the HTTP protocol.
     ///
@@ -87,9 +582,137 @@ impl Client<HttpConnector> {
         endpoints: &[&str],
         basic_auth: Option<BasicAuth>,
     ) -> Result<Client<HttpConnector>, Error> {
+        Client::with_auth(endpoints, basic_auth.into())
+    }
+
+    /// Constructs a new client for a single cluster member using the HTTP protocol.
+    ///
+    /// # Parameters
+    ///
+    /// * endpoint: The URL of the cluster member.
+    /// * basic_auth: Credentials for HTTP basic authentication.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the endpoint is an invalid URL.
+    pub fn from_endpoint<U>(
+        endpoint: U,
+        basic_auth: Option<BasicAuth>,
+    ) -> Result<Client<HttpConnector>, Error>
+    where
+        U: AsRef<str>,
+    {
+        Client::new(&[endpoint.as_ref()], basic_auth)
+    }
+
+    /// Constructs a new client using the HTTP protocol from any collection of endpoints, such as
+    /// a `Vec<String>`.
+    ///
+    /// # Parameters
+    ///
+    /// * endpoints: URLs for one or more cluster members. When making an API call, the client will
+    /// make the call to each member in order until it receives a successful response.
+    /// * basic_auth: Credentials for HTTP basic authentication.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no endpoints are provided or if any of the endpoints is an invalid URL.
+    pub fn from_endpoints<I>(
+        endpoints: I,
+        basic_auth: Option<BasicAuth>,
+    ) -> Result<Client<HttpConnector>, Error>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let endpoints: Vec<I::Item> = endpoints.into_iter().collect();
+        let endpoints: Vec<&str> = endpoints.iter().map(AsRef::as_ref).collect();
+
+        Client::new(&endpoints, basic_auth)
+    }
+
+    /// Constructs a new client using the HTTP protocol and any supported kind of authentication.
+    ///
+    /// # Parameters
+    ///
+    /// * endpoints: URLs for one or more cluster members. When making an API call, the client will
+    /// make the call to each member in order until it receives a successful response.
+    /// * auth: Credentials to authenticate with.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no endpoints are provided or if any of the endpoints is an invalid URL.
+    pub fn with_auth(endpoints: &[&str], auth: AuthMethod) -> Result<Client<HttpConnector>, Error> {
         let hyper = Hyper::builder().keep_alive(true).build_http();
 
-        Client::custom(hyper, endpoints, basic_auth)
+        let mut client = Client::custom(hyper, endpoints, None)?;
+        client.set_auth(auth);
+
+        Ok(client)
+    }
+}
+
+impl Client<PinnedConnector<HttpConnector>> {
+    /// Constructs a new client using the HTTP protocol that connects to a fixed address for each
+    /// cluster member instead of resolving its host name.
+    ///
+    /// Requests still use each endpoint's host name in the `Host` header. See `PinnedConnector`.
+    ///
+    /// # Parameters
+    ///
+    /// * endpoints: URLs for one or more cluster members, each with the address to connect to for
+    /// it. When making an API call, the client will make the call to each member in order until it
+    /// receives a successful response.
+    /// * basic_auth: Credentials for HTTP basic authentication.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no endpoints are provided or if any of the endpoints is an invalid URL.
+    pub fn pinned(
+        endpoints: &[(&str, SocketAddr)],
+        basic_auth: Option<BasicAuth>,
+    ) -> Result<Client<PinnedConnector<HttpConnector>>, Error> {
+        let connector = PinnedConnector::new(HttpConnector::new(), endpoints)?;
+        let hyper = Hyper::builder().keep_alive(true).build(connector);
+        let endpoints: Vec<&str> = endpoints.iter().map(|&(endpoint, _)| endpoint).collect();
+
+        Client::custom(hyper, &endpoints, basic_auth)
+    }
+}
+
+#[cfg(feature = "tls")]
+impl Client<HttpsConnector<PinnedConnector<HttpConnector>>> {
+    /// Constructs a new client using the HTTPS protocol that connects to a fixed address for each
+    /// cluster member instead of resolving its host name.
+    ///
+    /// Requests still use each endpoint's host name in the `Host` header, and certificates are
+    /// verified against the host name, not the address. See `PinnedConnector`.
+    ///
+    /// # Parameters
+    ///
+    /// * endpoints: URLs for one or more cluster members, each with the address to connect to for
+    /// it. When making an API call, the client will make the call to each member in order until it
+    /// receives a successful response.
+    /// * basic_auth: Credentials for HTTP basic authentication.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no endpoints are provided, if any of the endpoints is an invalid URL, or if the
+    /// TLS connector can't be created.
+    pub fn https_pinned(
+        endpoints: &[(&str, SocketAddr)],
+        basic_auth: Option<BasicAuth>,
+    ) -> Result<Client<HttpsConnector<PinnedConnector<HttpConnector>>>, Error> {
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+
+        let pinned = PinnedConnector::new(http, endpoints)?;
+        let tls = TlsConnector::new()?;
+        let connector = HttpsConnector::from((pinned, tls.into()));
+        let hyper = Hyper::builder().keep_alive(true).build(connector);
+        let endpoints: Vec<&str> = endpoints.iter().map(|&(endpoint, _)| endpoint).collect();
+
+        Client::custom(hyper, &endpoints, basic_auth)
     }
 }
 
@@ -200,12 +823,30 @@ where
         let mut uri_endpoints = Vec::with_capacity(endpoints.len());
 
         for endpoint in endpoints {
-            uri_endpoints.push(endpoint.parse()?);
+            let uri = endpoint.parse().map_err(|source| Error::InvalidEndpoint {
+                endpoint: (*endpoint).to_owned(),
+                source,
+            })?;
+
+            uri_endpoints.push(normalize_endpoint(uri));
         }
 
         Ok(Client {
-            endpoints: uri_endpoints,
-            http_client: HttpClient::new(hyper, basic_auth),
+            allow_no_ttl: true,
+            concurrency: Concurrency::default(),
+            endpoint_strategy: EndpointStrategy::default(),
+            endpoints: Arc::new(RwLock::new(uri_endpoints.clone())),
+            http_client: HttpClient::new(hyper, basic_auth.into()),
+            leader_check: Arc::new(Mutex::new(None)),
+            next_endpoint: Arc::new(AtomicUsize::new(0)),
+            read_your_writes: false,
+            redact_values: true,
+            require_leader: false,
+            retry_policy: RetryPolicy::default(),
+            seed_endpoints: uri_endpoints,
+            ttl_bounds: (None, None),
+            watches: None,
+            written_index: Arc::new(AtomicU64::new(0)),
         })
     }
 
@@ -215,123 +856,795 @@ where
     }
 
     /// Lets other internal code access the cluster endpoints.
-    pub(crate) fn endpoints(&self) -> &[Uri] {
-        &self.endpoints
+    pub(crate) fn endpoints(&self) -> Vec<Uri> {
+        self.endpoints.read().unwrap().clone()
     }
 
-    /// Runs a basic health check against each etcd member.
-    pub fn health(&self) -> impl Stream<Item = Result<Response<Health>, Error>> + Send {
-        self.endpoints.iter().map(|endpoint| {
-            let url = build_url(&endpoint, "health");
-            let uri = ready(url.parse()).err_into();
-            let cloned_client = self.http_client.clone();
-            let response = uri.and_then(move |uri| cloned_client.get(uri).map_err(Error::from));
-            response.and_then(|response| {
-                let status = response.status();
-                let cluster_info = ClusterInfo::from(response.headers());
-                let body = hyper::body::aggregate(response.into_body())
-                    .map_ok(BufExt::reader)
-                    .err_into();
-
-                body.and_then(move |body| async move {
-                    if status == StatusCode::OK {
-                        match serde_json::from_reader::<_, Health>(body) {
-                            Ok(data) => Ok(Response { data, cluster_info }),
-                            Err(error) => Err(Error::Serialization(error)),
-                        }
-                    } else {
-                        match serde_json::from_reader::<_, ApiError>(body) {
-                            Ok(error) => Err(Error::Api(error)),
-                            Err(error) => Err(Error::Serialization(error)),
-                        }
-                    }
-                })
-            })
-            })
-            .collect::<FuturesUnordered<_>>()
+    /// Lets other internal code fail fast when the cluster has no leader, if the client requires
+    /// one.
+    ///
+    /// The cluster has a leader if any member reports one in its statistics. The result is reused
+    /// for a short time so that a burst of writes doesn't cause a burst of statistics requests.
+    pub(crate) async fn ensure_leader(&self) -> Result<(), Vec<Error>> {
+        if !self.require_leader {
+            return Ok(());
+        }
+
+        let cached = *self.leader_check.lock().unwrap();
+
+        let has_leader = match cached {
+            Some((checked_at, has_leader)) if checked_at.elapsed() < LEADER_CHECK_TTL => has_leader,
+            _ => {
+                let results: Vec<_> = self_stats(self).collect().await;
+
+                if results.iter().all(Result::is_err) {
+                    return Err(results.into_iter().filter_map(Result::err).collect());
+                }
+
+                let has_leader = results.into_iter().filter_map(Result::ok).any(|response| {
+                    !response.data.leader_info.id.is_empty()
+                        && response.data.state != "StateCandidate"
+                });
+
+                *self.leader_check.lock().unwrap() = Some((Instant::now(), has_leader));
+
+                has_leader
+            }
+        };
+
+        if has_leader {
+            Ok(())
+        } else {
+            Err(vec![Error::NoLeader])
+        }
     }
 
-    /// Returns version information from each etcd cluster member the client was initialized with.
-    pub fn versions(&self) -> impl Stream<Item = Result<Response<VersionInfo>, Error>> + Send {
-        self.endpoints.iter().map(|endpoint| {
-            let url = build_url(&endpoint, "version");
-            let uri = ready(url.parse()).err_into();
-            let cloned_client = self.http_client.clone();
-            let response = uri.and_then(move |uri| cloned_client.get(uri).err_into());
-            response.and_then(|response| {
-                let status = response.status();
-                let cluster_info = ClusterInfo::from(response.headers());
-                let body = hyper::body::aggregate(response.into_body())
-                    .err_into()
-                    .map_ok(BufExt::reader);
-
-                body.and_then(move |body| async move {
-                    if status == StatusCode::OK {
-                        match serde_json::from_reader::<_, VersionInfo>(body) {
-                            Ok(data) => Ok(Response { data, cluster_info }),
-                            Err(error) => Err(Error::Serialization(error)),
-                        }
-                    } else {
-                        match serde_json::from_reader::<_, ApiError>(body) {
-                            Ok(error) => Err(Error::Api(error)),
-                            Err(error) => Err(Error::Serialization(error)),
-                        }
-                    }
-                })
-            })
-            })
-            .collect::<FuturesUnordered<_>>()
+    /// Lets other internal code record the etcd index of a successful write, so that later reads
+    /// can be made to observe it.
+    pub(crate) fn record_write(&self, cluster_info: &ClusterInfo) {
+        if let (true, Some(index)) = (self.read_your_writes, cluster_info.etcd_index) {
+            self.written_index.fetch_max(index, Ordering::SeqCst);
+        }
     }
 
-    /// Lets other internal code make basic HTTP requests.
-    pub(crate) async fn request<U, T>(
+    /// Lets other internal code find the etcd index a member must have reached to serve a read.
+    pub(crate) fn required_read_index(&self) -> Option<u64> {
+        if !self.read_your_writes {
+            return None;
+        }
+
+        match self.written_index.load(Ordering::SeqCst) {
+            0 => None,
+            index => Some(index),
+        }
+    }
+
+    /// Lets other internal code check the TTL of a write against the client's TTL bounds before
+    /// sending it.
+    ///
+    /// A TTL of zero is treated the same as no TTL.
+    pub(crate) fn check_ttl(&self, ttl: Option<u64>) -> Result<(), Error> {
+        let (min, max) = self.ttl_bounds;
+
+        let allowed = match ttl {
+            None | Some(0) => self.allow_no_ttl,
+            Some(ttl) => min.is_none_or(|min| ttl >= min) && max.is_none_or(|max| ttl <= max),
+        };
+
+        if allowed {
+            Ok(())
+        } else {
+            Err(Error::TtlOutOfBounds {
+                min,
+                max,
+                requested: ttl.filter(|&ttl| ttl != 0),
+            })
+        }
+    }
+
+    /// Lets other internal code register a watch so it can be cancelled by `cancel_all_watches`.
+    ///
+    /// If the client doesn't track watches, no guard is returned and the registration is never
+    /// aborted.
+    pub(crate) fn register_watch(&self) -> (Option<WatchGuard>, AbortRegistration) {
+        match self.watches {
+            Some(ref watches) => {
+                let (guard, registration) = watches.register();
+
+                (Some(guard), registration)
+            }
+            None => (None, AbortHandle::new_pair().1),
+        }
+    }
+
+    /// Lets other internal code make a request to the cluster members until one of them succeeds,
+    /// according to the client's `Concurrency`, retrying according to its `RetryPolicy`.
+    ///
+    /// `idempotent` says whether making the request twice has the same outcome as making it once.
+    /// If it doesn't, the request is not retried after an error that leaves it unclear whether
+    /// etcd applied it. If every attempt fails, the errors from all of them are returned.
+    pub(crate) fn first_ok<F, G, T>(
         &self,
-        uri: U,
-    ) -> Result<Response<T>, Error>
+        idempotent: bool,
+        mut callback: F,
+    ) -> impl Future<Output = Result<T, Vec<Error>>>
     where
-        U: Future<Output = Result<Uri, Error>> + Send,
-        T: DeserializeOwned + Send + 'static,
+        F: FnMut(&Uri) -> G,
+        G: Future<Output = Result<T, Error>>,
     {
-        let http_client = self.http_client.clone();
-        let response = uri.and_then(move |uri| http_client.get(uri).err_into());
-        response.and_then(|response| {
-            let status = response.status();
-            let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::aggregate(response.into_body())
-                .err_into()
-                .map_ok(BufExt::reader);
-
-            body.and_then(move |body| async move {
-                if status == StatusCode::OK {
-                    match serde_json::from_reader::<_, T>(body) {
-                        Ok(data) => Ok(Response { data, cluster_info }),
-                        Err(error) => Err(Error::Serialization(error)),
+        let endpoints = self.ordered_endpoints();
+        let concurrency = self.concurrency;
+        let retry_policy = self.retry_policy;
+
+        async move {
+            let mut errors = Vec::new();
+            let mut attempt = 1;
+
+            loop {
+                let endpoints = endpoints.clone();
+                let callback = &mut callback;
+
+                let result = match concurrency {
+                    Concurrency::Sequential => first_ok(endpoints, callback).await,
+                    Concurrency::Hedged(n) => first_ok_hedged(endpoints, n, callback).await,
+                    Concurrency::Parallel => first_ok_parallel(endpoints, callback).await,
+                };
+
+                match result {
+                    Ok(value) => {
+                        #[cfg(feature = "metrics")]
+                        metrics::counter!("etcd_client_operations_total", "outcome" => "success")
+                            .increment(1);
+
+                        return Ok(value);
                     }
+                    Err(attempt_errors) => {
+                        let retryable = attempt_errors.iter().all(|error| {
+                            error.is_retryable() && (idempotent || !error.may_have_been_applied())
+                        });
+
+                        errors.extend(attempt_errors);
+
+                        if !retryable || attempt >= retry_policy.max_attempts {
+                            #[cfg(feature = "metrics")]
+                            metrics::counter!(
+                                "etcd_client_operations_total",
+                                "outcome" => "failure",
+                            )
+                            .increment(1);
+
+                            return Err(errors);
+                        }
+                    }
+                }
+
+                #[cfg(feature = "metrics")]
+                metrics::counter!("etcd_client_operation_retries_total").increment(1);
+
+                delay_for(retry_policy.delay(attempt)).await;
+
+                attempt += 1;
+            }
+        }
+    }
+
+    /// Returns the endpoints in the order they should be tried for the next API call, according
+    /// to the client's `EndpointStrategy`.
+    fn ordered_endpoints(&self) -> Vec<Uri> {
+        let mut endpoints = self.endpoints();
+
+        match self.endpoint_strategy {
+            EndpointStrategy::Ordered => {}
+            EndpointStrategy::RoundRobin => {
+                let start = self.next_endpoint.fetch_add(1, Ordering::Relaxed) % endpoints.len();
+
+                endpoints.rotate_left(start);
+            }
+            EndpointStrategy::Random => {
+                // Each `RandomState` is seeded differently, which is random enough to spread load.
+                let mut hasher = RandomState::new().build_hasher();
+
+                for i in (1..endpoints.len()).rev() {
+                    hasher.write_usize(i);
+                    endpoints.swap(i, hasher.finish() as usize % (i + 1));
+                }
+            }
+        }
+
+        endpoints
+    }
+
+    /// Returns how many cluster members an API call is sent to at a time.
+    pub fn concurrency(&self) -> Concurrency {
+        self.concurrency
+    }
+
+    /// Sets how many cluster members an API call is sent to at a time.
+    ///
+    /// The default is `Concurrency::Sequential`.
+    pub fn set_concurrency(&mut self, concurrency: Concurrency) {
+        self.concurrency = concurrency;
+    }
+
+    /// Returns the credentials used to authenticate to the cluster.
+    pub fn auth(&self) -> &AuthMethod {
+        self.http_client.auth()
+    }
+
+    /// Sets the credentials used to authenticate to the cluster.
+    ///
+    /// This allows authentication other than HTTP basic authentication with clients constructed
+    /// by `Client::https` or `Client::custom`.
+    pub fn set_auth(&mut self, auth: AuthMethod) {
+        self.http_client.set_auth(auth);
+    }
+
+    /// Returns the headers added to each request to a cluster member.
+    pub fn default_headers(&self) -> &HeaderMap {
+        self.http_client.default_headers()
+    }
+
+    /// Sets the headers added to each request to a cluster member, such as a request ID or
+    /// tracing headers for correlating requests across services.
+    ///
+    /// The headers the client sets itself take precedence: `Authorization` when HTTP basic
+    /// authentication is used, and `Content-Type` on requests with a body, since etcd only
+    /// accepts form-encoded bodies. A default header with either name is only sent on requests
+    /// where the client doesn't set it.
+    ///
+    /// There are no default headers by default.
+    pub fn set_default_headers(&mut self, default_headers: HeaderMap) {
+        self.http_client.set_default_headers(default_headers);
+    }
+
+    /// Returns the User-Agent header sent with each request to a cluster member.
+    pub fn user_agent(&self) -> &HeaderValue {
+        self.http_client.user_agent()
+    }
+
+    /// Sets the User-Agent header sent with each request to a cluster member, so that the
+    /// application making the requests can be identified in etcd's logs.
+    ///
+    /// A `User-Agent` default header takes precedence over this. Defaults to
+    /// `rust-etcd/<version>`, where `<version>` is the version of this crate.
+    pub fn set_user_agent(&mut self, user_agent: HeaderValue) {
+        self.http_client.set_user_agent(user_agent);
+    }
+
+    /// Returns whether the client follows redirects from one cluster member to another.
+    pub fn follow_redirects(&self) -> bool {
+        self.http_client.follow_redirects()
+    }
+
+    /// Sets whether the client follows redirects from one cluster member to another.
+    ///
+    /// Some operations, such as adding a member to the cluster, must be handled by the leader,
+    /// and a follower may answer them with a redirect to the leader. When redirects are followed,
+    /// a write answered with a 307 or 308 redirect is sent again to the URL in the redirect's
+    /// `Location` header, up to five times. Its credentials are only sent along while the URL has
+    /// the same scheme, host and port as the one redirected from. Any other redirect, and any
+    /// redirect while they aren't followed, fails the request with `Error::Redirect` so the caller
+    /// can retry against the leader itself.
+    ///
+    /// Redirects are not followed by default.
+    pub fn set_follow_redirects(&mut self, follow_redirects: bool) {
+        self.http_client.set_follow_redirects(follow_redirects);
+    }
+
+    /// Returns the timeout applied to each request to a cluster member.
+    pub fn request_timeout(&self) -> Option<Duration> {
+        self.http_client.request_timeout()
+    }
+
+    /// Sets the timeout applied to each request to a cluster member.
+    ///
+    /// The timeout covers the whole request, from connecting to the member to reading the full
+    /// response body. A request that takes longer fails with `Error::Timeout`, and the API call
+    /// moves on to the next member like it would for any other failure. Watches are not subject
+    /// to this timeout, since they wait for a change for as long as it takes; use
+    /// `kv::WatchOptions::timeout` for those instead.
+    ///
+    /// There is no timeout by default.
+    pub fn set_request_timeout(&mut self, request_timeout: Option<Duration>) {
+        self.http_client.set_request_timeout(request_timeout);
+    }
+
+    /// Returns the policy for retrying API calls after every cluster member has failed.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy
+    }
+
+    /// Sets the policy for retrying API calls after every cluster member has failed.
+    ///
+    /// When an API call fails with every member and all of the failures are transient, every
+    /// member is tried again after a delay, up to `RetryPolicy::max_attempts` times in total. If
+    /// every attempt fails, the API call fails with the errors from all of the attempts.
+    ///
+    /// By default, API calls are not retried.
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    /// Returns the order in which cluster members are tried for each API call.
+    pub fn endpoint_strategy(&self) -> EndpointStrategy {
+        self.endpoint_strategy
+    }
+
+    /// Sets the order in which cluster members are tried for each API call.
+    ///
+    /// The round-robin position is shared with the client's clones, so concurrent API calls made
+    /// through any of them are spread evenly. This is best set right after constructing the
+    /// client.
+    ///
+    /// The default is `EndpointStrategy::Ordered`.
+    pub fn set_endpoint_strategy(&mut self, endpoint_strategy: EndpointStrategy) {
+        self.endpoint_strategy = endpoint_strategy;
+    }
+
+    /// Returns whether `ClusterInfo` is parsed from the headers of each response.
+    pub fn track_cluster_info(&self) -> bool {
+        self.http_client.track_cluster_info()
+    }
+
+    /// Sets whether `ClusterInfo` is parsed from the headers of each response.
+    ///
+    /// Tracking is enabled by default. Disabling it skips parsing the `X-Etcd-*` and `X-Raft-*`
+    /// headers of every response, for applications that never look at cluster metadata. When
+    /// disabled, every `Response` carries `ClusterInfo::default()`, so its `etcd_index`,
+    /// `raft_index`, `raft_term`, and `cluster_id` are all `None`. Callers that track
+    /// indexes should fall back to the `modified_index` of returned nodes instead.
+    pub fn set_track_cluster_info(&mut self, track_cluster_info: bool) {
+        self.http_client.set_track_cluster_info(track_cluster_info);
+    }
+
+    /// Makes an arbitrary HTTP request to the cluster members, for endpoints this crate doesn't
+    /// otherwise support.
+    ///
+    /// The request is made in the same way as every other API call, using the client's
+    /// credentials, TLS configuration, and `Concurrency`. `path` is appended to the base URL of
+    /// each member endpoint, so a path of `v2/keys/foo` is requested as
+    /// `http://etcd.example.com:2379/v2/keys/foo`. A body, if given, is sent with a content type of
+    /// `application/x-www-form-urlencoded`.
+    ///
+    /// The response is returned as-is, whatever its status code. Only a failure to get a response
+    /// at all causes the request to be retried against the next member.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the URL cannot be constructed or if no member returns a response.
+    pub async fn raw_request(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<String>,
+    ) -> Result<(StatusCode, Bytes, ClusterInfo), Vec<Error>> {
+        let http_client = self.http_client.clone();
+        let path = path.trim_start_matches('/').to_owned();
+
+        self.first_ok(method.is_safe(), move |endpoint| {
+            let url = build_url(endpoint, &path);
+            let uri = ready(url.parse()).err_into();
+            let http_client = http_client.clone();
+            let method = method.clone();
+            let body = body.clone();
+
+            uri.and_then(move |uri| http_client.raw(method, uri, body))
+                .map_ok(|response| response.into_parts())
+        })
+        .await
+    }
+
+    /// Returns whether reads are guaranteed to observe the client's own earlier writes.
+    pub fn read_your_writes(&self) -> bool {
+        self.read_your_writes
+    }
+
+    /// Sets whether reads are guaranteed to observe the client's own earlier writes.
+    ///
+    /// Without this, a read that follows a write may be served by a member that hasn't applied
+    /// the write yet, for example after failing over to another member. When this is enabled, the
+    /// client remembers the highest etcd index returned by its writes, which is shared with its
+    /// clones. A read served by a member whose `X-Etcd-Index` is lower than that is discarded and
+    /// retried against the next member, failing with `Error::StaleRead` if no member has caught
+    /// up.
+    ///
+    /// This adds no latency while the member serving a read is up to date, but a lagging member
+    /// costs an extra round trip to another member. Watches are not affected. The check relies on
+    /// cluster information from response headers, so it has no effect if
+    /// `Client::set_track_cluster_info` has disabled it.
+    ///
+    /// This is disabled by default.
+    pub fn set_read_your_writes(&mut self, read_your_writes: bool) {
+        self.read_your_writes = read_your_writes;
+    }
+
+    /// Returns whether values are redacted from logged request bodies.
+    pub fn redact_values(&self) -> bool {
+        self.redact_values
+    }
+
+    /// Sets whether values are redacted from logged request bodies.
+    ///
+    /// With the `tracing` feature enabled, write operations emit a `debug` event with the target
+    /// URI and the form-encoded parameters sent to etcd. Since values may contain secrets, the
+    /// `value` and `prevValue` parameters are replaced with `[redacted]` unless this is disabled.
+    ///
+    /// Redaction is enabled by default.
+    pub fn set_redact_values(&mut self, redact_values: bool) {
+        self.redact_values = redact_values;
+    }
+
+    /// Returns whether write operations check that the cluster has a leader first.
+    pub fn require_leader(&self) -> bool {
+        self.require_leader
+    }
+
+    /// Sets whether write operations check that the cluster has a leader first.
+    ///
+    /// While the cluster is electing a leader, writes can fail in confusing ways. When this is
+    /// enabled, the members' statistics are checked before each write to the key-value API, and
+    /// the write fails immediately with `Error::NoLeader` if no member reports a leader. The
+    /// result of the check is reused for one second.
+    ///
+    /// This is disabled by default.
+    pub fn set_require_leader(&mut self, require_leader: bool) {
+        self.require_leader = require_leader;
+    }
+
+    /// Returns the minimum and maximum TTL in seconds allowed for writes.
+    pub fn ttl_bounds(&self) -> (Option<u64>, Option<u64>) {
+        self.ttl_bounds
+    }
+
+    /// Sets the minimum and maximum TTL in seconds allowed for writes, to enforce a policy on
+    /// expiring keys before anything is sent to etcd.
+    ///
+    /// Every key-value API call that sets or updates a node with a TTL, including `kv::set`,
+    /// `kv::create`, `kv::update`, `kv::compare_and_swap`, `kv::refresh`, and their directory
+    /// counterparts, fails with `Error::TtlOutOfBounds` if the TTL is below `min` or above `max`.
+    /// A bound of `None` is not enforced. Writes without a TTL are governed by
+    /// `Client::set_allow_no_ttl` instead.
+    ///
+    /// There are no bounds by default.
+    pub fn set_ttl_bounds(&mut self, min: Option<u64>, max: Option<u64>) {
+        self.ttl_bounds = (min, max);
+    }
+
+    /// Returns whether writes without a TTL are allowed.
+    pub fn allow_no_ttl(&self) -> bool {
+        self.allow_no_ttl
+    }
+
+    /// Sets whether writes without a TTL, or with a TTL of zero, are allowed.
+    ///
+    /// Such writes create nodes that never expire, so they are not subject to the bounds set with
+    /// `Client::set_ttl_bounds`. When this is disabled, they fail with `Error::TtlOutOfBounds`
+    /// instead.
+    ///
+    /// Writes without a TTL are allowed by default.
+    pub fn set_allow_no_ttl(&mut self, allow_no_ttl: bool) {
+        self.allow_no_ttl = allow_no_ttl;
+    }
+
+    /// Forgets everything the client has learned about the cluster, so that the next request
+    /// starts from a clean slate.
+    ///
+    /// This is meant to be called after the process may have been partitioned from the cluster
+    /// for a while, or when the endpoints may now belong to a different cluster. It clears:
+    ///
+    /// * the cached result of the leader check used by `Client::set_require_leader`, so the next
+    ///   write checks for a leader again.
+    /// * the etcd index of the latest write used by `Client::set_read_your_writes`, so reads no
+    ///   longer require members to have caught up to writes made before the reset.
+    ///
+    /// The client has no other cached state: it doesn't remember which member last answered, nor
+    /// the cluster ID or Raft term it saw, and it doesn't cache reads. The order in which members
+    /// are tried only depends on the `EndpointStrategy`. Active watch streams are not affected;
+    /// use `Client::cancel_all_watches` to stop them.
+    ///
+    /// The state is shared with the client's clones, so they are reset as well.
+    pub fn reset_state(&self) {
+        *self.leader_check.lock().unwrap() = None;
+        self.written_index.store(0, Ordering::SeqCst);
+    }
+
+    /// Returns the number of active watch streams started with this client or its clones.
+    ///
+    /// Always returns zero unless watch tracking is enabled with `Client::set_track_watches`.
+    pub fn active_watches(&self) -> usize {
+        self.watches.as_ref().map_or(0, |watches| watches.len())
+    }
+
+    /// Cancels every active watch stream started with this client or its clones, which is useful
+    /// for shutting down gracefully without leaving long-polling requests behind.
+    ///
+    /// Each cancelled stream ends the next time it is polled, dropping any request in flight.
+    /// Has no effect unless watch tracking is enabled with `Client::set_track_watches`.
+    pub fn cancel_all_watches(&self) {
+        if let Some(ref watches) = self.watches {
+            watches.cancel_all();
+        }
+    }
+
+    /// Sets whether the client keeps track of the streams returned by `kv::watch_stream`, so they
+    /// can be counted with `Client::active_watches` and cancelled with
+    /// `Client::cancel_all_watches`.
+    ///
+    /// A stream is tracked from the time it is created until it is dropped or cancelled. Only
+    /// streams created while tracking is enabled are tracked. Clones of the client made while
+    /// tracking is enabled share the same set of watches.
+    ///
+    /// Tracking is disabled by default.
+    pub fn set_track_watches(&mut self, track_watches: bool) {
+        if !track_watches {
+            self.watches = None;
+        } else if self.watches.is_none() {
+            self.watches = Some(Arc::new(WatchRegistry::default()));
+        }
+    }
+
+    /// Runs a basic health check against each etcd member.
+    ///
+    /// The stream yields one item per member, in the order the members respond. A member that
+    /// can't be reached or returns an error yields an `Err` item of its own, and the stream goes on
+    /// to yield the other members' results. Collecting the stream with `TryStreamExt::try_collect`
+    /// stops at the first error, so use `StreamExt::collect` or `Client::cluster_health` to see
+    /// the outcome for every member.
+    pub fn health(&self) -> impl Stream<Item = Result<Response<Health>, Error>> + Send {
+        self.endpoints()
+            .into_iter()
+            .map(|endpoint| {
+                let url = build_url(&endpoint, "health");
+                let uri = ready(url.parse()).err_into();
+                let cloned_client = self.http_client.clone();
+                let response = uri.and_then(move |uri| cloned_client.get(uri));
+                response.and_then(|response| {
+                    ready(if response.status() == StatusCode::OK {
+                        response
+                            .json::<Health>()
+                            .map(|data| response.into_response(data))
+                    } else {
+                        Err(response.api_error_or_status())
+                    })
+                })
+            })
+            .collect::<FuturesUnordered<_>>()
+    }
+
+    /// Runs a basic health check against each etcd member, returning the result for each member.
+    ///
+    /// Unlike `Client::health`, the results are returned together, in the order the client's
+    /// endpoints were given, along with the endpoint each came from.
+    pub async fn cluster_health(&self) -> ClusterHealth {
+        let checks = self.endpoints().into_iter().map(|endpoint| {
+            let uri = ready(build_url(&endpoint, "health").parse()).err_into();
+
+            self.request::<_, Health>(uri)
+                .map(move |result| MemberHealth { endpoint, result })
+        });
+
+        ClusterHealth {
+            members: join_all(checks).await,
+        }
+    }
+
+    /// Returns whether a quorum of the etcd members the client was initialized with are healthy.
+    ///
+    /// A member that can't be reached or returns an error counts as unhealthy. Use
+    /// `Client::cluster_health` to find out which members are unhealthy.
+    ///
+    /// # Errors
+    ///
+    /// Fails only if every member returned an error, since then the health of the cluster is
+    /// unknown rather than known to be bad.
+    pub async fn cluster_healthy(&self) -> Result<bool, Vec<Error>> {
+        let health = self.cluster_health().await;
+
+        if health.members.iter().all(|member| member.result.is_err()) {
+            return Err(health
+                .members
+                .into_iter()
+                .filter_map(|member| member.result.err())
+                .collect());
+        }
+
+        Ok(health.is_healthy())
+    }
+
+    /// Returns version information from each etcd member, along with the endpoint each came from.
+    ///
+    /// Unlike `Client::versions`, the results are returned together, in the order the client's
+    /// endpoints were given.
+    pub async fn cluster_versions(&self) -> Vec<MemberVersion> {
+        let requests = self.endpoints().into_iter().map(|endpoint| {
+            let uri = ready(build_url(&endpoint, "version").parse()).err_into();
+
+            self.request::<_, VersionInfo>(uri)
+                .map(move |result| MemberVersion { endpoint, result })
+        });
+
+        join_all(requests).await
+    }
+
+    /// Returns version information from the first etcd cluster member to respond successfully.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no member returned version information, with the errors from every attempt.
+    pub async fn version(&self) -> Result<Response<VersionInfo>, Vec<Error>> {
+        let http_client = self.http_client.clone();
+
+        self.first_ok(true, move |endpoint| {
+            let url = build_url(endpoint, "version");
+            let uri = ready(url.parse()).err_into();
+            let http_client = http_client.clone();
+            let response = uri.and_then(move |uri| http_client.get(uri));
+
+            response.and_then(|response| {
+                ready(if response.status() == StatusCode::OK {
+                    response
+                        .json::<VersionInfo>()
+                        .map(|data| response.into_response(data))
                 } else {
-                    match serde_json::from_reader::<_, ApiError>(body) {
-                        Ok(error) => Err(Error::Api(error)),
-                        Err(error) => Err(Error::Serialization(error)),
+                    Err(response.api_error_or_status())
+                })
+            })
+        })
+        .await
+    }
+
+    /// Returns version information from each etcd cluster member the client was initialized with.
+    ///
+    /// As with `Client::health`, each member yields its own item, in the order the members
+    /// respond, and an error from one member doesn't end the stream. Use `StreamExt::collect` or
+    /// `Client::cluster_versions` to see the outcome for every member.
+    pub fn versions(&self) -> impl Stream<Item = Result<Response<VersionInfo>, Error>> + Send {
+        self.endpoints()
+            .into_iter()
+            .map(|endpoint| {
+                let url = build_url(&endpoint, "version");
+                let uri = ready(url.parse()).err_into();
+                let cloned_client = self.http_client.clone();
+                let response = uri.and_then(move |uri| cloned_client.get(uri));
+                response.and_then(|response| {
+                    ready(if response.status() == StatusCode::OK {
+                        response
+                            .json::<VersionInfo>()
+                            .map(|data| response.into_response(data))
+                    } else {
+                        Err(response.api_error_or_status())
+                    })
+                })
+            })
+            .collect::<FuturesUnordered<_>>()
+    }
+
+    /// Replaces the client's endpoints with the client URLs of the cluster's current members.
+    ///
+    /// Endpoints the client was created with are kept, after the members' URLs, even if they
+    /// aren't in the member list, so that the client can still reach the cluster if the member
+    /// list turns out to be wrong. The endpoints are shared with any clones of the client.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the member list can't be retrieved or a member has an invalid client URL, in which
+    /// case the endpoints are left unchanged.
+    pub async fn refresh_endpoints(&self) -> Result<(), Vec<Error>> {
+        let members = members::list(self).await?.data;
+        let mut endpoints = Vec::new();
+
+        for url in members.into_iter().flat_map(|member| member.client_urls) {
+            let uri = url.parse().map_err(|source| {
+                vec![Error::InvalidEndpoint {
+                    endpoint: url.clone(),
+                    source,
+                }]
+            })?;
+            let uri = normalize_endpoint(uri);
+
+            if !endpoints.contains(&uri) {
+                endpoints.push(uri);
+            }
+        }
+
+        for uri in &self.seed_endpoints {
+            if !endpoints.contains(uri) {
+                endpoints.push(uri.clone());
+            }
+        }
+
+        *self.endpoints.write().unwrap() = endpoints;
+
+        Ok(())
+    }
+
+    /// Spawns a task on the Tokio runtime that calls `Client::refresh_endpoints` every
+    /// `interval`, returning a handle that stops the task when aborted.
+    ///
+    /// A failed refresh is logged and leaves the endpoints unchanged until the next one.
+    pub fn spawn_endpoint_refresh(&self, interval: Duration) -> AbortHandle {
+        let client = self.clone();
+        let (handle, registration) = AbortHandle::new_pair();
+
+        let task = async move {
+            loop {
+                delay_for(interval).await;
+
+                if let Err(errors) = client.refresh_endpoints().await {
+                    for error in errors {
+                        error!("failed to refresh etcd endpoints: {}", error);
                     }
                 }
+            }
+        };
+
+        tokio::spawn(Abortable::new(task, registration));
+
+        handle
+    }
+
+    /// Lets other internal code make basic HTTP requests.
+    pub(crate) async fn request<U, T>(&self, uri: U) -> Result<Response<T>, Error>
+    where
+        U: Future<Output = Result<Uri, Error>> + Send,
+        T: DeserializeOwned + Send + 'static,
+    {
+        let http_client = self.http_client.clone();
+        let response = uri.and_then(move |uri| http_client.get(uri));
+        response
+            .and_then(|response| {
+                ready(if response.status() == StatusCode::OK {
+                    response
+                        .json::<T>()
+                        .map(|data| response.into_response(data))
+                } else {
+                    Err(response.api_error_or_status())
+                })
             })
-        }).await
+            .await
     }
 }
 
 /// A wrapper type returned by all API calls.
 ///
 /// Contains the primary data of the response along with information about the cluster extracted
-/// from the HTTP response headers.
+/// from the HTTP response headers, and the HTTP status and headers themselves.
 #[derive(Clone, Debug)]
 pub struct Response<T> {
     /// Information about the state of the cluster.
     pub cluster_info: ClusterInfo,
     /// The primary data of the response.
     pub data: T,
+    /// The HTTP headers of the response, including any that `cluster_info` doesn't model, such as
+    /// `Date` or headers added by a proxy.
+    pub headers: HeaderMap,
+    /// Whether the data may be stale because a strongly consistent read could not be made.
+    ///
+    /// This is only ever true for reads that opted in to `kv::GetOptions::fallback_to_stale`.
+    pub is_stale: bool,
+    /// The HTTP status code of the response.
+    pub status: StatusCode,
+}
+
+impl<T> Response<T> {
+    /// Constructs a new `Response` from its parts.
+    ///
+    /// API calls construct responses themselves, so this is mainly useful for fabricating
+    /// responses when testing code that consumes them. The response has the status `200 OK` and
+    /// no headers.
+    pub fn new(data: T, cluster_info: ClusterInfo) -> Self {
+        Response {
+            cluster_info,
+            data,
+            headers: HeaderMap::new(),
+            is_stale: false,
+            status: StatusCode::OK,
+        }
+    }
 }
 
 /// Information about the state of the etcd cluster from an API response's HTTP headers.
-#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct ClusterInfo {
     /// An internal identifier for the cluster.
     pub cluster_id: Option<String>,
@@ -343,6 +1656,48 @@ pub struct ClusterInfo {
     pub raft_term: Option<u64>,
 }
 
+impl ClusterInfo {
+    /// Constructs a new `ClusterInfo` from its parts.
+    ///
+    /// API calls extract this information from the HTTP response headers, so this is mainly useful
+    /// for fabricating responses when testing code that consumes them.
+    pub fn new(
+        cluster_id: Option<String>,
+        etcd_index: Option<u64>,
+        raft_index: Option<u64>,
+        raft_term: Option<u64>,
+    ) -> Self {
+        ClusterInfo {
+            cluster_id,
+            etcd_index,
+            raft_index,
+            raft_term,
+        }
+    }
+
+    /// Returns the internal identifier for the cluster, from the `X-Etcd-Cluster-Id` header.
+    pub fn cluster_id(&self) -> Option<&str> {
+        self.cluster_id.as_deref()
+    }
+
+    /// Returns the etcd index at the time of the response, from the `X-Etcd-Index` header.
+    ///
+    /// To watch for changes made after a read, watch from this index plus one.
+    pub fn etcd_index(&self) -> Option<u64> {
+        self.etcd_index
+    }
+
+    /// Returns the Raft index at the time of the response, from the `X-Raft-Index` header.
+    pub fn raft_index(&self) -> Option<u64> {
+        self.raft_index
+    }
+
+    /// Returns the Raft election term at the time of the response, from the `X-Raft-Term` header.
+    pub fn raft_term(&self) -> Option<u64> {
+        self.raft_term
+    }
+}
+
 impl<'a> From<&'a HeaderMap<HeaderValue>> for ClusterInfo {
     fn from(headers: &'a HeaderMap<HeaderValue>) -> Self {
         let cluster_id = headers.get(XETCD_CLUSTER_ID).and_then(|v| {
@@ -407,3 +1762,135 @@ impl<'a> From<&'a HeaderMap<HeaderValue>> for ClusterInfo {
 fn build_url(endpoint: &Uri, path: &str) -> String {
     format!("{}{}", endpoint, path)
 }
+
+/// Appends a trailing slash to the endpoint's path if it doesn't already have one, since the API
+/// modules build request URLs by appending paths like "v2/keys" directly to the endpoint.
+///
+/// Any query string is dropped, since it would end up in the middle of the request URLs.
+pub(crate) fn normalize_endpoint(endpoint: Uri) -> Uri {
+    if endpoint.path().ends_with('/') && endpoint.query().is_none() {
+        return endpoint;
+    }
+
+    let path = format!("{}/", endpoint.path().trim_end_matches('/'));
+    let mut parts = endpoint.into_parts();
+    parts.path_and_query = Some(path.parse().expect("valid path"));
+
+    Uri::from_parts(parts).expect("valid endpoint")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use http::header::{HeaderMap, HeaderValue};
+    use hyper::Uri;
+
+    use super::{normalize_endpoint, Client, ClusterInfo, EndpointStrategy, RetryPolicy};
+
+    const ENDPOINTS: [&str; 3] = ["http://a:2379", "http://b:2379", "http://c:2379"];
+
+    fn hosts(endpoints: Vec<Uri>) -> Vec<String> {
+        endpoints
+            .iter()
+            .map(|uri| uri.host().unwrap().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_ordered_endpoints() {
+        let client = Client::new(&ENDPOINTS, None).unwrap();
+
+        assert_eq!(hosts(client.ordered_endpoints()), vec!["a", "b", "c"]);
+        assert_eq!(hosts(client.ordered_endpoints()), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_round_robin_endpoints() {
+        let mut client = Client::new(&ENDPOINTS, None).unwrap();
+        client.set_endpoint_strategy(EndpointStrategy::RoundRobin);
+        let clone = client.clone();
+
+        assert_eq!(hosts(client.ordered_endpoints()), vec!["a", "b", "c"]);
+        assert_eq!(hosts(clone.ordered_endpoints()), vec!["b", "c", "a"]);
+        assert_eq!(hosts(client.ordered_endpoints()), vec!["c", "a", "b"]);
+        assert_eq!(hosts(clone.ordered_endpoints()), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_random_endpoints() {
+        let mut client = Client::new(&ENDPOINTS, None).unwrap();
+        client.set_endpoint_strategy(EndpointStrategy::Random);
+
+        for _ in 0..10 {
+            let mut endpoints = hosts(client.ordered_endpoints());
+            endpoints.sort();
+
+            assert_eq!(endpoints, vec!["a", "b", "c"]);
+        }
+    }
+
+    #[test]
+    fn test_retry_policy_delay() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(500),
+            jitter: false,
+        };
+
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+        assert_eq!(policy.delay(3), Duration::from_millis(400));
+        assert_eq!(policy.delay(4), Duration::from_millis(500));
+        assert_eq!(policy.delay(100), Duration::from_millis(500));
+
+        let policy = RetryPolicy {
+            jitter: true,
+            ..policy
+        };
+
+        for _ in 0..10 {
+            let delay = policy.delay(2);
+
+            assert!(delay >= Duration::from_millis(100));
+            assert!(delay <= Duration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn test_cluster_info_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "X-Etcd-Cluster-Id",
+            HeaderValue::from_static("7e27652122e8b2ae"),
+        );
+        headers.insert("X-Etcd-Index", HeaderValue::from_static("42"));
+        headers.insert("X-Raft-Index", HeaderValue::from_static("1024"));
+        headers.insert("X-Raft-Term", HeaderValue::from_static("not a number"));
+
+        let cluster_info = ClusterInfo::from(&headers);
+
+        assert_eq!(cluster_info.cluster_id(), Some("7e27652122e8b2ae"));
+        assert_eq!(cluster_info.etcd_index(), Some(42));
+        assert_eq!(cluster_info.raft_index(), Some(1024));
+        assert_eq!(cluster_info.raft_term(), None);
+    }
+
+    #[test]
+    fn test_normalize_endpoint() {
+        let normalize = |endpoint: &str| normalize_endpoint(endpoint.parse().unwrap()).to_string();
+
+        assert_eq!(normalize("http://etcd:2379"), "http://etcd:2379/");
+        assert_eq!(normalize("http://etcd:2379/"), "http://etcd:2379/");
+        assert_eq!(normalize("http://proxy/etcd"), "http://proxy/etcd/");
+        assert_eq!(normalize("http://proxy/etcd/"), "http://proxy/etcd/");
+        assert_eq!(normalize("http://proxy/etcd?x=1"), "http://proxy/etcd/");
+        assert_eq!(normalize("http://[::1]:2379"), "http://[::1]:2379/");
+        assert_eq!(normalize("http://[2001:db8::1]"), "http://[2001:db8::1]/");
+        assert_eq!(
+            normalize("https://[::1]:4001/etcd"),
+            "https://[::1]:4001/etcd/"
+        );
+    }
+}
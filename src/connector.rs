@@ -0,0 +1,133 @@
+//! A connector that dials pre-resolved addresses for cluster members.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use hyper::Uri;
+use tower_service::Service;
+
+use crate::error::Error;
+
+/// A hyper connector that connects to fixed addresses for some endpoints instead of resolving
+/// their host names, wrapping another connector that makes the connections.
+///
+/// Requests still target the endpoint's host name, so the `Host` header and, when wrapped in an
+/// `HttpsConnector`, the TLS server name and certificate verification use the host name rather
+/// than the address. Connections to any other host, for example after a redirect, are resolved
+/// as usual.
+///
+/// This is useful where DNS is unreliable, or where cluster members must be reached through
+/// addresses that their host names don't resolve to. See `Client::pinned`.
+#[derive(Clone, Debug)]
+pub struct PinnedConnector<C> {
+    addresses: Arc<HashMap<String, SocketAddr>>,
+    inner: C,
+}
+
+impl<C> PinnedConnector<C> {
+    /// Constructs a connector that connects to the given address for each endpoint, and otherwise
+    /// uses `inner`.
+    ///
+    /// An address applies to every URI with the same host and port as its endpoint, so the
+    /// endpoint's path is ignored.
+    ///
+    /// # Errors
+    ///
+    /// Fails if any of the endpoints is an invalid URL.
+    pub fn new(inner: C, endpoints: &[(&str, SocketAddr)]) -> Result<Self, Error> {
+        let mut addresses = HashMap::with_capacity(endpoints.len());
+
+        for &(endpoint, address) in endpoints {
+            let uri: Uri = endpoint.parse().map_err(|source| Error::InvalidEndpoint {
+                endpoint: endpoint.to_owned(),
+                source,
+            })?;
+
+            addresses.insert(host_and_port(&uri), address);
+        }
+
+        Ok(PinnedConnector {
+            addresses: Arc::new(addresses),
+            inner,
+        })
+    }
+
+    /// Returns the URI to connect to for a destination, with the pinned address as its authority
+    /// if there is one.
+    fn destination(&self, uri: Uri) -> Uri {
+        let address = match self.addresses.get(&host_and_port(&uri)) {
+            Some(address) => address,
+            None => return uri,
+        };
+
+        let mut parts = uri.into_parts();
+        parts.authority = Some(address.to_string().parse().expect("valid authority"));
+
+        Uri::from_parts(parts).expect("valid destination")
+    }
+}
+
+impl<C> Service<Uri> for PinnedConnector<C>
+where
+    C: Service<Uri>,
+{
+    type Response = C::Response;
+    type Error = C::Error;
+    type Future = C::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), C::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let destination = self.destination(uri);
+
+        self.inner.call(destination)
+    }
+}
+
+/// Returns the host and port of a URI, using the scheme's default port if it has none.
+pub(crate) fn host_and_port(uri: &Uri) -> String {
+    let port = uri.port_u16().unwrap_or_else(|| match uri.scheme_str() {
+        Some("https") => 443,
+        _ => 80,
+    });
+
+    format!("{}:{}", uri.host().unwrap_or_default(), port)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use hyper::Uri;
+
+    use super::PinnedConnector;
+
+    #[test]
+    fn test_destination() {
+        let address: SocketAddr = "10.0.0.1:2379".parse().unwrap();
+        let connector = PinnedConnector::new((), &[("https://etcd.example.com", address)]).unwrap();
+        let destination = |uri: &str| connector.destination(uri.parse::<Uri>().unwrap());
+
+        assert_eq!(
+            destination("https://etcd.example.com:443/v2/keys/foo"),
+            "https://10.0.0.1:2379/v2/keys/foo"
+        );
+        assert_eq!(
+            destination("https://etcd.example.com:2379/"),
+            "https://etcd.example.com:2379/"
+        );
+        assert_eq!(destination("http://other:2379/"), "http://other:2379/");
+
+        let address: SocketAddr = "[fd00::1]:2379".parse().unwrap();
+        let connector = PinnedConnector::new((), &[("http://etcd:2379", address)]).unwrap();
+
+        assert_eq!(
+            connector.destination("http://etcd:2379/".parse().unwrap()),
+            "http://[fd00::1]:2379/"
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Leader election built on the key-value API.
+//!
+//! A candidate becomes the leader by creating a key with its ID as the value and a TTL, and stays
+//! the leader by refreshing the key's TTL before it expires, as long as the key still holds its ID.
+//! Refreshing doesn't notify watchers, so other candidates only wake up once the key is deleted or
+//! expires. For a stream of leadership changes that keeps campaigning after leadership is lost,
+//! see `kv::campaign`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::channel::oneshot::Receiver;
+use futures::future::{AbortHandle, FutureExt, Shared};
+use futures::Future;
+use hyper::client::connect::Connect;
+
+use crate::client::Client;
+use crate::error::{Error, EtcdErrorCode};
+use crate::kv::{self, WatchError, WatchOptions};
+
+/// Leadership won by `election::campaign`.
+///
+/// While the handle is held, a background task keeps the leadership key alive. Dropping the handle
+/// stops the task, but does not give up leadership immediately: the key is left to expire. Use
+/// `Election::resign` to give it up right away.
+#[derive(Debug)]
+pub struct Election<C>
+where
+    C: Clone + Connect + Sync + Send + 'static,
+{
+    client: Client<C>,
+    key: String,
+    node_id: String,
+    leading: Arc<AtomicBool>,
+    lost: Shared<Receiver<()>>,
+    refresh: AbortHandle,
+}
+
+impl<C> Election<C>
+where
+    C: Clone + Connect + Sync + Send + 'static,
+{
+    /// Returns true while this candidate is still the leader.
+    pub fn is_leader(&self) -> bool {
+        self.leading.load(Ordering::SeqCst)
+    }
+
+    /// Returns a future that resolves once this candidate is no longer the leader.
+    ///
+    /// Leadership is lost when the key can't be refreshed because another candidate holds it or it
+    /// no longer exists, for example because it expired while this candidate was partitioned from
+    /// the cluster, or when the key's TTL runs out while refreshing keeps failing with transient
+    /// errors. The future also resolves once `Election::resign` is called, and resolves
+    /// immediately if leadership has already been lost.
+    pub fn lost(&self) -> impl Future<Output = ()> + Send {
+        self.lost.clone().map(|_| ())
+    }
+
+    /// Gives up leadership, deleting the key so that another candidate can take over right away.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the key could not be deleted, including if it is now held by another candidate.
+    /// Leadership is given up either way.
+    pub async fn resign(self) -> Result<(), Vec<Error>> {
+        self.refresh.abort();
+        self.leading.store(false, Ordering::SeqCst);
+
+        kv::compare_and_delete(&self.client, &self.key, Some(&self.node_id), None)
+            .await
+            .map(|_| ())
+    }
+}
+
+impl<C> Drop for Election<C>
+where
+    C: Clone + Connect + Sync + Send + 'static,
+{
+    fn drop(&mut self) {
+        self.refresh.abort();
+        self.leading.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Campaigns for leadership, resolving once this candidate is the leader.
+///
+/// The leader is whichever candidate holds `key`, which is created with `node_id` as its value and
+/// expires after `ttl` seconds. While another candidate holds the key, it is watched until it is
+/// deleted or expires, at which point this candidate tries to take it again. Once this candidate
+/// holds the key, a task spawned on the Tokio runtime refreshes its TTL every third of `ttl`, the
+/// same way `kv::keep_alive` does.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API calls.
+/// * key: The name of the key that represents leadership.
+/// * node_id: A unique identifier for this candidate.
+/// * ttl: The number of seconds after which leadership expires unless refreshed.
+///
+/// # Errors
+///
+/// Fails with `Error::ZeroTtl` if `ttl` is zero, if the key can't be created for any reason other
+/// than another candidate holding it, or if watching the key held by another candidate fails.
+pub async fn campaign<C>(
+    client: &Client<C>,
+    key: &str,
+    node_id: &str,
+    ttl: u64,
+) -> Result<Election<C>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send + 'static,
+{
+    if ttl == 0 {
+        return Err(vec![Error::ZeroTtl]);
+    }
+
+    loop {
+        match kv::create(client, key, node_id, Some(ttl)).await {
+            Ok(_) => break,
+            Err(errors) => {
+                let held_at = errors.iter().find_map(|error| match error {
+                    Error::Api(error) if error.code() == EtcdErrorCode::KeyAlreadyExists => {
+                        error.index
+                    }
+                    _ => None,
+                });
+
+                let index = match held_at {
+                    Some(index) => index,
+                    None => return Err(errors),
+                };
+
+                let options = WatchOptions {
+                    index: Some(index + 1),
+                    timeout: Some(Duration::from_secs(ttl)),
+                    ..Default::default()
+                };
+
+                match kv::watch(client, key, options).await {
+                    Ok(_) | Err(WatchError::Outdated { .. }) | Err(WatchError::Timeout) => {}
+                    Err(WatchError::Other(errors)) => return Err(errors),
+                }
+            }
+        }
+    }
+
+    // Only the value is compared, so that retrying a renewal that was applied but whose response
+    // was lost still succeeds. Refreshing rather than setting the key keeps other candidates'
+    // watches from firing on every renewal.
+    let (leading, lost, refresh) = {
+        let client = client.clone();
+        let key = key.to_owned();
+        let node_id = node_id.to_owned();
+
+        kv::spawn_renewal(ttl, move || {
+            let client = client.clone();
+            let key = key.clone();
+            let node_id = node_id.clone();
+
+            async move {
+                kv::refresh_if_value(&client, &key, &node_id, ttl)
+                    .await
+                    .map(|_| ())
+            }
+        })
+    };
+
+    Ok(Election {
+        client: client.clone(),
+        key: key.to_owned(),
+        node_id: node_id.to_owned(),
+        leading,
+        lost,
+        refresh,
+    })
+}
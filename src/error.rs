@@ -3,7 +3,9 @@
 use std::convert::From;
 use std::error::Error as StdError;
 use std::fmt::{Display, Error as FmtError, Formatter};
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
 
+use base64::DecodeError as Base64Error;
 use http::uri::InvalidUri;
 use hyper::{Error as HttpError, StatusCode};
 #[cfg(feature = "tls")]
@@ -24,15 +26,71 @@ pub struct ApiError {
     /// The etcd error code.
     #[serde(rename = "errorCode")]
     pub error_code: u64,
-    /// The etcd index.
-    pub index: u64,
+    /// The index at which the error occurred, if reported. This is not the same as `index`, and
+    /// should not be used to retry an operation.
+    #[serde(rename = "errorIndex")]
+    pub error_index: Option<u64>,
+    /// The current etcd index at the time of the error.
+    ///
+    /// After a failed compare-and-swap or compare-and-delete, this is the index to read the node
+    /// at, or to start watching from, before retrying. See `ApiError::retry_index`.
+    pub index: Option<u64>,
     /// A human-friendly description of the error.
     pub message: String,
 }
 
+impl ApiError {
+    /// Returns the key that was being operated upon or reason for the failure, if reported.
+    pub fn cause(&self) -> Option<&str> {
+        self.cause.as_deref()
+    }
+
+    /// Returns the etcd error code.
+    pub fn error_code(&self) -> u64 {
+        self.error_code
+    }
+
+    /// Returns the etcd error code as an `EtcdErrorCode`.
+    pub fn code(&self) -> EtcdErrorCode {
+        EtcdErrorCode::from_u64(self.error_code)
+    }
+
+    /// Returns the current etcd index at the time of the error, if reported.
+    pub fn index(&self) -> Option<u64> {
+        self.index
+    }
+
+    /// Returns the human-friendly description of the error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the etcd index to use when retrying an operation that failed with this error, such
+    /// as a compare-and-swap with a stale modified index.
+    ///
+    /// This is the current etcd index, not the `error_index`.
+    pub fn retry_index(&self) -> Option<u64> {
+        self.index
+    }
+}
+
 impl Display for ApiError {
+    /// Formats the error as its message followed by its cause, error code, and index, such as
+    /// `Key not found: /foo (error code 100, index 7)`.
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
-        write!(f, "{}", self.message)
+        write!(f, "{}", self.message)?;
+
+        if let Some(ref cause) = self.cause {
+            write!(f, ": {}", cause)?;
+        }
+
+        write!(f, " (error code {}", self.error_code)?;
+
+        if let Some(index) = self.index {
+            write!(f, ", index {}", index)?;
+        }
+
+        write!(f, ")")
     }
 }
 
@@ -42,48 +100,306 @@ impl StdError for ApiError {
     }
 }
 
+/// An error code returned by etcd in an `ApiError`.
+///
+/// Error codes this crate doesn't know about are kept as `EtcdErrorCode::Unknown`, so converting
+/// to and from `u64` never loses information.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum EtcdErrorCode {
+    /// The key does not exist. Error code 100.
+    KeyNotFound,
+    /// The value or modified index of a compare-and-swap or compare-and-delete didn't match.
+    /// Error code 101.
+    CompareFailed,
+    /// The node is a directory where a key-value pair was expected. Error code 102.
+    NotAFile,
+    /// The node is a key-value pair where a directory was expected. Error code 104.
+    NotADirectory,
+    /// The key already exists. Error code 105.
+    KeyAlreadyExists,
+    /// The root directory can't be changed. Error code 107.
+    RootReadOnly,
+    /// The directory can't be deleted because it isn't empty. Error code 108.
+    DirectoryNotEmpty,
+    /// The request requires authentication, or the user lacks permission for it. Error code 110.
+    Unauthorized,
+    /// A compare-and-swap was missing its previous value. Error code 200.
+    PrevValueRequired,
+    /// The TTL given is not a number. Error code 201.
+    TtlNotANumber,
+    /// The index given is not a number. Error code 202.
+    IndexNotANumber,
+    /// A field of the request is invalid. Error code 209.
+    InvalidField,
+    /// The request's form is invalid. Error code 210.
+    InvalidForm,
+    /// A value was given when refreshing a key's TTL. Error code 211.
+    RefreshValue,
+    /// No TTL was given when refreshing a key's TTL. Error code 212.
+    RefreshTtlRequired,
+    /// An internal error in etcd's consensus algorithm, such as a lack of quorum. Error code 300.
+    RaftInternal,
+    /// The cluster is electing a new leader. Error code 301.
+    LeaderElection,
+    /// The watcher was cleared because etcd is recovering. Error code 400.
+    WatcherCleared,
+    /// The requested etcd index has already been cleared from etcd's event history. Error code
+    /// 401.
+    EventIndexCleared,
+    /// An error code not known to this crate.
+    Unknown(u64),
+}
+
+impl EtcdErrorCode {
+    /// Converts a numeric etcd error code.
+    pub fn from_u64(code: u64) -> Self {
+        match code {
+            100 => EtcdErrorCode::KeyNotFound,
+            101 => EtcdErrorCode::CompareFailed,
+            102 => EtcdErrorCode::NotAFile,
+            104 => EtcdErrorCode::NotADirectory,
+            105 => EtcdErrorCode::KeyAlreadyExists,
+            107 => EtcdErrorCode::RootReadOnly,
+            108 => EtcdErrorCode::DirectoryNotEmpty,
+            110 => EtcdErrorCode::Unauthorized,
+            200 => EtcdErrorCode::PrevValueRequired,
+            201 => EtcdErrorCode::TtlNotANumber,
+            202 => EtcdErrorCode::IndexNotANumber,
+            209 => EtcdErrorCode::InvalidField,
+            210 => EtcdErrorCode::InvalidForm,
+            211 => EtcdErrorCode::RefreshValue,
+            212 => EtcdErrorCode::RefreshTtlRequired,
+            300 => EtcdErrorCode::RaftInternal,
+            301 => EtcdErrorCode::LeaderElection,
+            400 => EtcdErrorCode::WatcherCleared,
+            401 => EtcdErrorCode::EventIndexCleared,
+            code => EtcdErrorCode::Unknown(code),
+        }
+    }
+
+    /// Returns the numeric etcd error code.
+    pub fn as_u64(self) -> u64 {
+        match self {
+            EtcdErrorCode::KeyNotFound => 100,
+            EtcdErrorCode::CompareFailed => 101,
+            EtcdErrorCode::NotAFile => 102,
+            EtcdErrorCode::NotADirectory => 104,
+            EtcdErrorCode::KeyAlreadyExists => 105,
+            EtcdErrorCode::RootReadOnly => 107,
+            EtcdErrorCode::DirectoryNotEmpty => 108,
+            EtcdErrorCode::Unauthorized => 110,
+            EtcdErrorCode::PrevValueRequired => 200,
+            EtcdErrorCode::TtlNotANumber => 201,
+            EtcdErrorCode::IndexNotANumber => 202,
+            EtcdErrorCode::InvalidField => 209,
+            EtcdErrorCode::InvalidForm => 210,
+            EtcdErrorCode::RefreshValue => 211,
+            EtcdErrorCode::RefreshTtlRequired => 212,
+            EtcdErrorCode::RaftInternal => 300,
+            EtcdErrorCode::LeaderElection => 301,
+            EtcdErrorCode::WatcherCleared => 400,
+            EtcdErrorCode::EventIndexCleared => 401,
+            EtcdErrorCode::Unknown(code) => code,
+        }
+    }
+}
+
 /// An error returned when an operation fails for some reaosn.
 #[derive(Debug)]
 pub enum Error {
     /// An error returned by an etcd API endpoint.
     Api(ApiError),
-    /// An error at the HTTP protocol layer.
+    /// An error returned when the requested etcd index is older than the change events etcd
+    /// still retains in its history.
+    Compacted(ApiError),
+    /// An error returned when a request to a cluster member fails because of a problem with the
+    /// connection, such as a refused connection, a failed TLS handshake, or a connection reset
+    /// while reading the response. The underlying error is available from `source`.
+    Connection {
+        /// The stage of the request at which the error occurred.
+        phase: ConnectionPhase,
+        /// The kind of I/O error that caused the failure, or `Other` if there wasn't one.
+        kind: IoErrorKind,
+        /// The underlying HTTP error.
+        source: HttpError,
+    },
+    /// An error returned when a compressed response body can't be decompressed.
+    #[cfg(feature = "compression")]
+    Decompression(IoError),
+    /// An error returned when copying a subtree into a destination directory that already has
+    /// nodes in it.
+    DestinationNotEmpty,
+    /// An error returned when reading a node at an etcd index that the cluster hasn't reached yet.
+    FutureIndex {
+        /// The etcd index that was requested.
+        requested: u64,
+        /// The cluster's current etcd index.
+        current: u64,
+    },
+    /// An error at the HTTP protocol layer, such as a malformed response from a cluster member.
+    /// Problems reaching the member are reported as `Error::Connection` instead.
     Http(HttpError),
+    /// An error returned when a value read as binary data is not valid base64.
+    InvalidBase64(Base64Error),
     /// An error returned when invalid conditions have been provided for a compare-and-delete or
     /// compare-and-swap operation.
     InvalidConditions,
+    /// An error returned when `kv::increment` finds a value that isn't an integer, or that would
+    /// overflow an `i64` once incremented. Contains the stored value.
+    InvalidCounter(String),
+    /// An error returned when creating a client with a cluster member endpoint that is not a
+    /// valid URI.
+    InvalidEndpoint {
+        /// The endpoint as it was given.
+        endpoint: String,
+        /// The error from parsing the endpoint.
+        source: InvalidUri,
+    },
     /// An error returned when an etcd cluster member's endpoint is not a valid URI.
     InvalidUri(InvalidUri),
     /// An error returned when the URL for a specific API endpoint cannot be generated.
     InvalidUrl(UrlError),
+    /// An error returned when etcd's response to creating a node unexpectedly omits the node's key.
+    MissingKey,
+    /// An error returned when reading the value of a node that is a directory or has no value.
+    MissingValue,
     /// An error returned when attempting to create a client without at least one member endpoint.
     NoEndpoints,
+    /// An error returned when a write is attempted while the cluster has no leader and the client
+    /// requires one.
+    NoLeader,
+    /// An error returned when listing the children of a node that is a key-value pair rather than
+    /// a directory.
+    NotADirectory,
+    /// An error returned when a cluster member redirects a request elsewhere, typically to the
+    /// leader, and the client doesn't follow the redirect. See `Client::set_follow_redirects`.
+    Redirect {
+        /// The `Location` the member redirected to.
+        location: String,
+    },
     /// An error returned when attempting to deserializing invalid JSON.
     Serialization(SerializationError),
+    /// An error returned when a read was served by a member that hasn't caught up to the client's
+    /// own earlier writes. See `Client::set_read_your_writes`.
+    StaleRead {
+        /// The etcd index of the client's latest write.
+        required: u64,
+        /// The etcd index of the member that served the read.
+        actual: u64,
+    },
+    /// An error returned when a request to a cluster member takes longer than the timeout set
+    /// with `Client::set_request_timeout`.
+    Timeout,
     /// An error returned when configuring TLS.
     #[cfg(feature = "tls")]
     Tls(TlsError),
+    /// An error returned before a write is sent when its TTL is outside the bounds set with
+    /// `Client::set_ttl_bounds`, or when it has no TTL and the client forbids that.
+    TtlOutOfBounds {
+        /// The minimum allowed TTL in seconds, if any.
+        min: Option<u64>,
+        /// The maximum allowed TTL in seconds, if any.
+        max: Option<u64>,
+        /// The requested TTL in seconds, or `None` if the write had no TTL.
+        requested: Option<u64>,
+    },
     /// An error returned when an unexpected HTTP status code is returned by the server.
-    UnexpectedStatus(StatusCode),
+    UnexpectedStatus {
+        /// The HTTP status code.
+        status: StatusCode,
+        /// The body of the response, if it wasn't empty. Bytes that aren't valid UTF-8 are
+        /// replaced with U+FFFD.
+        body: Option<String>,
+    },
+    /// An error returned when a key that is renewed periodically, such as one kept alive with
+    /// `kv::keep_alive` or a leadership key, is given a TTL of zero, which would leave no time
+    /// between renewals.
+    ZeroTtl,
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
         match *self {
             Error::Api(ref error) => write!(f, "{}", error),
+            Error::Compacted(ref error) => write!(f, "{}", error),
+            Error::Connection {
+                phase,
+                kind,
+                ref source,
+            } => write!(
+                f,
+                "connection error during {} ({:?}): {}",
+                phase, kind, source
+            ),
+            #[cfg(feature = "compression")]
+            Error::Decompression(ref error) => write!(f, "{}", error),
+            ref error @ Error::DestinationNotEmpty => write!(f, "{}", error.description()),
+            Error::FutureIndex { requested, current } => write!(
+                f,
+                "the requested index {} is beyond the cluster's current index {}",
+                requested, current
+            ),
             Error::Http(ref error) => write!(f, "{}", error),
+            Error::InvalidBase64(ref error) => write!(f, "{}", error),
             ref error @ Error::InvalidConditions => write!(f, "{}", error.description()),
+            Error::InvalidCounter(ref value) => {
+                write!(f, "the counter value {:?} can't be incremented", value)
+            }
+            Error::InvalidEndpoint {
+                ref endpoint,
+                ref source,
+            } => write!(f, "invalid endpoint {:?}: {}", endpoint, source),
             Error::InvalidUri(ref error) => write!(f, "{}", error),
             Error::InvalidUrl(ref error) => write!(f, "{}", error),
+            ref error @ Error::MissingKey => write!(f, "{}", error.description()),
+            ref error @ Error::MissingValue => write!(f, "{}", error.description()),
             ref error @ Error::NoEndpoints => write!(f, "{}", error.description()),
+            ref error @ Error::NoLeader => write!(f, "{}", error.description()),
+            ref error @ Error::NotADirectory => write!(f, "{}", error.description()),
+            Error::Redirect { ref location } => {
+                write!(f, "the etcd member redirected the request to {}", location)
+            }
+            ref error @ Error::Timeout => write!(f, "{}", error.description()),
             #[cfg(feature = "tls")]
             Error::Tls(ref error) => write!(f, "{}", error),
             Error::Serialization(ref error) => write!(f, "{}", error),
-            Error::UnexpectedStatus(ref status) => write!(
+            Error::TtlOutOfBounds {
+                min,
+                max,
+                requested,
+            } => {
+                match requested {
+                    Some(ttl) => write!(f, "a TTL of {} seconds", ttl)?,
+                    None => write!(f, "a write without a TTL")?,
+                }
+
+                write!(f, " is outside the allowed TTL bounds")?;
+
+                match (min, max) {
+                    (Some(min), Some(max)) => write!(f, " of {} to {} seconds", min, max),
+                    (Some(min), None) => write!(f, " of at least {} seconds", min),
+                    (None, Some(max)) => write!(f, " of at most {} seconds", max),
+                    (None, None) => Ok(()),
+                }
+            }
+            Error::StaleRead { required, actual } => write!(
                 f,
-                "the etcd server returned an unexpected HTTP status code: {}",
-                status
+                "the etcd member is at index {}, behind the client's latest write at index {}",
+                actual, required
             ),
+            Error::UnexpectedStatus { status, ref body } => {
+                write!(
+                    f,
+                    "the etcd server returned an unexpected HTTP status code: {}",
+                    status
+                )?;
+
+                match body {
+                    Some(body) => write!(f, " ({})", body),
+                    None => Ok(()),
+                }
+            }
+            ref error @ Error::ZeroTtl => write!(f, "{}", error.description()),
         }
     }
 }
@@ -92,22 +408,184 @@ impl StdError for Error {
     fn description(&self) -> &str {
         match *self {
             Error::Api(_) => "the etcd server returned an error",
+            Error::Compacted(_) => "the requested index has been cleared from etcd's event history",
+            Error::Connection { .. } => "an error occurred on the connection to an etcd member",
+            #[cfg(feature = "compression")]
+            Error::Decompression(_) => "the response body could not be decompressed",
+            Error::DestinationNotEmpty => "the destination directory is not empty",
+            Error::FutureIndex { .. } => "the requested index has not been reached yet",
             Error::Http(_) => "an error occurred during the HTTP request",
+            Error::InvalidBase64(_) => "the value is not valid base64",
             Error::InvalidConditions => "current value or modified index is required",
+            Error::InvalidCounter(_) => "the counter value is not an integer or would overflow",
+            Error::InvalidEndpoint { .. } => "a supplied endpoint could not be parsed as a URI",
             Error::InvalidUri(_) => "a supplied endpoint could not be parsed as a URI",
             Error::InvalidUrl(_) => "a URL for the request could not be generated",
+            Error::MissingKey => "the etcd server did not return the key of the created node",
+            Error::MissingValue => "the node is a directory or has no value",
             Error::NoEndpoints => "at least one endpoint is required to create a Client",
+            Error::NoLeader => "the etcd cluster has no leader",
+            Error::NotADirectory => "the node is not a directory",
+            Error::Redirect { .. } => "the etcd member redirected the request to another URL",
+            Error::Timeout => "the request to the etcd member timed out",
             #[cfg(feature = "tls")]
             Error::Tls(_) => "an error occurred configuring TLS",
             Error::Serialization(_) => "an error occurred deserializing JSON",
-            Error::UnexpectedStatus(_) => "the etcd server returned an unexpected HTTP status code",
+            Error::StaleRead { .. } => "the etcd member has not caught up to the client's writes",
+            Error::TtlOutOfBounds { .. } => "the requested TTL is outside the allowed bounds",
+            Error::UnexpectedStatus { .. } => {
+                "the etcd server returned an unexpected HTTP status code"
+            }
+            Error::ZeroTtl => "a key that is renewed needs a TTL of at least one second",
+        }
+    }
+
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match *self {
+            Error::Connection { ref source, .. } => Some(source),
+            #[cfg(feature = "compression")]
+            Error::Decompression(ref error) => Some(error),
+            Error::Http(ref error) => Some(error),
+            Error::InvalidBase64(ref error) => Some(error),
+            Error::InvalidEndpoint { ref source, .. } => Some(source),
+            Error::InvalidUri(ref error) => Some(error),
+            Error::InvalidUrl(ref error) => Some(error),
+            Error::Serialization(ref error) => Some(error),
+            #[cfg(feature = "tls")]
+            Error::Tls(ref error) => Some(error),
+            _ => None,
         }
     }
 }
 
+impl Error {
+    /// Returns true if the error is likely to be transient, so that retrying the request may
+    /// succeed.
+    pub(crate) fn is_retryable(&self) -> bool {
+        match *self {
+            Error::Api(ref error) => {
+                error.code() == EtcdErrorCode::RaftInternal
+                    || error.code() == EtcdErrorCode::LeaderElection
+            }
+            Error::Connection { .. } | Error::Http(_) | Error::StaleRead { .. } => true,
+            Error::Timeout => true,
+            Error::UnexpectedStatus { status, .. } => status.is_server_error(),
+            _ => false,
+        }
+    }
+
+    /// Returns true if the request may have reached etcd and been applied before the error
+    /// occurred, so that it isn't safe to assume the request had no effect.
+    pub(crate) fn may_have_been_applied(&self) -> bool {
+        match *self {
+            Error::Api(ref error) => error.code() == EtcdErrorCode::RaftInternal,
+            Error::Connection { phase, .. } => {
+                phase == ConnectionPhase::Headers || phase == ConnectionPhase::Body
+            }
+            Error::Http(_) | Error::Timeout => true,
+            Error::UnexpectedStatus { status, .. } => status.is_server_error(),
+            _ => false,
+        }
+    }
+
+    /// Classifies an error returned by hyper while making a request to a cluster member.
+    ///
+    /// `phase` is where the request was when it failed: `ConnectionPhase::Headers` while waiting
+    /// for the response, or `ConnectionPhase::Body` while reading it. An error waiting for the
+    /// response is attributed to establishing the connection instead if hyper reports it as one,
+    /// and to the TLS handshake if a TLS error caused it.
+    pub(crate) fn connection(phase: ConnectionPhase, source: HttpError) -> Error {
+        let phase = if phase == ConnectionPhase::Headers && source.is_connect() {
+            if is_tls_error(&source) {
+                ConnectionPhase::TlsHandshake
+            } else {
+                ConnectionPhase::Connect
+            }
+        } else {
+            phase
+        };
+
+        let mut kind = IoErrorKind::Other;
+        let mut cause = source.source();
+
+        while let Some(error) = cause {
+            if let Some(error) = error.downcast_ref::<IoError>() {
+                kind = error.kind();
+                break;
+            }
+
+            cause = error.source();
+        }
+
+        Error::Connection {
+            phase,
+            kind,
+            source,
+        }
+    }
+}
+
+/// The stage of a request to a cluster member at which an `Error::Connection` occurred.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ConnectionPhase {
+    /// Establishing the connection, including resolving the member's host name.
+    Connect,
+    /// Performing the TLS handshake.
+    TlsHandshake,
+    /// Sending the request or waiting for the response headers.
+    Headers,
+    /// Reading the response body.
+    Body,
+}
+
+impl Display for ConnectionPhase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        let phase = match *self {
+            ConnectionPhase::Connect => "connect",
+            ConnectionPhase::TlsHandshake => "TLS handshake",
+            ConnectionPhase::Headers => "headers",
+            ConnectionPhase::Body => "body",
+        };
+
+        write!(f, "{}", phase)
+    }
+}
+
+/// Returns true if a TLS error is in the chain of causes of an error.
+#[cfg(feature = "tls")]
+fn is_tls_error(error: &HttpError) -> bool {
+    let mut cause = error.source();
+
+    while let Some(error) = cause {
+        if error.is::<TlsError>() {
+            return true;
+        }
+
+        cause = error.source();
+    }
+
+    false
+}
+
+/// Returns true if a TLS error is in the chain of causes of an error.
+#[cfg(not(feature = "tls"))]
+fn is_tls_error(_error: &HttpError) -> bool {
+    false
+}
+
 impl From<HttpError> for Error {
+    /// Converts a hyper error into `Error::Connection`, unless it is a protocol error such as a
+    /// malformed response, which becomes `Error::Http`.
+    ///
+    /// Since the stage of the request at which the error occurred isn't known here, a connection
+    /// error is attributed to waiting for the response unless hyper reports it as a failure to
+    /// connect.
     fn from(error: HttpError) -> Error {
-        Error::Http(error)
+        if error.is_parse() || error.is_user() {
+            Error::Http(error)
+        } else {
+            Error::connection(ConnectionPhase::Headers, error)
+        }
     }
 }
 
@@ -141,6 +619,12 @@ impl From<InvalidUri> for Error {
 pub enum WatchError {
     /// An error for each failed request to an etcd member.
     Other(Vec<Error>),
+    /// The index to watch from has been cleared from etcd's event history. Watching can resume
+    /// from just after `current_index`, at the cost of missing the changes in between.
+    Outdated {
+        /// The current etcd index, as reported with the error.
+        current_index: u64,
+    },
     /// The supplied timeout was reached before any request successfully completed.
     Timeout,
 }
@@ -154,14 +638,160 @@ impl From<TokioTimeoutError> for WatchError {
 impl Display for WatchError {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
         match *self {
+            WatchError::Outdated { current_index } => write!(
+                f,
+                "the watched index has been cleared from etcd's event history, which is now at \
+                 index {}",
+                current_index
+            ),
+            WatchError::Other(ref errors) => {
+                write!(f, "{}", self.description())?;
+
+                for (i, error) in errors.iter().enumerate() {
+                    write!(f, "{} {}", if i == 0 { ":" } else { ";" }, error)?;
+                }
+
+                Ok(())
+            }
             WatchError::Timeout => write!(f, "{}", self.description()),
-            ref other => other.fmt(f),
         }
     }
 }
 
 impl StdError for WatchError {
     fn description(&self) -> &str {
-        "operation timed out"
+        match *self {
+            WatchError::Other(_) => "the watch failed",
+            WatchError::Outdated { .. } => {
+                "the watched index has been cleared from etcd's event history"
+            }
+            WatchError::Timeout => "operation timed out",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind as IoErrorKind;
+
+    use hyper::Client as Hyper;
+    use tokio::runtime::Runtime;
+
+    use super::{ApiError, ConnectionPhase, Error, EtcdErrorCode};
+
+    #[test]
+    fn test_api_error_compare_failed() {
+        let body =
+            r#"{"errorCode":101,"message":"Compare failed","cause":"[two != one]","index":8}"#;
+        let error: ApiError = serde_json::from_str(body).unwrap();
+
+        assert_eq!(error.error_code, 101);
+        assert_eq!(error.cause.as_deref(), Some("[two != one]"));
+        assert_eq!(error.index, Some(8));
+        assert_eq!(error.error_index, None);
+        assert_eq!(error.retry_index(), Some(8));
+
+        let body = r#"{"errorCode":101,"message":"Compare failed","index":12,"errorIndex":9}"#;
+        let error: ApiError = serde_json::from_str(body).unwrap();
+
+        assert_eq!(error.error_index, Some(9));
+        assert_eq!(error.retry_index(), Some(12));
+    }
+
+    #[test]
+    fn test_ttl_out_of_bounds_display() {
+        let error = Error::TtlOutOfBounds {
+            min: Some(10),
+            max: Some(300),
+            requested: Some(5),
+        };
+        assert_eq!(
+            error.to_string(),
+            "a TTL of 5 seconds is outside the allowed TTL bounds of 10 to 300 seconds"
+        );
+
+        let error = Error::TtlOutOfBounds {
+            min: Some(10),
+            max: None,
+            requested: None,
+        };
+        assert_eq!(
+            error.to_string(),
+            "a write without a TTL is outside the allowed TTL bounds of at least 10 seconds"
+        );
+    }
+
+    #[test]
+    fn test_api_error_display() {
+        let body = r#"{"errorCode":100,"message":"Key not found","cause":"/foo","index":7}"#;
+        let error: ApiError = serde_json::from_str(body).unwrap();
+
+        assert_eq!(error.error_code(), 100);
+        assert_eq!(error.message(), "Key not found");
+        assert_eq!(error.cause(), Some("/foo"));
+        assert_eq!(error.index(), Some(7));
+        assert_eq!(
+            error.to_string(),
+            "Key not found: /foo (error code 100, index 7)"
+        );
+
+        let body = r#"{"errorCode":300,"message":"Raft Internal Error"}"#;
+        let error: ApiError = serde_json::from_str(body).unwrap();
+
+        assert_eq!(error.to_string(), "Raft Internal Error (error code 300)");
+    }
+
+    #[test]
+    fn test_from_hyper_connection_error() {
+        let work = Hyper::new().get("http://127.0.0.1:1".parse().unwrap());
+        let error = Runtime::new().unwrap().block_on(work).unwrap_err();
+
+        match Error::from(error) {
+            Error::Connection { phase, kind, .. } => {
+                assert_eq!(phase, ConnectionPhase::Connect);
+                assert_eq!(kind, IoErrorKind::ConnectionRefused);
+            }
+            error => panic!("unexpected error: {:?}", error),
+        }
+    }
+
+    #[test]
+    fn test_etcd_error_code() {
+        let body = r#"{"errorCode":105,"message":"Key already exists","cause":"/foo","index":3}"#;
+        let error: ApiError = serde_json::from_str(body).unwrap();
+
+        assert_eq!(error.code(), EtcdErrorCode::KeyAlreadyExists);
+
+        for code in (100..110).chain(200..215).chain(300..305).chain(400..405) {
+            assert_eq!(EtcdErrorCode::from_u64(code).as_u64(), code);
+        }
+
+        assert_eq!(
+            EtcdErrorCode::from_u64(401),
+            EtcdErrorCode::EventIndexCleared
+        );
+        assert_eq!(EtcdErrorCode::from_u64(999), EtcdErrorCode::Unknown(999));
+    }
+
+    #[test]
+    fn test_watch_error_display() {
+        use super::WatchError;
+
+        let error = WatchError::Other(vec![Error::NoEndpoints, Error::Timeout]);
+
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "the watch failed: {}; {}",
+                Error::NoEndpoints,
+                Error::Timeout
+            )
+        );
+        assert_eq!(WatchError::Other(vec![]).to_string(), "the watch failed");
+        assert_eq!(
+            WatchError::Outdated { current_index: 42 }.to_string(),
+            "the watched index has been cleared from etcd's event history, which is now at index 42"
+        );
+        assert_eq!(WatchError::Timeout.to_string(), "operation timed out");
     }
 }
@@ -1,15 +1,101 @@
+use futures::stream::{FuturesUnordered, StreamExt};
 use futures::Future;
 use hyper::Uri;
+#[cfg(feature = "tracing")]
+use tracing::Instrument;
 
 /// Executes the given closure with each cluster member and short-circuit returns the first
 /// successful result. If all members are exhausted without success, a vector of all errors is
 /// returned.
+///
+/// With the `tracing` feature enabled, the requests are made within a `first_ok` span, and a
+/// `warn` event is emitted each time a member fails and the next one is tried. With the `metrics`
+/// feature enabled, each such failure is also counted in `etcd_client_endpoint_fallbacks_total`.
 pub async fn first_ok<F, G, T, E>(endpoints: Vec<Uri>, callback: F) -> Result<T, Vec<E>>
 where
     F: FnMut(&Uri) -> G,
     G: Future<Output = Result<T, E>>,
 {
-    first_future_ok(endpoints.iter().map(callback)).await
+    #[cfg(feature = "tracing")]
+    let span = tracing::debug_span!(
+        "first_ok",
+        endpoints = endpoints.len(),
+        concurrency = "sequential",
+    );
+
+    let work = first_future_ok(endpoints.iter().map(callback));
+
+    #[cfg(feature = "tracing")]
+    let work = work.instrument(span);
+
+    work.await
+}
+
+/// Executes the given closure with each cluster member, keeping up to `concurrency` requests in
+/// flight at a time, and returns the first successful result. Requests still in flight at that
+/// point are cancelled. If all members are exhausted without success, a vector of all errors is
+/// returned.
+///
+/// The requests are traced and counted in the same way as for `first_ok`.
+pub async fn first_ok_hedged<F, G, T, E>(
+    endpoints: Vec<Uri>,
+    concurrency: usize,
+    callback: F,
+) -> Result<T, Vec<E>>
+where
+    F: FnMut(&Uri) -> G,
+    G: Future<Output = Result<T, E>>,
+{
+    #[cfg(feature = "tracing")]
+    let span = tracing::debug_span!(
+        "first_ok",
+        endpoints = endpoints.len(),
+        concurrency = "hedged",
+    );
+
+    let work = first_future_ok_hedged(endpoints.iter().map(callback), concurrency);
+
+    #[cfg(feature = "tracing")]
+    let work = work.instrument(span);
+
+    work.await
+}
+
+/// Executes the given closure with every cluster member at once and returns the first successful
+/// result. Requests still in flight at that point are cancelled. If no member succeeds, a vector
+/// of all errors is returned, in the same order as the members.
+///
+/// The requests are traced and counted in the same way as for `first_ok`, except that a failure is
+/// reported while any other member is still in flight, since every member has already been tried.
+pub async fn first_ok_parallel<F, G, T, E>(endpoints: Vec<Uri>, callback: F) -> Result<T, Vec<E>>
+where
+    F: FnMut(&Uri) -> G,
+    G: Future<Output = Result<T, E>>,
+{
+    #[cfg(feature = "tracing")]
+    let span = tracing::debug_span!(
+        "first_ok",
+        endpoints = endpoints.len(),
+        concurrency = "parallel",
+    );
+
+    let work = first_future_ok_parallel(endpoints.iter().map(callback));
+
+    #[cfg(feature = "tracing")]
+    let work = work.instrument(span);
+
+    work.await
+}
+
+/// Reports that a member failed while others are still to be tried, as a `warn` event with the
+/// `tracing` feature and in `etcd_client_endpoint_fallbacks_total` with the `metrics` feature.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+fn record_fallback(attempt: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(attempt, "etcd member failed, falling back to the next one");
+
+    #[cfg(feature = "metrics")]
+    metrics::counter!("etcd_client_endpoint_fallbacks_total").increment(1);
 }
 
 /// Await all TryFutures in sequence, returning the result (and short-circuiting) if one
@@ -21,6 +107,10 @@ where
 {
     let mut errors: Vec<E> = Vec::new();
     for future in futures {
+        if !errors.is_empty() {
+            record_fallback(errors.len() + 1);
+        }
+
         match future.await {
             Ok(item) => return Ok(item),
             Err(err) => {
@@ -31,12 +121,67 @@ where
     Err(errors)
 }
 
+/// Await all TryFutures, keeping at most `concurrency` of them in flight at a time, returning the
+/// result (and dropping the rest) as soon as one completes successfully or a vector of all errors
+/// if none does. A `concurrency` of zero is treated as one.
+async fn first_future_ok_hedged<I, T, E>(futures: I, concurrency: usize) -> Result<T, Vec<E>>
+where
+    I: IntoIterator,
+    I::Item: Future<Output = Result<T, E>>,
+{
+    let mut pending = futures.into_iter();
+    let mut in_flight: FuturesUnordered<_> = pending.by_ref().take(concurrency.max(1)).collect();
+    let mut errors: Vec<E> = Vec::new();
+    while let Some(result) = in_flight.next().await {
+        match result {
+            Ok(item) => return Ok(item),
+            Err(err) => {
+                errors.push(err);
+                if let Some(future) = pending.next() {
+                    record_fallback(errors.len() + 1);
+                    in_flight.push(future);
+                }
+            }
+        }
+    }
+    Err(errors)
+}
+
+/// Await all TryFutures concurrently, returning the result (and dropping the rest) as soon as one
+/// completes successfully or a vector of all errors, in the order of the futures, if none does.
+async fn first_future_ok_parallel<I, T, E>(futures: I) -> Result<T, Vec<E>>
+where
+    I: IntoIterator,
+    I::Item: Future<Output = Result<T, E>>,
+{
+    let mut in_flight: FuturesUnordered<_> = futures
+        .into_iter()
+        .enumerate()
+        .map(|(i, future)| async move { (i, future.await) })
+        .collect();
+    let mut errors: Vec<(usize, E)> = Vec::new();
+    while let Some((i, result)) = in_flight.next().await {
+        match result {
+            Ok(item) => return Ok(item),
+            Err(err) => {
+                errors.push((i, err));
+                if !in_flight.is_empty() {
+                    record_fallback(errors.len() + 1);
+                }
+            }
+        }
+    }
+    errors.sort_by_key(|&(i, _)| i);
+    Err(errors.into_iter().map(|(_, err)| err).collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use futures::executor::block_on;
-    use futures::future::{ready, Ready};
+    use futures::future::{pending, poll_fn, ready, Ready};
     use std::sync::{atomic, Arc};
+    use std::task::Poll;
 
     #[test]
     fn test_first_ok_ok() {
@@ -74,4 +219,90 @@ mod tests {
         assert_eq!(actual, Ok(1));
         assert_eq!(count.load(atomic::Ordering::Relaxed), 1);
     }
+
+    #[test]
+    fn test_first_ok_hedged_err() {
+        let futures: Vec<Ready<Result<usize, usize>>> =
+            vec![ready(Err(1)), ready(Err(2)), ready(Err(3))];
+        let mut actual = block_on(first_future_ok_hedged(futures, 2)).unwrap_err();
+        actual.sort();
+        assert_eq!(actual, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_first_ok_hedged_window() {
+        let started = Arc::new(atomic::AtomicUsize::new(0));
+        let futures = (0..4).map(|i| {
+            started.fetch_add(1, atomic::Ordering::Relaxed);
+            async move {
+                if i == 0 {
+                    Ok(i)
+                } else {
+                    pending::<()>().await;
+                    Err(i)
+                }
+            }
+        });
+        let actual = block_on(first_future_ok_hedged(futures, 2));
+        assert_eq!(actual, Ok(0));
+        assert_eq!(started.load(atomic::Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_first_ok_hedged_zero_concurrency() {
+        let count = Arc::new(atomic::AtomicUsize::new(0));
+        let futures = vec![
+            bump_count(count.clone()),
+            bump_count(count.clone()),
+            bump_count(count.clone()),
+        ];
+        let actual = block_on(first_future_ok_hedged(futures, 0));
+        assert_eq!(actual, Ok(1));
+        assert_eq!(count.load(atomic::Ordering::Relaxed), 1);
+    }
+
+    async fn yield_now() {
+        let mut yielded = false;
+        poll_fn(|cx| {
+            if yielded {
+                Poll::Ready(())
+            } else {
+                yielded = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    #[test]
+    fn test_first_ok_parallel_all_started() {
+        let started = Arc::new(atomic::AtomicUsize::new(0));
+        let futures = (0..4).map(|i| {
+            started.fetch_add(1, atomic::Ordering::Relaxed);
+            async move {
+                if i == 3 {
+                    Ok(i)
+                } else {
+                    pending::<()>().await;
+                    Err(i)
+                }
+            }
+        });
+        let actual = block_on(first_future_ok_parallel(futures));
+        assert_eq!(actual, Ok(3));
+        assert_eq!(started.load(atomic::Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn test_first_ok_parallel_err() {
+        let futures = (1..4).map(|i| async move {
+            for _ in 0..(4 - i) {
+                yield_now().await;
+            }
+            Err::<usize, usize>(i)
+        });
+        let actual = block_on(first_future_ok_parallel(futures));
+        assert_eq!(actual, Err(vec![1, 2, 3]));
+    }
 }
@@ -1,19 +1,51 @@
+#[cfg(feature = "compression")]
+use std::io::Read;
+use std::time::Duration;
+#[cfg(any(feature = "metrics", feature = "tracing"))]
+use std::time::Instant;
+
 use base64::encode;
-use http::header::{AUTHORIZATION, CONTENT_TYPE};
+use bytes::Bytes;
+#[cfg(feature = "compression")]
+use flate2::read::{GzDecoder, ZlibDecoder};
+use futures::future::{BoxFuture, FutureExt};
+use http::header::{
+    HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE, COOKIE, LOCATION, PROXY_AUTHORIZATION,
+    USER_AGENT,
+};
+#[cfg(feature = "compression")]
+use http::header::{ACCEPT_ENCODING, CONTENT_ENCODING};
 use http::request::Builder;
 use hyper::client::connect::Connect;
-use hyper::client::ResponseFuture;
-use hyper::{Body, Client as Hyper, Method, Request, Uri};
+use hyper::{Body, Client as Hyper, Method, Request, StatusCode, Uri};
+use serde::de::DeserializeOwned;
+use serde_json::{self, Deserializer};
+use tokio::time::timeout;
+#[cfg(feature = "tracing")]
+use tracing::{Instrument, Span};
+
+use crate::client::{AuthMethod, ClusterInfo, Response};
+use crate::connector::host_and_port;
+use crate::error::{ApiError, ConnectionPhase, Error};
+
+/// The User-Agent header sent with each request unless another one is configured.
+const DEFAULT_USER_AGENT: &str = concat!("rust-etcd/", env!("CARGO_PKG_VERSION"));
 
-use crate::client::BasicAuth;
+/// A future returned by `HttpClient` requests.
+pub type ResponseFuture = BoxFuture<'static, Result<HttpResponse, Error>>;
 
 #[derive(Clone, Debug)]
 pub struct HttpClient<C>
 where
     C: Clone + Connect + Sync + Send + 'static,
 {
-    basic_auth: Option<BasicAuth>,
+    auth: AuthMethod,
+    default_headers: HeaderMap,
+    follow_redirects: bool,
     hyper: Hyper<C>,
+    request_timeout: Option<Duration>,
+    track_cluster_info: bool,
+    user_agent: HeaderValue,
 }
 
 impl<C> HttpClient<C>
@@ -21,8 +53,16 @@ where
     C: Clone + Connect + Sync + Send + 'static,
 {
     /// Constructs a new `HttpClient`.
-    pub fn new(hyper: Hyper<C>, basic_auth: Option<BasicAuth>) -> Self {
-        HttpClient { basic_auth, hyper }
+    pub fn new(hyper: Hyper<C>, auth: AuthMethod) -> Self {
+        HttpClient {
+            auth,
+            default_headers: HeaderMap::new(),
+            follow_redirects: false,
+            hyper,
+            request_timeout: None,
+            track_cluster_info: true,
+            user_agent: HeaderValue::from_static(DEFAULT_USER_AGENT),
+        }
     }
 
     /// Makes a DELETE request to etcd.
@@ -45,17 +85,96 @@ where
         self.request_with_body(Method::PUT, uri, body)
     }
 
+    /// Makes a request to etcd with any HTTP method.
+    pub fn raw(&self, method: Method, uri: Uri, body: Option<String>) -> ResponseFuture {
+        match body {
+            Some(body) => self.request_with_body(method, uri, body),
+            None => self.request(method, uri),
+        }
+    }
+
+    /// Makes a long-polling GET request to etcd, which is not subject to the request timeout.
+    pub fn watch(&self, uri: Uri) -> ResponseFuture {
+        let request = self.add_auth_header(Request::builder().method(Method::GET).uri(uri));
+
+        self.send_with_timeout(request.body(Bytes::new()).unwrap(), None)
+    }
+
+    /// Returns the credentials added to each request.
+    pub fn auth(&self) -> &AuthMethod {
+        &self.auth
+    }
+
+    /// Sets the credentials added to each request.
+    pub fn set_auth(&mut self, auth: AuthMethod) {
+        self.auth = auth;
+    }
+
+    /// Returns the headers added to each request.
+    pub fn default_headers(&self) -> &HeaderMap {
+        &self.default_headers
+    }
+
+    /// Sets the headers added to each request.
+    pub fn set_default_headers(&mut self, default_headers: HeaderMap) {
+        self.default_headers = default_headers;
+    }
+
+    /// Returns whether redirects to another member are followed.
+    pub fn follow_redirects(&self) -> bool {
+        self.follow_redirects
+    }
+
+    /// Sets whether redirects to another member are followed.
+    pub fn set_follow_redirects(&mut self, follow_redirects: bool) {
+        self.follow_redirects = follow_redirects;
+    }
+
+    /// Returns the timeout applied to each request.
+    pub fn request_timeout(&self) -> Option<Duration> {
+        self.request_timeout
+    }
+
+    /// Sets the timeout applied to each request.
+    pub fn set_request_timeout(&mut self, request_timeout: Option<Duration>) {
+        self.request_timeout = request_timeout;
+    }
+
+    /// Returns whether cluster information is parsed from response headers.
+    pub fn track_cluster_info(&self) -> bool {
+        self.track_cluster_info
+    }
+
+    /// Sets whether cluster information is parsed from response headers.
+    pub fn set_track_cluster_info(&mut self, track_cluster_info: bool) {
+        self.track_cluster_info = track_cluster_info;
+    }
+
+    /// Returns the User-Agent header sent with each request.
+    pub fn user_agent(&self) -> &HeaderValue {
+        &self.user_agent
+    }
+
+    /// Sets the User-Agent header sent with each request.
+    pub fn set_user_agent(&mut self, user_agent: HeaderValue) {
+        self.user_agent = user_agent;
+    }
+
     // private
 
     /// Adds the Authorization HTTP header to a request if a credentials were supplied.
     fn add_auth_header<'a>(&self, request: Builder) -> http::request::Builder {
-        if let Some(ref basic_auth) = self.basic_auth {
-            let auth = format!("{}:{}", basic_auth.username, basic_auth.password);
-            let header_value = format!("Basic {}", encode(&auth));
+        match self.auth {
+            AuthMethod::Basic(ref basic_auth) => {
+                let auth = format!("{}:{}", basic_auth.username, basic_auth.password);
+                let header_value = format!("Basic {}", encode(&auth));
 
-            request.header(AUTHORIZATION, header_value)
-        } else {
-            request
+                request.header(AUTHORIZATION, header_value)
+            }
+            AuthMethod::Bearer(ref token) => {
+                request.header(AUTHORIZATION, format!("Bearer {}", token))
+            }
+            AuthMethod::None => request,
         }
     }
 
@@ -63,7 +182,7 @@ where
     fn request(&self, method: Method, uri: Uri) -> ResponseFuture {
         let request = self.add_auth_header(Request::builder().method(method).uri(uri));
 
-        self.hyper.request(request.body(Body::empty()).unwrap())
+        self.send(request.body(Bytes::new()).unwrap())
     }
 
     /// Makes a request with an HTTP body to etcd.
@@ -75,6 +194,565 @@ where
                 .header(CONTENT_TYPE, "application/x-www-form-urlencoded"),
         );
 
-        self.hyper.request(request.body(Body::from(body)).unwrap())
+        self.send(request.body(Bytes::from(body)).unwrap())
+    }
+
+    /// Sends a request and reads the full response within the request timeout.
+    fn send(&self, request: Request<Bytes>) -> ResponseFuture {
+        self.send_with_timeout(request, self.request_timeout)
+    }
+
+    /// Sends a request and reads the full response, failing with `Error::Timeout` if that takes
+    /// longer than the given timeout.
+    ///
+    /// Errors from hyper are reported as `Error::Connection`, classified by where in the request
+    /// they occurred. A redirect with a `Location` header is either followed, up to
+    /// `MAX_REDIRECTS` times, or reported as `Error::Redirect`. Only temporary and permanent
+    /// redirects of write requests are followed, since those keep the method and body, and
+    /// credentials are dropped when a redirect leads to a different scheme, host or port.
+    fn send_with_timeout(
+        &self,
+        mut request: Request<Bytes>,
+        request_timeout: Option<Duration>,
+    ) -> ResponseFuture {
+        // Headers set by the request itself take precedence over the default headers.
+        for name in self.default_headers.keys() {
+            if !request.headers().contains_key(name) {
+                for value in self.default_headers.get_all(name) {
+                    request.headers_mut().append(name.clone(), value.clone());
+                }
+            }
+        }
+
+        if !request.headers().contains_key(USER_AGENT) {
+            request
+                .headers_mut()
+                .insert(USER_AGENT, self.user_agent.clone());
+        }
+
+        #[cfg(feature = "compression")]
+        {
+            if !request.headers().contains_key(ACCEPT_ENCODING) {
+                request
+                    .headers_mut()
+                    .insert(ACCEPT_ENCODING, HeaderValue::from_static("gzip, deflate"));
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!(
+            "etcd_request",
+            method = %request.method(),
+            uri = %request.uri(),
+            elapsed_ms = tracing::field::Empty,
+        );
+
+        #[cfg(feature = "metrics")]
+        let labels = (endpoint_label(request.uri()), request.method().to_string());
+
+        #[cfg(feature = "tracing")]
+        let uri = request.uri().clone();
+        let hyper = self.hyper.clone();
+        let follow_redirects = self.follow_redirects;
+        let track_cluster_info = self.track_cluster_info;
+
+        let connection_error = move |phase, error| {
+            let error = Error::connection(phase, error);
+
+            #[cfg(feature = "tracing")]
+            {
+                if let Error::Connection {
+                    phase,
+                    kind,
+                    ref source,
+                } = error
+                {
+                    tracing::warn!(
+                        uri = %uri,
+                        phase = %phase,
+                        kind = ?kind,
+                        error = %source,
+                        "connection to etcd member failed",
+                    );
+                }
+            }
+
+            error
+        };
+
+        let work = async move {
+            let (parts, body) = request.into_parts();
+            let mut headers = parts.headers;
+            let mut uri = parts.uri;
+            let mut redirects = 0;
+
+            let response = loop {
+                let mut request = Request::new(Body::from(body.clone()));
+                *request.method_mut() = parts.method.clone();
+                *request.uri_mut() = uri.clone();
+                *request.headers_mut() = headers.clone();
+
+                let response = hyper
+                    .request(request)
+                    .await
+                    .map_err(|error| connection_error(ConnectionPhase::Headers, error))?;
+
+                let location = match response.headers().get(LOCATION) {
+                    Some(location) if response.status().is_redirection() => {
+                        String::from_utf8_lossy(location.as_bytes()).into_owned()
+                    }
+                    _ => break response,
+                };
+
+                let followable = follow_redirects
+                    && !parts.method.is_safe()
+                    && (response.status() == StatusCode::TEMPORARY_REDIRECT
+                        || response.status() == StatusCode::PERMANENT_REDIRECT);
+
+                match resolve_location(&uri, &location) {
+                    Some(next) if followable && redirects < MAX_REDIRECTS => {
+                        if !is_same_origin(&uri, &next) {
+                            for name in &[AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION] {
+                                headers.remove(name);
+                            }
+                        }
+
+                        uri = next;
+                        redirects += 1;
+                    }
+                    _ => return Err(Error::Redirect { location }),
+                }
+            };
+
+            let status = response.status();
+            let headers = response.headers().clone();
+            let cluster_info = if track_cluster_info {
+                ClusterInfo::from(&headers)
+            } else {
+                ClusterInfo::default()
+            };
+            #[cfg(feature = "compression")]
+            let content_encoding = response.headers().get(CONTENT_ENCODING).cloned();
+            let body = hyper::body::to_bytes(response.into_body())
+                .await
+                .map_err(|error| connection_error(ConnectionPhase::Body, error))?;
+            #[cfg(feature = "compression")]
+            let body = decode_body(content_encoding.as_ref(), body)?;
+
+            Ok(HttpResponse {
+                body,
+                cluster_info,
+                headers,
+                status,
+            })
+        };
+
+        let work = async move {
+            match request_timeout {
+                Some(duration) => timeout(duration, work).await.unwrap_or(Err(Error::Timeout)),
+                None => work.await,
+            }
+        };
+
+        #[cfg(feature = "tracing")]
+        let work = async move {
+            let started = Instant::now();
+
+            tracing::debug!("sending request to etcd");
+
+            let result = work.await;
+            let elapsed = started.elapsed();
+
+            Span::current().record("elapsed_ms", elapsed.as_millis() as u64);
+
+            match result {
+                Ok(ref response) => tracing::debug!(
+                    status = %response.status,
+                    elapsed = ?elapsed,
+                    "received response from etcd",
+                ),
+                Err(ref error) => tracing::debug!(
+                    error = %error,
+                    elapsed = ?elapsed,
+                    "request to etcd failed",
+                ),
+            }
+
+            result
+        }
+        .instrument(span);
+
+        #[cfg(feature = "metrics")]
+        let work = async move {
+            let started = Instant::now();
+            let result = work.await;
+            let (endpoint, method) = labels;
+
+            record_request(endpoint, method, &result, started.elapsed());
+
+            result
+        };
+
+        work.boxed()
+    }
+}
+
+/// Returns the scheme and authority of a request URI, identifying the cluster member it was sent
+/// to in metrics.
+#[cfg(feature = "metrics")]
+fn endpoint_label(uri: &Uri) -> String {
+    match (uri.scheme_str(), uri.authority()) {
+        (Some(scheme), Some(authority)) => format!("{}://{}", scheme, authority),
+        _ => uri.to_string(),
+    }
+}
+
+/// Records the metrics for a request to a cluster member. See the crate documentation for the
+/// metric names and labels.
+#[cfg(feature = "metrics")]
+fn record_request(
+    endpoint: String,
+    method: String,
+    result: &Result<HttpResponse, Error>,
+    elapsed: Duration,
+) {
+    let status = match *result {
+        Ok(ref response) => response.status.as_str().to_owned(),
+        Err(_) => "error".to_owned(),
+    };
+
+    metrics::counter!(
+        "etcd_client_requests_total",
+        "endpoint" => endpoint.clone(),
+        "method" => method.clone(),
+        "status" => status,
+    )
+    .increment(1);
+
+    metrics::histogram!(
+        "etcd_client_request_duration_seconds",
+        "endpoint" => endpoint.clone(),
+        "method" => method,
+    )
+    .record(elapsed.as_secs_f64());
+
+    if let Err(ref error) = *result {
+        metrics::counter!(
+            "etcd_client_request_errors_total",
+            "endpoint" => endpoint,
+            "kind" => error_kind(error),
+            "phase" => error_phase(error),
+        )
+        .increment(1);
+    }
+}
+
+/// Categorizes an error returned while making a request, for the `kind` label of
+/// `etcd_client_request_errors_total`.
+#[cfg(feature = "metrics")]
+fn error_kind(error: &Error) -> &'static str {
+    match *error {
+        Error::Connection { .. } => "connection",
+        #[cfg(feature = "compression")]
+        Error::Decompression(_) => "decompression",
+        Error::Http(_) => "http",
+        Error::Redirect { .. } => "redirect",
+        Error::Timeout => "timeout",
+        _ => "other",
+    }
+}
+
+/// Returns the stage of the request at which a connection error occurred, for the `phase` label
+/// of `etcd_client_request_errors_total`.
+#[cfg(feature = "metrics")]
+fn error_phase(error: &Error) -> &'static str {
+    match *error {
+        Error::Connection { phase, .. } => match phase {
+            ConnectionPhase::Connect => "connect",
+            ConnectionPhase::TlsHandshake => "tls_handshake",
+            ConnectionPhase::Headers => "headers",
+            ConnectionPhase::Body => "body",
+        },
+        _ => "none",
+    }
+}
+
+/// The maximum number of redirects followed for a single request.
+const MAX_REDIRECTS: usize = 5;
+
+/// Resolves the `Location` header of a redirect against the URI of the request that was
+/// redirected, returning `None` if it isn't a valid URI.
+fn resolve_location(uri: &Uri, location: &str) -> Option<Uri> {
+    let location: Uri = location.parse().ok()?;
+
+    if location.scheme().is_some() {
+        return Some(location);
+    }
+
+    let mut parts = location.into_parts();
+    parts.scheme = uri.scheme().cloned();
+    parts.authority = uri.authority().cloned();
+
+    Uri::from_parts(parts).ok()
+}
+
+/// Checks whether two URIs have the same scheme, host and port.
+fn is_same_origin(uri: &Uri, other: &Uri) -> bool {
+    uri.scheme() == other.scheme() && host_and_port(uri) == host_and_port(other)
+}
+
+/// Decompresses a response body according to its `Content-Encoding` header, returning it as is if
+/// it isn't compressed.
+#[cfg(feature = "compression")]
+fn decode_body(content_encoding: Option<&HeaderValue>, body: Bytes) -> Result<Bytes, Error> {
+    let encoding = match content_encoding.and_then(|value| value.to_str().ok()) {
+        Some(encoding) => encoding.trim().to_ascii_lowercase(),
+        None => return Ok(body),
+    };
+
+    let mut decoded = Vec::new();
+
+    match encoding.as_str() {
+        "gzip" | "x-gzip" => GzDecoder::new(&body[..]).read_to_end(&mut decoded),
+        "deflate" => ZlibDecoder::new(&body[..]).read_to_end(&mut decoded),
+        _ => return Ok(body),
+    }
+    .map_err(Error::Decompression)?;
+
+    Ok(Bytes::from(decoded))
+}
+
+/// A fully read HTTP response from etcd.
+#[derive(Debug)]
+pub struct HttpResponse {
+    body: Bytes,
+    cluster_info: ClusterInfo,
+    headers: HeaderMap,
+    status: StatusCode,
+}
+
+impl HttpResponse {
+    /// Returns the HTTP status code of the response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Deserializes the response body as JSON.
+    ///
+    /// Only the first complete JSON value in the body is read, so any trailing bytes appended by
+    /// a misbehaving intermediary are ignored.
+    pub fn json<T>(&self) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let mut values = Deserializer::from_slice(&self.body).into_iter::<T>();
+
+        match values.next() {
+            Some(result) => result.map_err(Error::from),
+            // An empty body is reported the same way as any other invalid JSON.
+            None => serde_json::from_slice(&self.body).map_err(Error::from),
+        }
+    }
+
+    /// Deserializes the response body as an etcd API error, falling back to
+    /// `Error::UnexpectedStatus` if the body isn't one.
+    ///
+    /// This keeps the status code and the raw body when the response didn't come from etcd
+    /// itself, such as an HTML error page from a reverse proxy in front of the cluster.
+    pub fn api_error_or_status(&self) -> Error {
+        match self.json::<ApiError>() {
+            Ok(error) => Error::Api(error),
+            Err(_) => Error::UnexpectedStatus {
+                status: self.status,
+                body: if self.body.is_empty() {
+                    None
+                } else {
+                    Some(String::from_utf8_lossy(&self.body).into_owned())
+                },
+            },
+        }
+    }
+
+    /// Splits the response into its status code, body, and cluster information.
+    pub fn into_parts(self) -> (StatusCode, Bytes, ClusterInfo) {
+        (self.status, self.body, self.cluster_info)
+    }
+
+    /// Wraps the given data in a `Response` carrying this response's cluster information, status
+    /// and headers.
+    pub fn into_response<T>(self, data: T) -> Response<T> {
+        Response {
+            data,
+            cluster_info: self.cluster_info,
+            headers: self.headers,
+            is_stale: false,
+            status: self.status,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use http::header::HeaderMap;
+    use hyper::{StatusCode, Uri};
+    use serde_derive::Deserialize;
+
+    use super::{resolve_location, HttpResponse};
+    use crate::client::ClusterInfo;
+    use crate::error::Error;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Value {
+        value: String,
+    }
+
+    fn response(body: &'static str) -> HttpResponse {
+        HttpResponse {
+            body: Bytes::from_static(body.as_bytes()),
+            cluster_info: ClusterInfo::default(),
+            headers: HeaderMap::new(),
+            status: StatusCode::OK,
+        }
+    }
+
+    #[test]
+    fn test_json_trailing_bytes() {
+        let expected = Value {
+            value: "foo".to_owned(),
+        };
+
+        assert_eq!(
+            response("{\"value\":\"foo\"}").json::<Value>().unwrap(),
+            expected
+        );
+        assert_eq!(
+            response("{\"value\":\"foo\"}\n").json::<Value>().unwrap(),
+            expected
+        );
+        assert_eq!(
+            response("{\"value\":\"foo\"}<html>garbage</html>")
+                .json::<Value>()
+                .unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_json_empty() {
+        assert!(response("").json::<Value>().is_err());
+    }
+
+    #[test]
+    fn test_api_error_or_status() {
+        let mut error = response(r#"{"errorCode":105,"message":"Key already exists"}"#);
+        error.status = StatusCode::PRECONDITION_FAILED;
+
+        match error.api_error_or_status() {
+            Error::Api(error) => assert_eq!(error.error_code, 105),
+            error => panic!("unexpected error: {:?}", error),
+        }
+
+        let mut error = response(r#"{"message":"auth: Insufficient credentials"}"#);
+        error.status = StatusCode::UNAUTHORIZED;
+
+        match error.api_error_or_status() {
+            Error::UnexpectedStatus { status, body } => {
+                assert_eq!(status, StatusCode::UNAUTHORIZED);
+                assert_eq!(
+                    body.unwrap(),
+                    r#"{"message":"auth: Insufficient credentials"}"#
+                );
+            }
+            error => panic!("unexpected error: {:?}", error),
+        }
+
+        let mut error = response("");
+        error.status = StatusCode::BAD_GATEWAY;
+
+        match error.api_error_or_status() {
+            Error::UnexpectedStatus { status, body } => {
+                assert_eq!(status, StatusCode::BAD_GATEWAY);
+                assert_eq!(body, None);
+            }
+            error => panic!("unexpected error: {:?}", error),
+        }
+    }
+
+    #[test]
+    fn test_resolve_location() {
+        let uri: Uri = "http://follower:2379/v2/members".parse().unwrap();
+
+        assert_eq!(
+            resolve_location(&uri, "http://leader:2379/v2/members").unwrap(),
+            "http://leader:2379/v2/members"
+        );
+        assert_eq!(
+            resolve_location(&uri, "/v2/members?x=1").unwrap(),
+            "http://follower:2379/v2/members?x=1"
+        );
+        assert!(resolve_location(&uri, "not a uri").is_none());
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_decode_body() {
+        use std::io::Write;
+
+        use flate2::write::{GzEncoder, ZlibEncoder};
+        use flate2::Compression;
+        use http::header::HeaderValue;
+
+        use super::decode_body;
+
+        let json = r#"{"value":"foo"}"#;
+        let expected = Value {
+            value: "foo".to_owned(),
+        };
+
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(json.as_bytes()).unwrap();
+        let gzip = Bytes::from(gzip.finish().unwrap());
+
+        let mut deflate = ZlibEncoder::new(Vec::new(), Compression::default());
+        deflate.write_all(json.as_bytes()).unwrap();
+        let deflate = Bytes::from(deflate.finish().unwrap());
+
+        for (encoding, body) in vec![
+            (Some("gzip"), gzip.clone()),
+            (Some("deflate"), deflate),
+            (Some("identity"), Bytes::from(json)),
+            (None, Bytes::from(json)),
+        ] {
+            let encoding = encoding.map(HeaderValue::from_static);
+            let decoded = HttpResponse {
+                body: decode_body(encoding.as_ref(), body).unwrap(),
+                cluster_info: ClusterInfo::default(),
+                headers: HeaderMap::new(),
+                status: StatusCode::OK,
+            };
+
+            assert_eq!(decoded.json::<Value>().unwrap(), expected);
+        }
+
+        let gzip_header = HeaderValue::from_static("gzip");
+        match decode_body(Some(&gzip_header), gzip.slice(..gzip.len() / 2)) {
+            Err(Error::Decompression(_)) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_endpoint_label() {
+        use super::endpoint_label;
+
+        let uri: Uri = "http://etcd:2379/v2/keys/foo?recursive=true"
+            .parse()
+            .unwrap();
+        assert_eq!(endpoint_label(&uri), "http://etcd:2379");
+
+        let uri: Uri = "/v2/keys/foo".parse().unwrap();
+        assert_eq!(endpoint_label(&uri), "/v2/keys/foo");
     }
 }
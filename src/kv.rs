@@ -5,29 +5,47 @@
 //! of key-value pairs. For example, "/foo" is a key if it has a value, but it is a directory if
 //! there other other key-value pairs "underneath" it, such as "/foo/bar".
 
-use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::hash::{BuildHasher, Hasher};
 use std::str::FromStr;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use bytes::buf::BufExt;
-use futures::future::{ready, Future, FutureExt, TryFutureExt};
+use futures::channel::oneshot::{channel, Receiver};
+use futures::future::{ready, AbortHandle, Future, FutureExt, Shared, TryFutureExt};
+use futures::stream::{self, Abortable, Stream, StreamExt};
 use hyper::client::connect::Connect;
-use hyper::{StatusCode, Uri};
+use hyper::{Method, StatusCode, Uri};
+use serde::de::DeserializeOwned;
 use serde_derive::{Deserialize, Serialize};
-use serde_json;
-use tokio::time::timeout;
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tokio::time::{delay_for, timeout};
 use url::Url;
 
 pub use crate::error::WatchError;
 
-use crate::client::{Client, ClusterInfo, Response};
-use crate::error::{ApiError, Error};
-use crate::first_ok::first_ok;
+use crate::client::{normalize_endpoint, Client, ClusterInfo, Response};
+use crate::election::{self, Election};
+use crate::error::{ApiError, Error, EtcdErrorCode};
 use crate::options::{
-    ComparisonConditions, DeleteOptions, GetOptions as InternalGetOptions, SetOptions,
+    ComparisonConditions, DeleteOptions, GetOptions as InternalGetOptions,
+    SetOptions as InternalSetOptions,
 };
 use url::form_urlencoded::Serializer;
 
+/// How long `kv::get_at_index` waits for etcd to find a change to a node that no longer exists in
+/// its event history. Changes that are in the history are returned right away.
+const HISTORY_LOOKUP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The number of times `kv::increment` tries to write the new value before giving up.
+const MAX_INCREMENT_ATTEMPTS: u32 = 10;
+
+/// The shortest time a renewal task waits between renewals of a key, however short its TTL.
+const MIN_RENEWAL_INTERVAL: Duration = Duration::from_millis(100);
+
 /// Information about the result of a successful key-value API operation.
 #[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct KeyValueInfo {
@@ -40,39 +58,444 @@ pub struct KeyValueInfo {
     pub prev_node: Option<Node>,
 }
 
+impl KeyValueInfo {
+    /// Returns a guard that allows deleting the node with `kv::delete_if` only if it hasn't been
+    /// modified since this response was returned.
+    ///
+    /// Returns `None` if the node has no modified index.
+    pub fn delete_guard(&self) -> Option<DeleteGuard> {
+        self.node
+            .modified_index
+            .map(|modified_index| DeleteGuard { modified_index })
+    }
+
+    /// Returns the index at which the node was created, or `None` if etcd didn't report it.
+    pub fn created_index(&self) -> Option<u64> {
+        self.node.created_index
+    }
+
+    /// Returns the index at which the node was last modified, or `None` if etcd didn't report it.
+    ///
+    /// After a write, this is the index to pass to `kv::compare_and_swap` or to start a
+    /// `kv::watch` from (plus one) to see only later changes.
+    pub fn modified_index(&self) -> Option<u64> {
+        self.node.modified_index
+    }
+
+    /// Returns the value of the node after the action, or `None` if the action removed it or it
+    /// is a directory.
+    pub fn current_value(&self) -> Option<&str> {
+        match self.action {
+            Action::CompareAndDelete | Action::Delete | Action::Expire => None,
+            _ => self.node.value.as_deref(),
+        }
+    }
+
+    /// Returns the value of the node before the action, or `None` if it didn't exist before, is a
+    /// directory, or etcd didn't report its previous state.
+    pub fn previous_value(&self) -> Option<&str> {
+        self.prev_node
+            .as_ref()
+            .and_then(|node| node.value.as_deref())
+    }
+
+    /// Returns true if the action changed the value of the node, including by creating or
+    /// removing it.
+    pub fn value_changed(&self) -> bool {
+        self.previous_value() != self.current_value()
+    }
+
+    /// Returns the key of the node relative to `prefix`, such as the directory given to a
+    /// recursive `kv::watch`.
+    ///
+    /// For example, a change to "/config/db/host" relative to "/config" is "db/host". Leading and
+    /// trailing slashes in `prefix` are ignored, and a change to the prefix itself is the empty
+    /// string. Returns `None` if the node has no key or it isn't under `prefix`.
+    pub fn changed_key_relative_to(&self, prefix: &str) -> Option<&str> {
+        let key = self.node.key.as_ref()?.trim_start_matches('/');
+        let prefix = prefix.trim_matches('/');
+
+        if prefix.is_empty() {
+            return Some(key);
+        }
+
+        if !key.starts_with(prefix) {
+            return None;
+        }
+
+        match &key[prefix.len()..] {
+            "" => Some(""),
+            rest if rest.starts_with('/') => Some(&rest[1..]),
+            _ => None,
+        }
+    }
+
+    /// Returns why the node was removed, or `None` if the action didn't remove it.
+    pub fn deletion_reason(&self) -> Option<DeletionReason> {
+        match self.action {
+            Action::CompareAndDelete => Some(DeletionReason::CompareAndDeleted),
+            Action::Delete => Some(DeletionReason::Deleted),
+            Action::Expire => Some(DeletionReason::Expired),
+            _ => None,
+        }
+    }
+
+    /// Returns the keys removed by the action, or an empty list if the action didn't remove the
+    /// node.
+    ///
+    /// The node's previous state is walked recursively, returning the key of every node in it
+    /// that has no children: each key-value pair, at any depth, and each empty directory. This
+    /// is useful for invalidating cached entries after a recursive delete.
+    ///
+    /// etcd doesn't include the contents of a deleted directory in its previous state, so the
+    /// directory's own key is returned to stand for everything under it unless the contents were
+    /// reported. To know every key that a recursive delete removes, read the directory
+    /// recursively beforehand.
+    pub fn deleted_keys(&self) -> Vec<String> {
+        if self.deletion_reason().is_none() {
+            return vec![];
+        }
+
+        let mut keys = vec![];
+
+        match self.prev_node {
+            Some(ref node) => collect_leaf_keys(node, &mut keys),
+            None => keys.extend(self.node.key.clone()),
+        }
+
+        keys
+    }
+
+    /// Returns how much longer the node has to live, based on its expiration time.
+    ///
+    /// This can be compared with the TTL that was requested to confirm that etcd applied it as
+    /// expected. A node that has already expired has an effective TTL of zero.
+    ///
+    /// Returns `None` if the node has no expiration or it could not be parsed.
+    pub fn effective_ttl(&self) -> Option<Duration> {
+        let expiration = self.node.expiration_time()?;
+
+        Some(
+            expiration
+                .duration_since(SystemTime::now())
+                .unwrap_or_else(|_| Duration::from_secs(0)),
+        )
+    }
+}
+
+/// A condition for `kv::delete_if`, capturing the modified index of a node as it was read.
+///
+/// Obtained from `KeyValueInfo::delete_guard`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct DeleteGuard {
+    modified_index: u64,
+}
+
+impl DeleteGuard {
+    /// Returns the modified index the node must still be at to be deleted.
+    pub fn modified_index(&self) -> u64 {
+        self.modified_index
+    }
+}
+
+/// The differences between two snapshots of a directory, as computed by `kv::diff`.
+///
+/// Each list is sorted by key.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct DirDiff {
+    /// The keys of nodes that are only in the new snapshot.
+    pub added: Vec<String>,
+    /// The keys of nodes that are only in the old snapshot.
+    pub removed: Vec<String>,
+    /// The key, old value, and new value of each node whose value changed.
+    ///
+    /// A directory has no value, so when a directory was replaced by a key or vice versa, the
+    /// directory's side is given as an empty string.
+    pub changed: Vec<(String, String, String)>,
+}
+
+impl DirDiff {
+    /// Returns true if the snapshots are the same.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// A single guarded change to one node: a condition on the node's current state, and a set or
+/// delete that is applied only if the condition holds.
+///
+/// The condition is checked and the change applied atomically by etcd, using a compare-and-swap,
+/// compare-and-delete, or create. That atomicity only covers the one node named by the operation:
+/// etcd v2 has no transactions, so a condition on one key can't guard a change to another, and
+/// two `AtomicOp`s executed one after the other are not atomic together.
+///
+/// ```no_run
+/// # use etcd::{Client, kv};
+/// # async fn example(client: Client<hyper::client::HttpConnector>) {
+/// let response = kv::AtomicOp::set("/config/mode", "active")
+///     .if_value("standby")
+///     .execute(&client)
+///     .await
+///     .unwrap();
+///
+/// if let kv::AtomicOpOutcome::GuardFailed = response.data {
+///     // Another process changed the mode first.
+/// }
+/// # }
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct AtomicOp {
+    absent: bool,
+    key: String,
+    modified_index: Option<u64>,
+    ttl: Option<u64>,
+    value: Option<String>,
+    write: Option<String>,
+}
+
+impl AtomicOp {
+    /// Starts an operation that sets the value of a key-value pair.
+    pub fn set(key: &str, value: &str) -> Self {
+        AtomicOp {
+            absent: false,
+            key: key.to_owned(),
+            modified_index: None,
+            ttl: None,
+            value: None,
+            write: Some(value.to_owned()),
+        }
+    }
+
+    /// Starts an operation that deletes a key-value pair.
+    pub fn delete(key: &str) -> Self {
+        AtomicOp {
+            write: None,
+            ..AtomicOp::set(key, "")
+        }
+    }
+
+    /// Requires the node not to exist. Only valid for a set.
+    pub fn if_absent(mut self) -> Self {
+        self.absent = true;
+        self
+    }
+
+    /// Requires the node to be at the given modified index.
+    pub fn if_modified_index(mut self, modified_index: u64) -> Self {
+        self.modified_index = Some(modified_index);
+        self
+    }
+
+    /// Requires the node to have the given value.
+    pub fn if_value(mut self, value: &str) -> Self {
+        self.value = Some(value.to_owned());
+        self
+    }
+
+    /// Sets the number of seconds after which the node written by a set expires.
+    pub fn ttl(mut self, ttl: u64) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Executes the operation.
+    ///
+    /// A condition that doesn't hold, including a value or modified index condition on a node
+    /// that doesn't exist, is reported as `AtomicOpOutcome::GuardFailed` rather than as an error.
+    /// The response then carries only the etcd index reported with the failure.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidConditions` if no condition was given, if `if_absent` was
+    /// combined with another condition, or if a delete requires the node to be absent. Otherwise
+    /// fails for any reason other than the condition not holding.
+    pub async fn execute<C>(
+        &self,
+        client: &Client<C>,
+    ) -> Result<Response<AtomicOpOutcome>, Vec<Error>>
+    where
+        C: Clone + Connect + Sync + Send,
+    {
+        let compared = self.value.is_some() || self.modified_index.is_some();
+        let value = self.value.as_deref();
+
+        // Exactly one of the two kinds of condition is required.
+        if self.absent == compared {
+            return Err(vec![Error::InvalidConditions]);
+        }
+
+        let not_found_or_failed = &[EtcdErrorCode::KeyNotFound, EtcdErrorCode::CompareFailed];
+
+        let (result, guard_error_codes): (_, &[EtcdErrorCode]) = match self.write {
+            Some(ref write) if self.absent => (
+                create(client, &self.key, write, self.ttl).await,
+                &[EtcdErrorCode::KeyAlreadyExists],
+            ),
+            None if self.absent => return Err(vec![Error::InvalidConditions]),
+            Some(ref write) => {
+                let result = compare_and_swap(
+                    client,
+                    &self.key,
+                    write,
+                    self.ttl,
+                    value,
+                    self.modified_index,
+                )
+                .await;
+
+                (result, not_found_or_failed)
+            }
+            None => {
+                let result =
+                    compare_and_delete(client, &self.key, value, self.modified_index).await;
+
+                (result, not_found_or_failed)
+            }
+        };
+
+        let errors = match result {
+            Ok(response) => {
+                return Ok(Response {
+                    data: AtomicOpOutcome::Applied(Box::new(response.data)),
+                    cluster_info: response.cluster_info,
+                    headers: response.headers,
+                    is_stale: response.is_stale,
+                    status: response.status,
+                })
+            }
+            Err(errors) => errors,
+        };
+
+        let failed_at = errors.iter().find_map(|error| match error {
+            Error::Api(error) if guard_error_codes.contains(&error.code()) => Some(error.index),
+            _ => None,
+        });
+
+        match failed_at {
+            Some(index) => {
+                let cluster_info = ClusterInfo {
+                    etcd_index: index,
+                    ..Default::default()
+                };
+
+                Ok(Response::new(AtomicOpOutcome::GuardFailed, cluster_info))
+            }
+            None => Err(errors),
+        }
+    }
+}
+
+/// The outcome of executing an `AtomicOp`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum AtomicOpOutcome {
+    /// The condition held and the change was applied.
+    Applied(Box<KeyValueInfo>),
+    /// The condition didn't hold, so nothing was changed.
+    GuardFailed,
+}
+
 /// The type of action that was taken in response to a key value API request.
 ///
 /// "Node" refers to the key or directory being acted upon.
-#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Action {
     /// Atomic deletion of a node based on previous state.
-    #[serde(rename = "compareAndDelete")]
     CompareAndDelete,
     /// Atomtic update of a node based on previous state.
-    #[serde(rename = "compareAndSwap")]
     CompareAndSwap,
     /// Creation of a node that didn't previously exist.
-    #[serde(rename = "create")]
     Create,
     /// Deletion of a node.
-    #[serde(rename = "delete")]
     Delete,
     /// Expiration of a node.
-    #[serde(rename = "expire")]
     Expire,
     /// Retrieval of a node.
-    #[serde(rename = "get")]
     Get,
     /// Assignment of a node, which may have previously existed.
-    #[serde(rename = "set")]
     Set,
     /// Update of an existing node.
-    #[serde(rename = "update")]
     Update,
+    /// An action not known to this library, such as one reported by an etcd-compatible server
+    /// that names its actions differently. Contains the action string as returned by the server.
+    Unknown(String),
+}
+
+impl Action {
+    /// Returns the name etcd uses for the action.
+    pub fn as_str(&self) -> &str {
+        match *self {
+            Action::CompareAndDelete => "compareAndDelete",
+            Action::CompareAndSwap => "compareAndSwap",
+            Action::Create => "create",
+            Action::Delete => "delete",
+            Action::Expire => "expire",
+            Action::Get => "get",
+            Action::Set => "set",
+            Action::Update => "update",
+            Action::Unknown(ref action) => action,
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Action {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let action = String::deserialize(deserializer)?;
+
+        Ok(match action.as_str() {
+            "compareAndDelete" => Action::CompareAndDelete,
+            "compareAndSwap" => Action::CompareAndSwap,
+            "create" => Action::Create,
+            "delete" => Action::Delete,
+            "expire" => Action::Expire,
+            "get" => Action::Get,
+            "set" => Action::Set,
+            "update" => Action::Update,
+            _ => Action::Unknown(action),
+        })
+    }
+}
+
+impl serde::Serialize for Action {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// Why a node was removed, as returned by `KeyValueInfo::deletion_reason`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum DeletionReason {
+    /// The node was deleted by `kv::compare_and_delete` or one of the helpers built on it.
+    CompareAndDeleted,
+    /// The node was deleted unconditionally.
+    Deleted,
+    /// The node's TTL ran out.
+    Expired,
+}
+
+/// A direct child of a directory, as returned by `kv::list_dir`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct DirEntry {
+    /// The full key of the child.
+    pub key: String,
+    /// Whether the child is a directory.
+    pub is_dir: bool,
+    /// The value of the child, or `None` if it is a directory.
+    pub value: Option<String>,
 }
 
 /// An etcd key or directory.
+///
+/// Values that aren't valid UTF-8, such as those written by other tools, don't cause
+/// deserialization to fail. Instead, invalid sequences in `value` are replaced with U+FFFD, and
+/// the original bytes are kept in `raw_value`.
 #[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(from = "RawNode")]
 pub struct Node {
     /// The new value of the etcd creation index.
     #[serde(rename = "createdIndex")]
@@ -92,6 +515,172 @@ pub struct Node {
     pub ttl: Option<i64>,
     /// The value of the key.
     pub value: Option<String>,
+    /// The original bytes of the value, if it was not valid UTF-8.
+    #[serde(skip_serializing)]
+    pub raw_value: Option<Vec<u8>>,
+}
+
+/// The representation of a `Node` in JSON, before the value is checked for valid UTF-8.
+#[derive(Deserialize)]
+struct RawNode {
+    #[serde(rename = "createdIndex")]
+    created_index: Option<u64>,
+    dir: Option<bool>,
+    expiration: Option<String>,
+    key: Option<String>,
+    #[serde(rename = "modifiedIndex")]
+    modified_index: Option<u64>,
+    nodes: Option<Vec<Node>>,
+    ttl: Option<i64>,
+    value: Option<RawValue>,
+}
+
+impl From<RawNode> for Node {
+    fn from(raw: RawNode) -> Self {
+        let (value, raw_value) = match raw.value {
+            Some(RawValue(bytes)) => match String::from_utf8(bytes) {
+                Ok(value) => (Some(value), None),
+                Err(error) => {
+                    let bytes = error.into_bytes();
+
+                    (
+                        Some(String::from_utf8_lossy(&bytes).into_owned()),
+                        Some(bytes),
+                    )
+                }
+            },
+            None => (None, None),
+        };
+
+        Node {
+            created_index: raw.created_index,
+            dir: raw.dir,
+            expiration: raw.expiration,
+            key: raw.key,
+            modified_index: raw.modified_index,
+            nodes: raw.nodes,
+            ttl: raw.ttl,
+            value,
+            raw_value,
+        }
+    }
+}
+
+/// The bytes of a JSON string, read without checking that they are valid UTF-8.
+struct RawValue(Vec<u8>);
+
+impl<'de> serde::Deserialize<'de> for RawValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct RawValueVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for RawValueVisitor {
+            type Value = RawValue;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                formatter.write_str("a string")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<RawValue, E> {
+                Ok(RawValue(value.as_bytes().to_vec()))
+            }
+
+            fn visit_bytes<E>(self, value: &[u8]) -> Result<RawValue, E> {
+                Ok(RawValue(value.to_vec()))
+            }
+
+            fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<RawValue, E> {
+                Ok(RawValue(value))
+            }
+        }
+
+        deserializer.deserialize_bytes(RawValueVisitor)
+    }
+}
+
+impl Node {
+    /// Returns the value of the key as bytes.
+    ///
+    /// Unlike `value`, this returns the value exactly as it was stored, even if it's not valid
+    /// UTF-8.
+    pub fn value_bytes(&self) -> Option<&[u8]> {
+        match self.raw_value {
+            Some(ref bytes) => Some(bytes),
+            None => self.value.as_ref().map(String::as_bytes),
+        }
+    }
+
+    /// Parses the node's expiration timestamp into a `SystemTime`.
+    ///
+    /// Returns `None` if the node has no expiration or it is not a valid RFC 3339 timestamp.
+    pub fn expiration_time(&self) -> Option<SystemTime> {
+        self.expiration
+            .as_ref()
+            .and_then(|expiration| parse_rfc3339(expiration))
+    }
+
+    /// Returns the node's remaining time to live as a `Duration`.
+    ///
+    /// etcd can report a negative TTL for a node that is about to expire, which is treated as
+    /// zero. Returns `None` if the node doesn't expire.
+    pub fn ttl_duration(&self) -> Option<Duration> {
+        self.ttl.map(|ttl| Duration::from_secs(ttl.max(0) as u64))
+    }
+
+    /// Returns true if the node is hidden, i.e. the last segment of its key starts with an
+    /// underscore.
+    ///
+    /// etcd leaves hidden nodes out of directory listings, but they can still be read, written
+    /// and watched by their full key.
+    pub fn is_hidden(&self) -> bool {
+        self.key.as_deref().is_some_and(is_hidden_key)
+    }
+
+    /// Collects every key-value pair in the tree rooted at this node, such as the result of a
+    /// recursive `kv::get`, into a map from full key to value.
+    ///
+    /// Directories are not included, but the key-value pairs inside them are, at any depth.
+    pub fn flatten(&self) -> HashMap<String, String> {
+        let mut map = HashMap::new();
+
+        self.for_each_key_value(&mut |key, node| {
+            map.insert(key.to_owned(), node.value.clone().unwrap_or_default());
+        });
+
+        map
+    }
+
+    /// Like `Node::flatten`, but also includes the remaining TTL of each key-value pair, if it
+    /// expires.
+    pub fn flatten_with_ttl(&self) -> HashMap<String, (String, Option<i64>)> {
+        let mut map = HashMap::new();
+
+        self.for_each_key_value(&mut |key, node| {
+            map.insert(
+                key.to_owned(),
+                (node.value.clone().unwrap_or_default(), node.ttl),
+            );
+        });
+
+        map
+    }
+
+    /// Calls the given closure with the key of every node in the tree that is not a directory.
+    fn for_each_key_value<F>(&self, f: &mut F)
+    where
+        F: FnMut(&str, &Node),
+    {
+        match self.key {
+            Some(ref key) if self.dir != Some(true) => f(key, self),
+            _ => {}
+        }
+
+        for node in self.nodes.iter().flatten() {
+            node.for_each_key_value(f);
+        }
+    }
 }
 
 /// Options for customizing the behavior of `kv::get`.
@@ -107,6 +696,59 @@ pub struct GetOptions {
     ///
     /// This is slower but avoids possibly stale data from being returned.
     pub strong_consistency: bool,
+    /// If true along with `strong_consistency`, and the strongly consistent read fails because
+    /// the cluster can't reach quorum, the read is retried without `strong_consistency` and the
+    /// response is marked with `Response::is_stale`.
+    ///
+    /// This trades consistency for availability: during a network partition, the member serving
+    /// the retried read may be cut off from the rest of the cluster, so the value returned may be
+    /// arbitrarily out of date, and may even have been deleted. Only use this for data where an
+    /// old value is better than none.
+    pub fallback_to_stale: bool,
+}
+
+impl GetOptions {
+    /// Returns a builder for `GetOptions`, starting from the defaults.
+    pub fn builder() -> GetOptionsBuilder {
+        GetOptionsBuilder::default()
+    }
+}
+
+/// Builds `GetOptions` one setting at a time.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct GetOptionsBuilder {
+    options: GetOptions,
+}
+
+impl GetOptionsBuilder {
+    /// Sets `GetOptions::fallback_to_stale`.
+    pub fn fallback_to_stale(mut self, fallback_to_stale: bool) -> Self {
+        self.options.fallback_to_stale = fallback_to_stale;
+        self
+    }
+
+    /// Sets `GetOptions::strong_consistency`, named after etcd's `quorum` parameter.
+    pub fn quorum(mut self, quorum: bool) -> Self {
+        self.options.strong_consistency = quorum;
+        self
+    }
+
+    /// Sets `GetOptions::recursive`.
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.options.recursive = recursive;
+        self
+    }
+
+    /// Sets `GetOptions::sort`.
+    pub fn sorted(mut self, sorted: bool) -> Self {
+        self.options.sort = sorted;
+        self
+    }
+
+    /// Returns the built options.
+    pub fn build(self) -> GetOptions {
+        self.options
+    }
 }
 
 /// Options for customizing the behavior of `kv::watch`.
@@ -121,238 +763,433 @@ pub struct WatchOptions {
     pub timeout: Option<Duration>,
 }
 
-/// Deletes a node only if the given current value and/or current modified index match.
-///
-/// # Parameters
-///
-/// * client: A `Client` to use to make the API call.
-/// * key: The name of the node to delete.
-/// * current_value: If given, the node must currently have this value for the operation to
-/// succeed.
-/// * current_modified_index: If given, the node must currently be at this modified index for the
-/// operation to succeed.
-///
-/// # Errors
-///
-/// Fails if the conditions didn't match or if no conditions were given.
-pub async fn compare_and_delete<C>(
-    client: &Client<C>,
-    key: &str,
-    current_value: Option<&str>,
-    current_modified_index: Option<u64>,
-) -> Result<Response<KeyValueInfo>, Vec<Error>>
-where
-    C: Clone + Connect + Sync + Send,
-{
-    raw_delete(
-        client,
-        key,
-        DeleteOptions {
-            conditions: Some(ComparisonConditions {
-                value: current_value,
-                modified_index: current_modified_index,
-            }),
-            ..Default::default()
-        },
-    )
-    .await
+impl WatchOptions {
+    /// Returns a builder for `WatchOptions`, starting from the defaults.
+    pub fn builder() -> WatchOptionsBuilder {
+        WatchOptionsBuilder::default()
+    }
 }
 
-/// Updates a node only if the given current value and/or current modified index
-/// match.
-///
-/// # Parameters
-///
-/// * client: A `Client` to use to make the API call.
-/// * key: The name of the node to update.
-/// * value: The new value for the node.
-/// * ttl: If given, the node will expire after this many seconds.
-/// * current_value: If given, the node must currently have this value for the operation to
-/// succeed.
-/// * current_modified_index: If given, the node must currently be at this modified index for the
-/// operation to succeed.
-///
-/// # Errors
-///
-/// Fails if the conditions didn't match or if no conditions were given.
-pub async fn compare_and_swap<C>(
-    client: &Client<C>,
-    key: &str,
-    value: &str,
-    ttl: Option<u64>,
-    current_value: Option<&str>,
-    current_modified_index: Option<u64>,
-) -> Result<Response<KeyValueInfo>, Vec<Error>>
+/// Builds `WatchOptions` one setting at a time.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct WatchOptionsBuilder {
+    options: WatchOptions,
+}
+
+impl WatchOptionsBuilder {
+    /// Sets `WatchOptions::index`.
+    pub fn index(mut self, index: u64) -> Self {
+        self.options.index = Some(index);
+        self
+    }
+
+    /// Sets `WatchOptions::recursive`.
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.options.recursive = recursive;
+        self
+    }
+
+    /// Sets `WatchOptions::timeout`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
+    /// Returns the built options.
+    pub fn build(self) -> WatchOptions {
+        self.options
+    }
+}
+
+/// Options for customizing the behavior of `kv::set_with_options`.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct SetOptions {
+    /// If given, the set only succeeds if the node's existence matches: `true` requires the node
+    /// to exist already, and `false` requires that it doesn't.
+    pub prev_exist: Option<bool>,
+    /// If given, the set only succeeds if the node is currently at this modified index.
+    pub prev_index: Option<u64>,
+    /// If given, the set only succeeds if the node currently has this value.
+    pub prev_value: Option<String>,
+    /// If given, the node will expire after this many seconds.
+    pub ttl: Option<u64>,
+}
+
+impl SetOptions {
+    /// Returns a builder for `SetOptions`, starting from the defaults.
+    pub fn builder() -> SetOptionsBuilder {
+        SetOptionsBuilder::default()
+    }
+}
+
+/// Builds `SetOptions` one setting at a time.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct SetOptionsBuilder {
+    options: SetOptions,
+}
+
+impl SetOptionsBuilder {
+    /// Sets `SetOptions::prev_exist`.
+    pub fn prev_exist(mut self, prev_exist: bool) -> Self {
+        self.options.prev_exist = Some(prev_exist);
+        self
+    }
+
+    /// Sets `SetOptions::prev_index`.
+    pub fn prev_index(mut self, prev_index: u64) -> Self {
+        self.options.prev_index = Some(prev_index);
+        self
+    }
+
+    /// Sets `SetOptions::prev_value`.
+    pub fn prev_value(mut self, prev_value: &str) -> Self {
+        self.options.prev_value = Some(prev_value.to_owned());
+        self
+    }
+
+    /// Sets `SetOptions::ttl`.
+    pub fn ttl(mut self, ttl: u64) -> Self {
+        self.options.ttl = Some(ttl);
+        self
+    }
+
+    /// Returns the built options.
+    pub fn build(self) -> SetOptions {
+        self.options
+    }
+}
+
+/// A change to a node reported by `kv::watch_stream`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WatchEvent {
+    /// Information about the change.
+    pub info: KeyValueInfo,
+    /// Information about the state of the cluster when the change was reported.
+    pub cluster_info: ClusterInfo,
+    /// The difference between the modified index of this change and that of the previous change
+    /// reported by the stream.
+    ///
+    /// For the first change, the difference is measured from the index just before
+    /// `WatchOptions::index`, or is zero if no index was given.
+    pub index_gap: u64,
+}
+
+/// A key kept alive by `kv::keep_alive`.
+///
+/// While the handle is held, a background task keeps refreshing the key's TTL. Dropping the handle
+/// or calling `KeepAlive::cancel` stops the task, leaving the key to expire.
+#[derive(Debug)]
+pub struct KeepAlive {
+    key: String,
+    alive: Arc<AtomicBool>,
+    lost: Shared<Receiver<()>>,
+    renewal: AbortHandle,
+}
+
+impl KeepAlive {
+    /// Returns the name of the key being kept alive.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns true while the key is still being kept alive.
+    pub fn is_alive(&self) -> bool {
+        self.alive.load(Ordering::SeqCst)
+    }
+
+    /// Returns a future that resolves once the key is no longer being kept alive.
+    ///
+    /// Renewal fails permanently when the key can't be refreshed because it no longer exists, for
+    /// example because it was deleted or expired while the cluster was unreachable, or when the
+    /// key's TTL runs out while refreshing keeps failing with transient errors. The future also
+    /// resolves once renewal is cancelled, and resolves immediately if either has already happened.
+    pub fn lost(&self) -> impl Future<Output = ()> + Send {
+        self.lost.clone().map(|_| ())
+    }
+
+    /// Stops refreshing the key, leaving it to expire after its TTL.
+    pub fn cancel(self) {
+        drop(self);
+    }
+}
+
+impl Drop for KeepAlive {
+    fn drop(&mut self) {
+        self.renewal.abort();
+        self.alive.store(false, Ordering::SeqCst);
+    }
+}
+
+/// A handle to the background task of a `kv::watch_channel`.
+///
+/// Dropping the handle or calling `WatchHandle::stop` stops the task, after which the receiver
+/// yields any changes still buffered and then ends.
+#[derive(Debug)]
+pub struct WatchHandle {
+    producer: AbortHandle,
+}
+
+impl WatchHandle {
+    /// Stops watching for changes.
+    pub fn stop(self) {
+        drop(self);
+    }
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.producer.abort();
+    }
+}
+
+/// A change in leadership reported by `kv::campaign`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum LeadershipEvent {
+    /// The candidate became the leader.
+    Acquired,
+    /// The candidate is no longer the leader.
+    Lost,
+}
+
+/// The state of a candidate in a `kv::campaign`.
+struct Campaign<C>
 where
-    C: Clone + Connect + Sync + Send,
+    C: Clone + Connect + Sync + Send + 'static,
 {
-    raw_set(
-        client,
-        key,
-        SetOptions {
-            conditions: Some(ComparisonConditions {
-                value: current_value,
-                modified_index: current_modified_index,
-            }),
-            ttl: ttl,
-            value: Some(value),
-            ..Default::default()
-        },
-    )
-    .await
+    client: Client<C>,
+    key: String,
+    candidate_id: String,
+    ttl: u64,
+    election: Option<Election<C>>,
+    failed: bool,
 }
 
-/// Creates a new key-value pair.
+/// Campaigns for leadership, returning a stream of leadership changes.
+///
+/// The leader is whichever candidate holds `key`, which is created with the candidate's ID as its
+/// value and expires after `ttl` seconds. While another candidate holds the key, it is watched
+/// until it is deleted or expires, at which point the candidate tries to take it. While the
+/// candidate holds the key, its TTL is refreshed as described by `election::campaign`. Once
+/// leadership is lost, as described by `Election::lost`, `LeadershipEvent::Lost` is emitted and
+/// the candidate goes back to campaigning.
+///
+/// The stream only ends after yielding an error. Dropping it stops the campaign, but does not give
+/// up leadership immediately: the key is left to expire after `ttl` seconds.
 ///
 /// # Parameters
 ///
-/// * client: A `Client` to use to make the API call.
-/// * key: The name of the key-value pair to create.
-/// * value: The new value for the node.
-/// * ttl: If given, the node will expire after this many seconds.
+/// * client: A `Client` to use to make the API calls.
+/// * key: The name of the key that represents leadership.
+/// * candidate_id: A unique identifier for this candidate.
+/// * ttl: The number of seconds after which leadership expires unless renewed.
 ///
 /// # Errors
 ///
-/// Fails if the key already exists.
-pub async fn create<C>(
+/// Yields `Error::ZeroTtl` straight away if `ttl` is zero. A campaign attempt that fails with
+/// transient errors, such as connection errors or timeouts, is retried after a third of `ttl`.
+/// Any other failure, such as a permission error, is yielded and ends the stream.
+pub fn campaign<C>(
     client: &Client<C>,
     key: &str,
-    value: &str,
-    ttl: Option<u64>,
-) -> Result<Response<KeyValueInfo>, Vec<Error>>
+    candidate_id: &str,
+    ttl: u64,
+) -> impl Stream<Item = Result<LeadershipEvent, Vec<Error>>> + Send
 where
-    C: Clone + Connect + Sync + Send,
+    C: Clone + Connect + Sync + Send + 'static,
 {
-    raw_set(
-        client,
-        key,
-        SetOptions {
-            prev_exist: Some(false),
-            ttl: ttl,
-            value: Some(value),
-            ..Default::default()
-        },
-    )
-    .await
+    let campaign = Campaign {
+        client: client.clone(),
+        key: key.to_owned(),
+        candidate_id: candidate_id.to_owned(),
+        ttl,
+        election: None,
+        failed: false,
+    };
+
+    stream::unfold(campaign, |mut campaign| async move {
+        if campaign.failed {
+            return None;
+        }
+
+        if let Some(election) = campaign.election.take() {
+            election.lost().await;
+
+            return Some((Ok(LeadershipEvent::Lost), campaign));
+        }
+
+        loop {
+            let result = election::campaign(
+                &campaign.client,
+                &campaign.key,
+                &campaign.candidate_id,
+                campaign.ttl,
+            )
+            .await;
+
+            match result {
+                Ok(election) => {
+                    campaign.election = Some(election);
+
+                    return Some((Ok(LeadershipEvent::Acquired), campaign));
+                }
+                Err(ref errors) if errors.iter().all(Error::is_retryable) => {
+                    delay_for(Duration::from_secs(campaign.ttl) / 3).await
+                }
+                Err(errors) => {
+                    campaign.failed = true;
+
+                    return Some((Err(errors), campaign));
+                }
+            }
+        }
+    })
 }
 
-/// Creates a new empty directory.
+/// Gets every node in a directory's subtree that has been modified since the given etcd index.
+///
+/// etcd's v2 API can't query for changes directly, so this reads the whole subtree and keeps the
+/// nodes whose modified index is greater than `since_index`. The nodes are returned as a flat list
+/// without their children.
+///
+/// Deleted nodes are not captured, since they are no longer in the tree. To observe every change,
+/// including deletions, pair this with a recursive `kv::watch` starting at `since_index + 1`.
 ///
 /// # Parameters
 ///
 /// * client: A `Client` to use to make the API call.
-/// * key: The name of the directory to create.
-/// * ttl: If given, the node will expire after this many seconds.
+/// * dir: The name of the directory to look for changes in.
+/// * since_index: The etcd index after which changes are returned.
 ///
 /// # Errors
 ///
-/// Fails if the key already exists.
-pub async fn create_dir<C>(
+/// Fails if the directory doesn't exist.
+pub async fn changed_since<C>(
     client: &Client<C>,
-    key: &str,
-    ttl: Option<u64>,
-) -> Result<Response<KeyValueInfo>, Vec<Error>>
+    dir: &str,
+    since_index: u64,
+) -> Result<Vec<Node>, Vec<Error>>
 where
     C: Clone + Connect + Sync + Send,
 {
-    raw_set(
-        client,
-        key,
-        SetOptions {
-            dir: Some(true),
-            prev_exist: Some(false),
-            ttl: ttl,
-            ..Default::default()
-        },
-    )
-    .await
+    let options = GetOptions {
+        recursive: true,
+        ..Default::default()
+    };
+    let response = get(client, dir, options).await?;
+
+    let mut nodes = Vec::new();
+    flatten_nodes(response.data.node.nodes.unwrap_or_default(), &mut nodes);
+
+    Ok(nodes
+        .into_iter()
+        .filter(|node| node.modified_index.is_some_and(|index| index > since_index))
+        .collect())
 }
 
-/// Creates a new key-value pair in a directory with a numeric key name larger than any of its
-/// sibling key-value pairs.
+/// Returns a stream of every change to a node since the given etcd index, followed by each new
+/// change as it takes place.
 ///
-/// For example, the first value created with this function under the directory "/foo" will have a
-/// key name like "00000000000000000001" automatically generated. The second value created with
-/// this function under the same directory will have a key name like "00000000000000000002".
+/// This replays the changes etcd has buffered from `from_index` onwards, one response per change,
+/// and then keeps watching for new ones. It is `kv::watch_stream` with each event returned as the
+/// `Response` that `kv::watch` would have returned for it.
 ///
-/// This behavior is guaranteed by the server.
+/// If `from_index` has been cleared from etcd's event history, the stream skips ahead to the
+/// current etcd index instead of failing, so changes older than etcd's history are missed.
+///
+/// Each response carries the cluster information of the watch that reported the change, but not
+/// its HTTP status or headers.
 ///
 /// # Parameters
 ///
-/// * client: A `Client` to use to make the API call.
-/// * key: The name of the directory to create a key-value pair in.
-/// * value: The new value for the key-value pair.
-/// * ttl: If given, the node will expire after this many seconds.
+/// * client: A `Client` to use to make the API calls.
+/// * key: The name of the node to watch.
+/// * from_index: The etcd index of the first change to return.
+/// * recursive: Whether to include changes to the node's children.
 ///
 /// # Errors
 ///
-/// Fails if the key already exists and is not a directory.
-pub async fn create_in_order<C>(
+/// Yields an error for each failed watch. The stream continues after an error, retrying from the
+/// same index.
+pub fn changes_since<C>(
     client: &Client<C>,
     key: &str,
-    value: &str,
-    ttl: Option<u64>,
-) -> Result<Response<KeyValueInfo>, Vec<Error>>
+    from_index: u64,
+    recursive: bool,
+) -> impl Stream<Item = Result<Response<KeyValueInfo>, WatchError>> + Send
 where
-    C: Clone + Connect + Sync + Send,
+    C: Clone + Connect + Sync + Send + 'static,
 {
-    raw_set(
-        client,
-        key,
-        SetOptions {
-            create_in_order: true,
-            ttl: ttl,
-            value: Some(value),
-            ..Default::default()
-        },
-    )
-    .await
+    let options = WatchOptions {
+        index: Some(from_index),
+        recursive,
+        ..Default::default()
+    };
+
+    watch_stream(client, key, options)
+        .map(|result| result.map(|event| Response::new(event.info, event.cluster_info)))
 }
 
-/// Deletes a node.
+/// Atomically claims the oldest item in a queue directory populated by `kv::create_in_order`.
+///
+/// The items in the queue are read in order. To claim an item, a record of it is first created in
+/// a hidden `_claimed` directory inside the queue directory, where it expires after `claim_ttl`
+/// seconds, and the item is then removed with a compare-and-delete on its modified index. Only one
+/// consumer can create the record, so exactly one consumer claims each item. Items that another
+/// consumer is claiming, or that another consumer removed first, are skipped. Hidden nodes are not
+/// included when listing the queue, so the records never appear as items.
+///
+/// If a consumer fails after recording its claim but before removing the item, the item stays in
+/// the queue and can be claimed again once the record expires.
 ///
 /// # Parameters
 ///
-/// * client: A `Client` to use to make the API call.
-/// * key: The name of the node to delete.
-/// * recursive: If true, and the key is a directory, the directory and all child key-value
-/// pairs and directories will be deleted as well.
+/// * client: A `Client` to use to make the API calls.
+/// * queue_dir: The name of the directory holding the queue.
+/// * claim_ttl: The number of seconds for which the record of a claimed item is kept.
 ///
 /// # Errors
 ///
-/// Fails if the key is a directory and `recursive` is `false`.
-pub async fn delete<C>(
+/// Fails if `queue_dir` is not a directory, or if the claim can't be recorded or the item can't be
+/// removed for any reason other than another consumer claiming it first. An empty or missing
+/// queue is not an error: `Ok(None)` is returned instead, as it is when every item is being
+/// claimed by another consumer.
+pub async fn claim_oldest<C>(
     client: &Client<C>,
-    key: &str,
-    recursive: bool,
-) -> Result<Response<KeyValueInfo>, Vec<Error>>
+    queue_dir: &str,
+    claim_ttl: u64,
+) -> Result<Option<(String, String)>, Vec<Error>>
 where
     C: Clone + Connect + Sync + Send,
 {
-    raw_delete(
-        client,
-        key,
-        DeleteOptions {
-            recursive: Some(recursive),
-            ..Default::default()
-        },
-    )
-    .await
+    match try_claim_oldest(client, queue_dir, claim_ttl).await? {
+        Claim::Item(key, value) => Ok(Some((key, value))),
+        Claim::Empty(_) => Ok(None),
+    }
 }
 
-/// Deletes an empty directory or a key-value pair at the given key.
+/// Deletes a node only if the given current value and/or current modified index match.
 ///
 /// # Parameters
 ///
 /// * client: A `Client` to use to make the API call.
 /// * key: The name of the node to delete.
+/// * current_value: If given, the node must currently have this value for the operation to
+/// succeed. etcd doesn't accept an empty string here, so comparing against an empty value is
+/// done by reading the node first and then comparing its modified index instead.
+/// * current_modified_index: If given, the node must currently be at this modified index for the
+/// operation to succeed.
+///
+/// If both conditions are given, both must match.
 ///
 /// # Errors
 ///
-/// Fails if the directory is not empty.
-pub async fn delete_dir<C>(
+/// Fails if the conditions didn't match, with etcd's "compare failed" error (code 101), or if no
+/// conditions were given.
+pub async fn compare_and_delete<C>(
     client: &Client<C>,
     key: &str,
+    current_value: Option<&str>,
+    current_modified_index: Option<u64>,
 ) -> Result<Response<KeyValueInfo>, Vec<Error>>
 where
     C: Clone + Connect + Sync + Send,
@@ -361,94 +1198,268 @@ where
         client,
         key,
         DeleteOptions {
-            dir: Some(true),
+            conditions: Some(ComparisonConditions {
+                value: current_value,
+                modified_index: current_modified_index,
+            }),
             ..Default::default()
         },
     )
     .await
 }
 
-/// Gets the value of a node.
+/// Updates a node only if the given current value and/or current modified index
+/// match.
 ///
 /// # Parameters
 ///
 /// * client: A `Client` to use to make the API call.
-/// * key: The name of the node to retrieve.
-/// * options: Options to customize the behavior of the operation.
+/// * key: The name of the node to update.
+/// * value: The new value for the node.
+/// * ttl: If given, the node will expire after this many seconds.
+/// * current_value: If given, the node must currently have this value for the operation to
+/// succeed. etcd doesn't accept an empty string here, so comparing against an empty value is
+/// done by reading the node first and then comparing its modified index instead.
+/// * current_modified_index: If given, the node must currently be at this modified index for the
+/// operation to succeed.
+///
+/// If both conditions are given, both must match.
 ///
 /// # Errors
 ///
-/// Fails if the key doesn't exist.
-pub async fn get<C>(
+/// Fails if the conditions didn't match, with etcd's "compare failed" error (code 101), or if no
+/// conditions were given.
+pub async fn compare_and_swap<C>(
     client: &Client<C>,
     key: &str,
-    options: GetOptions,
+    value: &str,
+    ttl: Option<u64>,
+    current_value: Option<&str>,
+    current_modified_index: Option<u64>,
 ) -> Result<Response<KeyValueInfo>, Vec<Error>>
 where
     C: Clone + Connect + Sync + Send,
 {
-    raw_get(
+    raw_set(
         client,
         key,
-        InternalGetOptions {
-            recursive: options.recursive,
-            sort: Some(options.sort),
-            strong_consistency: options.strong_consistency,
+        InternalSetOptions {
+            conditions: Some(ComparisonConditions {
+                value: current_value,
+                modified_index: current_modified_index,
+            }),
+            ttl: ttl,
+            value: Some(value),
             ..Default::default()
         },
     )
     .await
 }
 
-/// Sets the value of a key-value pair.
+/// Updates a key-value pair holding JSON only if its current value deserializes to a value equal
+/// to `current_value`.
 ///
-/// Any previous value and TTL will be replaced.
+/// Unlike `kv::compare_and_swap`, which compares values as strings, this compares them after
+/// deserializing, so differences in formatting such as whitespace or the order of object fields
+/// don't matter. The node is read first and its value is compared in the client. If it matches,
+/// the node is updated with a compare-and-swap on the exact string that was read, so the update
+/// still fails if the value is changed in between.
+///
+/// The comparison is not made at a single point in time. If the value is changed and then
+/// changed back to the same string between the read and the update, the update succeeds. Where
+/// that matters, read the node and use `kv::compare_and_swap` with its modified index instead.
 ///
 /// # Parameters
 ///
-/// * client: A `Client` to use to make the API call.
-/// * key: The name of the key-value pair to set.
+/// * client: A `Client` to use to make the API calls.
+/// * key: The name of the key-value pair to update.
 /// * value: The new value for the key-value pair.
 /// * ttl: If given, the node will expire after this many seconds.
+/// * current_value: The value the key-value pair must currently have for the operation to
+/// succeed.
 ///
 /// # Errors
 ///
-/// Fails if the node is a directory.
-pub async fn set<C>(
+/// Fails if the values don't match, with the same "compare failed" API error (code 101) that etcd
+/// returns for `kv::compare_and_swap`.
+///
+/// Fails with `Error::MissingValue` if the node is a directory, or with `Error::Serialization` if
+/// its value is not valid JSON for `T`.
+pub async fn compare_and_swap_typed<C, T>(
     client: &Client<C>,
     key: &str,
-    value: &str,
+    value: &T,
     ttl: Option<u64>,
+    current_value: &T,
 ) -> Result<Response<KeyValueInfo>, Vec<Error>>
 where
     C: Clone + Connect + Sync + Send,
+    T: DeserializeOwned + PartialEq + serde::Serialize,
 {
-    raw_set(
-        client,
-        key,
-        SetOptions {
-            ttl: ttl,
-            value: Some(value),
-            ..Default::default()
-        },
-    )
-    .await
-}
+    let response = get(client, key, GetOptions::default()).await?;
 
-/// Sets the key to an empty directory.
-///
-/// An existing key-value pair will be replaced, but an existing directory will not.
-///
+    let stored = match response.data.node.value {
+        Some(ref value) if response.data.node.dir != Some(true) => value,
+        _ => return Err(vec![Error::MissingValue]),
+    };
+
+    let stored_value: T = serde_json::from_str(stored).map_err(|error| vec![Error::from(error)])?;
+
+    if stored_value != *current_value {
+        let expected =
+            serde_json::to_string(current_value).map_err(|error| vec![Error::from(error)])?;
+
+        return Err(vec![Error::Api(ApiError {
+            cause: Some(format!("[{} != {}]", expected, stored)),
+            error_code: EtcdErrorCode::CompareFailed.as_u64(),
+            error_index: None,
+            index: response.cluster_info.etcd_index,
+            message: "Compare failed".to_owned(),
+        })]);
+    }
+
+    let value = serde_json::to_string(value).map_err(|error| vec![Error::from(error)])?;
+
+    compare_and_swap(client, key, &value, ttl, Some(stored), None).await
+}
+
+/// Recursively copies every node in a directory to the same relative location under another
+/// directory, returning the number of key-value pairs copied.
+///
+/// Each directory is created before the nodes inside it are copied, and TTLs are carried over to
+/// the copies of both directories and key-value pairs. When overwriting, a directory that already
+/// exists in `to_dir` takes the TTL of its counterpart in `from_dir`.
+///
+/// The copy is not transactional: each node is written with a separate API call, so changes made
+/// to the source while copying may or may not be reflected in the copy, and if an API call fails,
+/// the nodes copied up to that point are left in place.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API calls.
+/// * from_dir: The name of the directory to copy.
+/// * to_dir: The name of the directory to copy into.
+/// * overwrite: If true, nodes already in `to_dir` are replaced by their counterparts in
+/// `from_dir` and other nodes are left alone. If false, the copy fails unless `to_dir` is empty or
+/// doesn't exist.
+///
+/// # Errors
+///
+/// Fails with `Error::DestinationNotEmpty` if `overwrite` is false and `to_dir` is not empty.
+///
+/// Fails if `from_dir` doesn't exist.
+pub async fn copy_subtree<C>(
+    client: &Client<C>,
+    from_dir: &str,
+    to_dir: &str,
+    overwrite: bool,
+) -> Result<usize, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    if !overwrite {
+        match get(client, to_dir, GetOptions::default()).await {
+            Ok(response) => {
+                let node = response.data.node;
+
+                if node.dir != Some(true) || node.nodes.is_some_and(|nodes| !nodes.is_empty()) {
+                    return Err(vec![Error::DestinationNotEmpty]);
+                }
+            }
+            Err(ref errors) if has_api_error(errors, EtcdErrorCode::KeyNotFound) => {}
+            Err(errors) => return Err(errors),
+        }
+    }
+
+    let options = GetOptions {
+        recursive: true,
+        ..Default::default()
+    };
+    let source = get(client, from_dir, options).await?.data.node;
+    let from_prefix = source.key.unwrap_or_default();
+    let to_prefix = to_dir.trim_end_matches('/');
+
+    let mut pending = source.nodes.unwrap_or_default();
+    let mut copied = 0;
+
+    while let Some(node) = pending.pop() {
+        let key = node.key.unwrap_or_default();
+        let destination = format!("{}{}", to_prefix, &key[from_prefix.len().min(key.len())..]);
+        let ttl = node.ttl.map(|ttl| ttl.max(1) as u64);
+
+        if node.dir == Some(true) {
+            match set_dir(client, &destination, ttl).await {
+                Ok(_) => {}
+                // Returned when the directory already exists.
+                Err(ref errors) if has_api_error(errors, EtcdErrorCode::NotAFile) => {
+                    update_dir(client, &destination, ttl).await?;
+                }
+                Err(errors) => return Err(errors),
+            }
+
+            pending.extend(node.nodes.unwrap_or_default());
+        } else {
+            set(
+                client,
+                &destination,
+                node.value.as_deref().unwrap_or(""),
+                ttl,
+            )
+            .await?;
+
+            copied += 1;
+        }
+    }
+
+    Ok(copied)
+}
+
+/// Creates a new key-value pair.
+///
 /// # Parameters
 ///
 /// * client: A `Client` to use to make the API call.
-/// * key: The name of the directory to set.
+/// * key: The name of the key-value pair to create.
+/// * value: The new value for the node.
 /// * ttl: If given, the node will expire after this many seconds.
 ///
 /// # Errors
 ///
-/// Fails if the node is an existing directory.
-pub async fn set_dir<C>(
+/// Fails if the key already exists.
+pub async fn create<C>(
+    client: &Client<C>,
+    key: &str,
+    value: &str,
+    ttl: Option<u64>,
+) -> Result<Response<KeyValueInfo>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    raw_set(
+        client,
+        key,
+        InternalSetOptions {
+            prev_exist: Some(false),
+            ttl: ttl,
+            value: Some(value),
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+/// Creates a new empty directory.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the directory to create.
+/// * ttl: If given, the node will expire after this many seconds.
+///
+/// # Errors
+///
+/// Fails if the key already exists.
+pub async fn create_dir<C>(
     client: &Client<C>,
     key: &str,
     ttl: Option<u64>,
@@ -459,8 +1470,9 @@ where
     raw_set(
         client,
         key,
-        SetOptions {
+        InternalSetOptions {
             dir: Some(true),
+            prev_exist: Some(false),
             ttl: ttl,
             ..Default::default()
         },
@@ -468,19 +1480,26 @@ where
     .await
 }
 
-/// Updates an existing key-value pair.
+/// Creates a new key-value pair in a directory with a numeric key name larger than any of its
+/// sibling key-value pairs.
+///
+/// For example, the first value created with this function under the directory "/foo" will have a
+/// key name like "00000000000000000001" automatically generated. The second value created with
+/// this function under the same directory will have a key name like "00000000000000000002".
+///
+/// This behavior is guaranteed by the server.
 ///
 /// # Parameters
 ///
 /// * client: A `Client` to use to make the API call.
-/// * key: The name of the key-value pair to update.
+/// * key: The name of the directory to create a key-value pair in.
 /// * value: The new value for the key-value pair.
 /// * ttl: If given, the node will expire after this many seconds.
 ///
 /// # Errors
 ///
-/// Fails if the key does not exist.
-pub async fn update<C>(
+/// Fails if the key already exists and is not a directory.
+pub async fn create_in_order<C>(
     client: &Client<C>,
     key: &str,
     value: &str,
@@ -492,8 +1511,8 @@ where
     raw_set(
         client,
         key,
-        SetOptions {
-            prev_exist: Some(true),
+        InternalSetOptions {
+            create_in_order: true,
             ttl: ttl,
             value: Some(value),
             ..Default::default()
@@ -502,24 +1521,2091 @@ where
     .await
 }
 
-/// Updates a directory.
+/// Deletes a node.
 ///
-/// If the directory already existed, only the TTL is updated. If the key was a key-value pair, its
-/// value is removed and its TTL is updated.
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the node to delete.
+/// * recursive: If true, and the key is a directory, the directory and all child key-value
+/// pairs and directories will be deleted as well.
+///
+/// # Errors
+///
+/// Fails if the key is a directory and `recursive` is `false`.
+pub async fn delete<C>(
+    client: &Client<C>,
+    key: &str,
+    recursive: bool,
+) -> Result<Response<KeyValueInfo>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    raw_delete(
+        client,
+        key,
+        DeleteOptions {
+            recursive: Some(recursive),
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+/// Deletes an empty directory or a key-value pair at the given key.
 ///
 /// # Parameters
 ///
 /// * client: A `Client` to use to make the API call.
-/// * key: The name of the node to update.
-/// * ttl: If given, the node will expire after this many seconds.
+/// * key: The name of the node to delete.
 ///
 /// # Errors
 ///
-/// Fails if the node does not exist.
-pub async fn update_dir<C>(
+/// Fails if the directory is not empty.
+pub async fn delete_dir<C>(
+    client: &Client<C>,
+    key: &str,
+) -> Result<Response<KeyValueInfo>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    raw_delete(
+        client,
+        key,
+        DeleteOptions {
+            dir: Some(true),
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+/// Deletes a key-value pair only if it hasn't been modified since it was read.
+///
+/// This is a convenience for `kv::compare_and_delete` that ties the delete to an earlier read:
+///
+/// ```no_run
+/// # use etcd::{Client, kv};
+/// # async fn example(client: Client<hyper::client::HttpConnector>) {
+/// let response = kv::get(&client, "/lock", kv::GetOptions::default()).await.unwrap();
+/// let guard = response.data.delete_guard().unwrap();
+///
+/// kv::delete_if(&client, "/lock", guard).await.unwrap();
+/// # }
+/// ```
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the node to delete.
+/// * guard: The guard returned by `KeyValueInfo::delete_guard` for an earlier read of the node.
+///
+/// # Errors
+///
+/// Fails if the node has been modified since it was read.
+pub async fn delete_if<C>(
+    client: &Client<C>,
+    key: &str,
+    guard: DeleteGuard,
+) -> Result<Response<KeyValueInfo>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    compare_and_delete(client, key, None, Some(guard.modified_index)).await
+}
+
+/// Deletes a key-value pair only if it has the expected value, returning whether it was deleted.
+///
+/// This is like `kv::compare_and_delete` with a value condition, except that a failed comparison
+/// is reported as `false` rather than as an error. The response to a failed comparison carries
+/// only the etcd index reported with the failure.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the key-value pair to delete.
+/// * expected: The value the key-value pair must have for it to be deleted.
+///
+/// # Errors
+///
+/// Fails if the key doesn't exist, or for any other reason than the value not matching.
+pub async fn delete_if_value<C>(
+    client: &Client<C>,
+    key: &str,
+    expected: &str,
+) -> Result<Response<bool>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    into_bool_response(
+        compare_and_delete(client, key, Some(expected), None).await,
+        EtcdErrorCode::CompareFailed,
+    )
+}
+
+/// Compares two snapshots of a directory, such as the results of two recursive `kv::get` calls.
+///
+/// Both trees are flattened and their nodes are matched by their full key. Nodes without a key
+/// are ignored. A node whose value differs between the two trees is reported as changed, as is a
+/// directory that became a key or a key that became a directory. When a directory is added or
+/// removed, each of the nodes inside it is reported as well.
+///
+/// This makes no API calls.
+///
+/// # Parameters
+///
+/// * old: The earlier snapshot.
+/// * new: The later snapshot.
+pub fn diff(old: &Node, new: &Node) -> DirDiff {
+    let mut old_values = BTreeMap::new();
+    let mut new_values = BTreeMap::new();
+
+    collect_values(old, &mut old_values);
+    collect_values(new, &mut new_values);
+
+    let mut diff = DirDiff::default();
+
+    for (&key, &old_value) in &old_values {
+        match new_values.get(key) {
+            None => diff.removed.push(key.to_owned()),
+            Some(&new_value) if new_value != old_value => diff.changed.push((
+                key.to_owned(),
+                old_value.unwrap_or("").to_owned(),
+                new_value.unwrap_or("").to_owned(),
+            )),
+            Some(_) => {}
+        }
+    }
+
+    for &key in new_values.keys() {
+        if !old_values.contains_key(key) {
+            diff.added.push(key.to_owned());
+        }
+    }
+
+    diff
+}
+
+/// Drains a queue directory populated by `kv::create_in_order`, returning a stream of the claimed
+/// items in order.
+///
+/// Each item is claimed with `kv::claim_oldest`, so several consumers can drain the same queue
+/// without processing any item twice. Each stream item is the key and value of a claimed queue
+/// item.
+///
+/// If `wait` is false, the stream ends as soon as the queue is empty. If `wait` is true, the stream
+/// never ends on its own: once the queue is empty, the queue directory is watched until new items
+/// arrive.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API calls.
+/// * queue_dir: The name of the directory holding the queue.
+/// * claim_ttl: The number of seconds for which the record of a claimed item is kept.
+/// * wait: Whether to wait for new items once the queue is empty instead of ending the stream.
+///
+/// # Errors
+///
+/// Yields an error if claiming an item or watching the queue fails. The stream continues after
+/// an error.
+pub fn drain_in_order<C>(
+    client: &Client<C>,
+    queue_dir: &str,
+    claim_ttl: u64,
+    wait: bool,
+) -> impl Stream<Item = Result<(String, String), Vec<Error>>> + Send
+where
+    C: Clone + Connect + Sync + Send + 'static,
+{
+    let state = (client.clone(), queue_dir.to_owned());
+
+    stream::unfold(state, move |(client, queue_dir)| async move {
+        loop {
+            let index = match try_claim_oldest(&client, &queue_dir, claim_ttl).await {
+                Ok(Claim::Item(key, value)) => {
+                    return Some((Ok((key, value)), (client, queue_dir)));
+                }
+                Ok(Claim::Empty(_)) if !wait => return None,
+                Ok(Claim::Empty(index)) => index,
+                Err(errors) => return Some((Err(errors), (client, queue_dir))),
+            };
+
+            // The watch starts right after the index of the listing that found the queue empty,
+            // so that items added since then are seen.
+            let options = WatchOptions {
+                index: index.map(|index| index + 1),
+                recursive: true,
+                ..Default::default()
+            };
+
+            match watch(&client, &queue_dir, options).await {
+                Ok(_) | Err(WatchError::Outdated { .. }) | Err(WatchError::Timeout) => {}
+                Err(WatchError::Other(errors)) => {
+                    return Some((Err(errors), (client, queue_dir)));
+                }
+            }
+        }
+    })
+}
+
+/// Adds a value to the end of a queue, returning the full key generated for it alongside the
+/// response.
+///
+/// This is `kv::create_in_order` for queue-style consumers, which need the generated key to refer
+/// to the item later, for example to delete it once it has been processed.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * dir: The name of the directory that holds the queue.
+/// * value: The value to add to the queue.
+/// * ttl: If given, the item will expire after this many seconds.
+///
+/// # Errors
+///
+/// Fails if the directory already exists as a key-value pair, or with `Error::MissingKey` if etcd
+/// doesn't return the generated key.
+pub async fn enqueue<C>(
+    client: &Client<C>,
+    dir: &str,
+    value: &str,
+    ttl: Option<u64>,
+) -> Result<(String, Response<KeyValueInfo>), Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    let response = create_in_order(client, dir, value, ttl).await?;
+
+    match response.data.node.key {
+        Some(ref key) if !key.is_empty() => Ok((key.clone(), response)),
+        _ => Err(vec![Error::MissingKey]),
+    }
+}
+
+/// Checks whether a node exists.
+///
+/// Unlike `kv::get`, a missing node is not an error. The response's `ClusterInfo` is available
+/// either way, so its etcd index can be used to watch for the node being created or deleted.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the node to check.
+///
+/// # Errors
+///
+/// Fails if the check can't be made, for example if no cluster member can be reached.
+pub async fn exists<C>(client: &Client<C>, key: &str) -> Result<Response<bool>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    let http_client = client.http_client().clone();
+    let key = key.to_string();
+
+    client
+        .first_ok(true, move |endpoint| {
+            let uri = ready(Uri::from_str(&build_url(endpoint, &key)).map_err(Error::from));
+
+            let http_client = http_client.clone();
+
+            let response = uri.and_then(move |uri| http_client.get(uri));
+
+            response.and_then(|response| {
+                ready(match response.status() {
+                    StatusCode::OK => Ok(response.into_response(true)),
+                    StatusCode::NOT_FOUND => match response.api_error_or_status() {
+                        ref error if is_api_error(error, EtcdErrorCode::KeyNotFound) => {
+                            Ok(response.into_response(false))
+                        }
+                        error => Err(error),
+                    },
+                    _ => Err(response.api_error_or_status()),
+                })
+            })
+        })
+        .await
+}
+
+/// Gets the value of a node.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the node to retrieve.
+/// * options: Options to customize the behavior of the operation.
+///
+/// # Errors
+///
+/// Fails if the key doesn't exist.
+pub async fn get<C>(
+    client: &Client<C>,
+    key: &str,
+    options: GetOptions,
+) -> Result<Response<KeyValueInfo>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    get_with_fallback(client, key, options).await
+}
+
+/// Gets a node as it was at the given etcd index.
+///
+/// This allows reading what a node looked like at a point in the past, which is useful for
+/// investigating an incident after the fact. etcd only retains a limited window of the most
+/// recent change events (1000 by default), so older indexes can no longer be read.
+///
+/// The node is read first. If it hasn't changed since `index`, it is returned as it is now.
+/// Otherwise, the first change made to it at or after `index` is looked up in etcd's event
+/// history. That change's `modified_index` is only equal to `index` if the node was changed at
+/// exactly that index; if it is greater, the node as it was at `index` is the change's
+/// `prev_node`, which is `None` if the node didn't exist yet.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API calls.
+/// * key: The name of the node to retrieve.
+/// * index: The etcd index at which to read the node.
+///
+/// # Errors
+///
+/// Fails with `Error::FutureIndex` if the cluster hasn't reached `index` yet, with
+/// `Error::Compacted` if the change events at `index` have already been flushed out of etcd's
+/// history, or with etcd's "key not found" error (code 100) if the node didn't exist at `index`
+/// and hasn't been changed since.
+pub async fn get_at_index<C>(
+    client: &Client<C>,
+    key: &str,
+    index: u64,
+) -> Result<Response<KeyValueInfo>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    let (current_index, not_found) = match get(client, key, GetOptions::default()).await {
+        Ok(response) => {
+            let current_index = response.cluster_info.etcd_index;
+
+            match response.data.node.modified_index {
+                Some(modified_index)
+                    if modified_index <= index && current_index.is_none_or(|c| index <= c) =>
+                {
+                    return Ok(response)
+                }
+                _ => (current_index, None),
+            }
+        }
+        Err(errors) => {
+            if !has_api_error(&errors, EtcdErrorCode::KeyNotFound) {
+                return Err(errors);
+            }
+
+            let current_index = errors.iter().find_map(|error| match error {
+                Error::Api(error) => error.index,
+                _ => None,
+            });
+
+            (current_index, Some(errors))
+        }
+    };
+
+    if let Some(current_index) = current_index {
+        if index > current_index {
+            return Err(vec![Error::FutureIndex {
+                requested: index,
+                current: current_index,
+            }]);
+        }
+    }
+
+    let lookup = raw_get(
+        client,
+        key,
+        InternalGetOptions {
+            wait: true,
+            wait_index: Some(index),
+            ..Default::default()
+        },
+    );
+
+    // A node that exists was changed after `index`, so the change is already in the history and
+    // is returned right away. A node that doesn't exist may never have been changed since, in
+    // which case etcd would wait for the next change instead.
+    let result = match not_found {
+        Some(errors) => match timeout(HISTORY_LOOKUP_TIMEOUT, lookup).await {
+            Ok(result) => result,
+            Err(_) => return Err(errors),
+        },
+        None => lookup.await,
+    };
+
+    result.map_err(|errors| {
+        errors
+            .into_iter()
+            .map(|error| match error {
+                Error::Api(error) if error.code() == EtcdErrorCode::EventIndexCleared => {
+                    Error::Compacted(error)
+                }
+                error => error,
+            })
+            .collect()
+    })
+}
+
+/// Gets the value of a key-value pair set with `kv::set_bytes`, decoding it from base64.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the key-value pair to retrieve.
+///
+/// # Errors
+///
+/// Fails if the key doesn't exist, with `Error::MissingValue` if the node is a directory or has no
+/// value, or with `Error::InvalidBase64` if the value is not valid base64.
+pub async fn get_bytes<C>(client: &Client<C>, key: &str) -> Result<Response<Vec<u8>>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    let response = get(client, key, GetOptions::default()).await?;
+
+    let value = match response.data.node.value {
+        Some(ref value) if response.data.node.dir != Some(true) => value,
+        _ => return Err(vec![Error::MissingValue]),
+    };
+
+    let data = base64::decode(value).map_err(|error| vec![Error::InvalidBase64(error)])?;
+
+    Ok(Response {
+        cluster_info: response.cluster_info,
+        data,
+        headers: response.headers,
+        is_stale: response.is_stale,
+        status: response.status,
+    })
+}
+
+/// Gets several nodes concurrently.
+///
+/// Each key is fetched as if by `kv::get`, with up to `limit` requests in flight at once.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API calls.
+/// * keys: The names of the nodes to retrieve.
+/// * options: Options to customize the behavior of each operation.
+/// * limit: The maximum number of concurrent requests. A limit of zero is treated as one.
+///
+/// # Errors
+///
+/// This function itself never fails. Instead, each key is returned alongside its own result, in
+/// the same order as `keys`, so one missing key doesn't hide the values of the others.
+pub async fn get_many<C>(
+    client: &Client<C>,
+    keys: &[&str],
+    options: GetOptions,
+    limit: usize,
+) -> Vec<(String, Result<Response<KeyValueInfo>, Vec<Error>>)>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    stream::iter(keys)
+        .map(|&key| async move { (key.to_owned(), get(client, key, options).await) })
+        .buffered(limit.max(1))
+        .collect()
+        .await
+}
+
+/// Creates a new key-value pair with a default value if it doesn't exist, or gets it if it does.
+///
+/// The returned flag is true if the key-value pair was created by this call. If the key exists but
+/// is deleted again before it can be read, creating it is tried again.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API calls.
+/// * key: The name of the key-value pair.
+/// * default_value: The value to create the node with if it doesn't exist.
+/// * ttl: If given and the node is created, it will expire after this many seconds.
+///
+/// # Errors
+///
+/// Fails if the key can't be created for any reason other than it already existing, or if the
+/// existing node can't be read.
+pub async fn get_or_create<C>(
+    client: &Client<C>,
+    key: &str,
+    default_value: &str,
+    ttl: Option<u64>,
+) -> Result<(Response<KeyValueInfo>, bool), Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    loop {
+        match create(client, key, default_value, ttl).await {
+            Ok(response) => return Ok((response, true)),
+            Err(ref errors) if has_api_error(errors, EtcdErrorCode::KeyAlreadyExists) => {}
+            Err(errors) => return Err(errors),
+        }
+
+        match get(client, key, GetOptions::default()).await {
+            Ok(response) => return Ok((response, false)),
+            Err(ref errors) if has_api_error(errors, EtcdErrorCode::KeyNotFound) => {}
+            Err(errors) => return Err(errors),
+        }
+    }
+}
+
+/// Gets the key-value pairs in a directory tree, returning a stream that yields them one at a
+/// time.
+///
+/// etcd can't paginate a directory itself, so rather than reading the whole tree at once like a
+/// recursive `kv::get`, this reads one directory at a time, sorted, and only reads a
+/// subdirectory once the stream reaches it. Key-value pairs are read in pages of up to
+/// `page_size` and yielded in sorted order across the whole tree, and directories themselves
+/// are not yielded. If `key` is a key-value pair rather than a directory, it is yielded on its
+/// own.
+///
+/// The stream is not a consistent snapshot: each directory is read at a different etcd index,
+/// so changes made while the stream is being consumed may or may not be reflected in later
+/// pages.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API calls.
+/// * key: The name of the directory to retrieve.
+/// * page_size: The maximum number of key-value pairs to read ahead of the consumer. No more
+/// directories are read until the current page has been yielded. A page size of zero is treated
+/// as one.
+///
+/// # Errors
+///
+/// Yields an error if a directory can't be read. The stream continues with the rest of the tree
+/// after an error, skipping the directory that couldn't be read.
+pub fn get_paged<C>(
+    client: &Client<C>,
+    key: &str,
+    page_size: usize,
+) -> impl Stream<Item = Result<Node, Vec<Error>>> + Send
+where
+    C: Clone + Connect + Sync + Send + 'static,
+{
+    let page_size = page_size.max(1);
+    let mut pending = VecDeque::new();
+    pending.push_back(PagedNode::Dir(key.to_owned()));
+
+    stream::unfold(
+        (client.clone(), pending),
+        move |(client, mut pending)| async move {
+            let mut page = Vec::new();
+
+            while page.len() < page_size {
+                let key = match pending.pop_front() {
+                    Some(PagedNode::Leaf(node)) => {
+                        page.push(node);
+                        continue;
+                    }
+                    Some(PagedNode::Dir(key)) => key,
+                    None => break,
+                };
+
+                let options = GetOptions {
+                    sort: true,
+                    ..Default::default()
+                };
+
+                let node = match get(&client, &key, options).await {
+                    Ok(response) => response.data.node,
+                    Err(errors) if page.is_empty() => {
+                        return Some((Err(errors), (client, pending)))
+                    }
+                    Err(_) => {
+                        // Yield the current page first, then try the directory again for the next.
+                        pending.push_front(PagedNode::Dir(key));
+                        break;
+                    }
+                };
+
+                if node.dir != Some(true) {
+                    pending.push_front(PagedNode::Leaf(node));
+                    continue;
+                }
+
+                for child in node.nodes.unwrap_or_default().into_iter().rev() {
+                    match child.key {
+                        Some(ref key) if child.dir == Some(true) => {
+                            pending.push_front(PagedNode::Dir(key.clone()))
+                        }
+                        _ => pending.push_front(PagedNode::Leaf(child)),
+                    }
+                }
+            }
+
+            if page.is_empty() {
+                None
+            } else {
+                Some((Ok(page), (client, pending)))
+            }
+        },
+    )
+    .flat_map(|page| {
+        stream::iter(match page {
+            Ok(nodes) => nodes.into_iter().map(Ok).collect(),
+            Err(errors) => vec![Err(errors)],
+        })
+    })
+}
+
+/// Gets a node as the raw JSON body of etcd's response.
+///
+/// This is an escape hatch for reading fields of the response that the typed API doesn't expose
+/// yet, for example ones added by newer versions of etcd. Prefer `kv::get` where it suffices.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the node to retrieve.
+/// * options: Options to customize the behavior of the operation.
+///
+/// # Errors
+///
+/// Fails if the key doesn't exist.
+pub async fn get_raw<C>(
+    client: &Client<C>,
+    key: &str,
+    options: GetOptions,
+) -> Result<Response<Value>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    get_with_fallback(client, key, options).await
+}
+
+/// Gets the value of a key-value pair, deserializing it from JSON.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the key-value pair to retrieve.
+/// * options: Options to customize the behavior of the operation.
+///
+/// # Errors
+///
+/// Fails if the key doesn't exist, with `Error::MissingValue` if the node is a directory or has no
+/// value, or with `Error::Serialization` if the value is not valid JSON for `T`.
+pub async fn get_typed<C, T>(
+    client: &Client<C>,
+    key: &str,
+    options: GetOptions,
+) -> Result<Response<T>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+    T: DeserializeOwned,
+{
+    let response = get(client, key, options).await?;
+
+    let value = match response.data.node.value {
+        Some(ref value) if response.data.node.dir != Some(true) => value,
+        _ => return Err(vec![Error::MissingValue]),
+    };
+
+    let data = serde_json::from_str(value).map_err(|error| vec![Error::from(error)])?;
+
+    Ok(Response {
+        cluster_info: response.cluster_info,
+        data,
+        headers: response.headers,
+        is_stale: response.is_stale,
+        status: response.status,
+    })
+}
+
+/// Atomically adds `delta` to the integer stored in a key-value pair, returning the new value.
+///
+/// The current value is read and the sum is written back with a compare-and-swap on the node's
+/// modified index, so concurrent increments are never lost. A key that doesn't exist is treated
+/// as holding zero and is created. If another client changes the key in between, the increment is
+/// tried again, up to 10 times in total. Any TTL the key had is removed.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API calls.
+/// * key: The name of the key-value pair holding the counter.
+/// * delta: The amount to add to the counter, which may be negative.
+///
+/// # Errors
+///
+/// Fails with `Error::MissingValue` if the node is a directory, or with `Error::InvalidCounter`
+/// if the stored value isn't an integer or adding `delta` to it would overflow an `i64`. If every
+/// attempt loses a race with another client, fails with the error from the last attempt.
+pub async fn increment<C>(
+    client: &Client<C>,
+    key: &str,
+    delta: i64,
+) -> Result<Response<i64>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    let mut attempt = 0;
+
+    loop {
+        attempt += 1;
+
+        let (result, value) = match get(client, key, GetOptions::default()).await {
+            Ok(response) => {
+                let node = response.data.node;
+
+                let stored = match node.value {
+                    Some(ref value) if node.dir != Some(true) => value,
+                    _ => return Err(vec![Error::MissingValue]),
+                };
+
+                let value = stored
+                    .trim()
+                    .parse::<i64>()
+                    .ok()
+                    .and_then(|current| current.checked_add(delta))
+                    .ok_or_else(|| vec![Error::InvalidCounter(stored.clone())])?;
+
+                let result = compare_and_swap(
+                    client,
+                    key,
+                    &value.to_string(),
+                    None,
+                    None,
+                    node.modified_index,
+                )
+                .await;
+
+                (result, value)
+            }
+            Err(ref errors) if has_api_error(errors, EtcdErrorCode::KeyNotFound) => {
+                (create(client, key, &delta.to_string(), None).await, delta)
+            }
+            Err(errors) => return Err(errors),
+        };
+
+        match result {
+            Ok(response) => {
+                return Ok(Response {
+                    cluster_info: response.cluster_info,
+                    data: value,
+                    headers: response.headers,
+                    is_stale: response.is_stale,
+                    status: response.status,
+                })
+            }
+            Err(ref errors)
+                if attempt < MAX_INCREMENT_ATTEMPTS
+                    && (has_api_error(errors, EtcdErrorCode::CompareFailed)
+                        || has_api_error(errors, EtcdErrorCode::KeyNotFound)
+                        || has_api_error(errors, EtcdErrorCode::KeyAlreadyExists)) => {}
+            Err(errors) => return Err(errors),
+        }
+    }
+}
+
+/// Sets a key-value pair with a TTL and keeps it alive until the returned handle is dropped.
+///
+/// This is the usual way to create an ephemeral "session" key, which disappears on its own
+/// shortly after the process that owns it stops. Once the key is set, a task spawned on the Tokio
+/// runtime refreshes its TTL with `kv::refresh` every third of `ttl`, give or take some random
+/// jitter so that many clients started at once don't refresh in lockstep. A refresh that fails
+/// with a transient error is retried sooner, until the key's TTL runs out. See `KeepAlive::lost`
+/// for when renewal stops for good.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API calls.
+/// * key: The name of the key-value pair to keep alive.
+/// * value: The value for the key-value pair.
+/// * ttl: The number of seconds after which the key expires unless refreshed.
+///
+/// # Errors
+///
+/// Fails with `Error::ZeroTtl` if `ttl` is zero, or if the key can't be set. Failures to refresh
+/// it afterwards are reported by `KeepAlive::lost`.
+pub async fn keep_alive<C>(
+    client: &Client<C>,
+    key: &str,
+    value: &str,
+    ttl: u64,
+) -> Result<KeepAlive, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send + 'static,
+{
+    if ttl == 0 {
+        return Err(vec![Error::ZeroTtl]);
+    }
+
+    set(client, key, value, Some(ttl)).await?;
+
+    let (alive, lost, renewal) = {
+        let client = client.clone();
+        let key = key.to_owned();
+
+        spawn_renewal(ttl, move || {
+            let client = client.clone();
+            let key = key.clone();
+
+            async move { refresh(&client, &key, ttl).await.map(|_| ()) }
+        })
+    };
+
+    Ok(KeepAlive {
+        key: key.to_owned(),
+        alive,
+        lost,
+        renewal,
+    })
+}
+
+/// Lists the direct children of a directory, sorted by key.
+///
+/// Unlike a recursive `kv::get`, the contents of child directories are not included.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the directory to list.
+///
+/// # Errors
+///
+/// Fails if the directory doesn't exist, or with `Error::NotADirectory` if the node is a
+/// key-value pair.
+pub async fn list_dir<C>(
+    client: &Client<C>,
+    key: &str,
+) -> Result<Response<Vec<DirEntry>>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    let options = GetOptions {
+        sort: true,
+        ..Default::default()
+    };
+    let response = get(client, key, options).await?;
+    let node = response.data.node;
+
+    if node.dir != Some(true) {
+        return Err(vec![Error::NotADirectory]);
+    }
+
+    let entries = node
+        .nodes
+        .unwrap_or_default()
+        .into_iter()
+        .map(|child| DirEntry {
+            key: child.key.unwrap_or_default(),
+            is_dir: child.dir == Some(true),
+            value: child.value,
+        })
+        .collect();
+
+    Ok(Response {
+        cluster_info: response.cluster_info,
+        data: entries,
+        headers: response.headers,
+        is_stale: response.is_stale,
+        status: response.status,
+    })
+}
+
+/// Lists the direct children of a directory that aren't hidden, sorted by key.
+///
+/// Like `kv::list_dir`, but leaves out any child whose name starts with an underscore, which etcd
+/// treats as hidden. etcd already omits these from directory listings, but this doesn't rely on
+/// it.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the directory to list.
+///
+/// # Errors
+///
+/// Fails if the directory doesn't exist, or with `Error::NotADirectory` if the node is a
+/// key-value pair.
+pub async fn list_visible<C>(
+    client: &Client<C>,
+    key: &str,
+) -> Result<Response<Vec<DirEntry>>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    let mut response = list_dir(client, key).await?;
+
+    response.data.retain(|entry| !is_hidden_key(&entry.key));
+
+    Ok(response)
+}
+
+/// Returns the method, URL and body of the request that `kv::set` would send to the given
+/// endpoint, without sending it.
+///
+/// The request is built the same way as `kv::set` builds it, so this is useful for debugging or
+/// for reproducing a request with another tool, such as curl. The body is form-encoded. Headers,
+/// such as those for authentication, are not included.
+///
+/// # Parameters
+///
+/// * endpoint: The URL of a cluster member.
+/// * key: The name of the key-value pair to set.
+/// * value: The new value for the key-value pair.
+/// * ttl: If given, the node will expire after this many seconds.
+///
+/// # Errors
+///
+/// Fails if the endpoint is an invalid URL.
+pub fn preview_set(
+    endpoint: &str,
+    key: &str,
+    value: &str,
+    ttl: Option<u64>,
+) -> Result<(Method, String, String), Error> {
+    let uri = endpoint.parse().map_err(|source| Error::InvalidEndpoint {
+        endpoint: endpoint.to_owned(),
+        source,
+    })?;
+    let options = InternalSetOptions {
+        ttl,
+        value: Some(value),
+        ..Default::default()
+    };
+    let body = encode_form(&set_form(&options, None));
+
+    Ok((Method::PUT, build_url(&normalize_endpoint(uri), key), body))
+}
+
+/// Resets the TTL of an existing node without changing its value.
+///
+/// Unlike updating the node with a new TTL, refreshing it does not notify watchers. The response
+/// reports the action as `Action::Update`.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the node to refresh.
+/// * ttl: The number of seconds after which the node will now expire.
+///
+/// # Errors
+///
+/// Fails if the node does not exist, with etcd's "key not found" error (code 100).
+pub async fn refresh<C>(
+    client: &Client<C>,
+    key: &str,
+    ttl: u64,
+) -> Result<Response<KeyValueInfo>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    raw_set(
+        client,
+        key,
+        InternalSetOptions {
+            prev_exist: Some(true),
+            refresh: true,
+            ttl: Some(ttl),
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+/// Sets the value of a key-value pair.
+///
+/// Any previous value and TTL will be replaced.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the key-value pair to set.
+/// * value: The new value for the key-value pair.
+/// * ttl: If given, the node will expire after this many seconds.
+///
+/// # Errors
+///
+/// Fails if the node is a directory.
+pub async fn set<C>(
+    client: &Client<C>,
+    key: &str,
+    value: &str,
+    ttl: Option<u64>,
+) -> Result<Response<KeyValueInfo>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    raw_set(
+        client,
+        key,
+        InternalSetOptions {
+            ttl: ttl,
+            value: Some(value),
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+/// Sets the value of a key-value pair to binary data, encoded as base64.
+///
+/// etcd values are strings, so the data is stored base64-encoded. Read it back with
+/// `kv::get_bytes`, which decodes it again; `kv::get` returns the encoded string.
+///
+/// Any previous value and TTL will be replaced.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the key-value pair to set.
+/// * value: The new value for the key-value pair.
+/// * ttl: If given, the node will expire after this many seconds.
+///
+/// # Errors
+///
+/// Fails if the node is a directory.
+pub async fn set_bytes<C>(
+    client: &Client<C>,
+    key: &str,
+    value: &[u8],
+    ttl: Option<u64>,
+) -> Result<Response<KeyValueInfo>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    set(client, key, &base64::encode(value), ttl).await
+}
+
+/// Sets the value of a hidden key-value pair.
+///
+/// The last segment of `key` is prefixed with an underscore if it doesn't already start with
+/// one, so that etcd leaves the key out of listings of its directory. For example, "/app/lock" is
+/// stored as "/app/_lock". The key actually written is the key of the node in the response.
+///
+/// Any previous value and TTL will be replaced.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the key-value pair to set.
+/// * value: The new value for the key-value pair.
+/// * ttl: If given, the node will expire after this many seconds.
+///
+/// # Errors
+///
+/// Fails if the node is a directory.
+pub async fn set_hidden<C>(
+    client: &Client<C>,
+    key: &str,
+    value: &str,
+    ttl: Option<u64>,
+) -> Result<Response<KeyValueInfo>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    set(client, &hidden_key(key), value, ttl).await
+}
+
+/// Sets the key to an empty directory.
+///
+/// An existing key-value pair will be replaced, but an existing directory will not.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the directory to set.
+/// * ttl: If given, the node will expire after this many seconds.
+///
+/// # Errors
+///
+/// Fails if the node is an existing directory.
+pub async fn set_dir<C>(
+    client: &Client<C>,
+    key: &str,
+    ttl: Option<u64>,
+) -> Result<Response<KeyValueInfo>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    raw_set(
+        client,
+        key,
+        InternalSetOptions {
+            dir: Some(true),
+            ttl: ttl,
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+/// Creates a new key-value pair only if it doesn't exist yet, returning whether it was created.
+///
+/// This is like `kv::create`, except that an existing key is reported as `false` rather than as
+/// an error. The response for an existing key carries only the etcd index reported with the
+/// failure.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the key-value pair to create.
+/// * value: The value for the new node.
+/// * ttl: If given, the node will expire after this many seconds.
+///
+/// # Errors
+///
+/// Fails for any reason other than the key already existing.
+pub async fn set_if_absent<C>(
+    client: &Client<C>,
+    key: &str,
+    value: &str,
+    ttl: Option<u64>,
+) -> Result<Response<bool>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    into_bool_response(
+        create(client, key, value, ttl).await,
+        EtcdErrorCode::KeyAlreadyExists,
+    )
+}
+
+/// Sets the value of a key-value pair to the JSON serialization of `value`.
+///
+/// Any previous value and TTL will be replaced.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the key-value pair to set.
+/// * value: The new value for the key-value pair.
+/// * ttl: If given, the node will expire after this many seconds.
+///
+/// # Errors
+///
+/// Fails if the node is a directory, or with `Error::Serialization` if `value` cannot be
+/// serialized.
+pub async fn set_typed<C, T>(
+    client: &Client<C>,
+    key: &str,
+    value: &T,
+    ttl: Option<u64>,
+) -> Result<Response<KeyValueInfo>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+    T: serde::Serialize + ?Sized,
+{
+    let value = serde_json::to_string(value).map_err(|error| vec![Error::from(error)])?;
+
+    set(client, key, &value, ttl).await
+}
+
+/// Sets the value of a key-value pair, with any combination of a TTL and conditions.
+///
+/// This is the general form of `kv::set`, `kv::create`, `kv::update` and `kv::compare_and_swap`.
+/// When several conditions are given, the set only succeeds if all of them match.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the key-value pair to set.
+/// * value: The new value for the key-value pair.
+/// * options: Options to customize the behavior of the operation.
+///
+/// # Errors
+///
+/// Fails if the node is a directory, or if any of the conditions didn't match.
+pub async fn set_with_options<C>(
+    client: &Client<C>,
+    key: &str,
+    value: &str,
+    options: SetOptions,
+) -> Result<Response<KeyValueInfo>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    let conditions = ComparisonConditions {
+        modified_index: options.prev_index,
+        value: options.prev_value.as_deref(),
+    };
+
+    raw_set(
+        client,
+        key,
+        InternalSetOptions {
+            conditions: if conditions.is_empty() {
+                None
+            } else {
+                Some(conditions)
+            },
+            prev_exist: options.prev_exist,
+            ttl: options.ttl,
+            value: Some(value),
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+/// Gets how long a key has left before it expires.
+///
+/// The remaining time is computed from the node's expiration timestamp and the local clock, which
+/// is more precise than the whole number of seconds etcd reports as the node's TTL. If the local
+/// clock disagrees with etcd's reported TTL by more than clock skew and rounding can account for,
+/// the reported TTL is used instead. Either way, the result is only as fresh as the response, so
+/// leave some margin when deciding when to refresh the key.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the key-value pair to check.
+///
+/// # Errors
+///
+/// Fails if the key does not exist. Succeeds with `None` if the key doesn't expire.
+pub async fn ttl_remaining<C>(
+    client: &Client<C>,
+    key: &str,
+) -> Result<Response<Option<Duration>>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    let response = get(client, key, GetOptions::default()).await?;
+
+    Ok(Response {
+        data: remaining_ttl(&response.data.node, SystemTime::now()),
+        cluster_info: response.cluster_info,
+        headers: response.headers,
+        is_stale: response.is_stale,
+        status: response.status,
+    })
+}
+
+/// Updates an existing key-value pair.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the key-value pair to update.
+/// * value: The new value for the key-value pair.
+/// * ttl: If given, the node will expire after this many seconds.
+///
+/// # Errors
+///
+/// Fails if the key does not exist.
+pub async fn update<C>(
+    client: &Client<C>,
+    key: &str,
+    value: &str,
+    ttl: Option<u64>,
+) -> Result<Response<KeyValueInfo>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    raw_set(
+        client,
+        key,
+        InternalSetOptions {
+            prev_exist: Some(true),
+            ttl: ttl,
+            value: Some(value),
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+/// Updates a directory.
+///
+/// If the directory already existed, only the TTL is updated. If the key was a key-value pair, its
+/// value is removed and its TTL is updated.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the node to update.
+/// * ttl: If given, the node will expire after this many seconds.
+///
+/// # Errors
+///
+/// Fails if the node does not exist.
+pub async fn update_dir<C>(
+    client: &Client<C>,
+    key: &str,
+    ttl: Option<u64>,
+) -> Result<Response<KeyValueInfo>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    raw_set(
+        client,
+        key,
+        InternalSetOptions {
+            dir: Some(true),
+            prev_exist: Some(true),
+            ttl: ttl,
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+fn flatten_result<T, E>(res: Result<Result<T, E>, E>) -> Result<T, E> {
+    res.and_then(|n: Result<T, E>| n)
+}
+
+/// Waits until a key-value pair has the expected value.
+///
+/// The key is read first, and if it already has the expected value, it is returned right away.
+/// Otherwise, the key is watched from just after its last change, through as many changes as it
+/// takes, until one leaves it with the expected value. The key doesn't need to exist yet.
+///
+/// If the changes being watched are cleared from etcd's event history in the meantime, the key is
+/// read again and watched from there.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API calls.
+/// * key: The name of the key-value pair to wait for.
+/// * expected: The value to wait for.
+/// * timeout: If given, how long to wait in total before giving up.
+///
+/// # Errors
+///
+/// Fails with `WatchError::Timeout` if the timeout lapses before the key has the expected value,
+/// or with `WatchError::Other` if reading or watching the key fails.
+pub async fn wait_for_value<C>(
+    client: &Client<C>,
+    key: &str,
+    expected: &str,
+    timeout: Option<Duration>,
+) -> Result<Response<KeyValueInfo>, WatchError>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    let work = async {
+        loop {
+            let mut index = match get(client, key, GetOptions::default()).await {
+                Ok(response) => {
+                    if response.data.node.value.as_deref() == Some(expected) {
+                        return Ok(response);
+                    }
+
+                    response.data.node.modified_index.unwrap_or_default()
+                }
+                Err(errors) => {
+                    let current_index = errors.iter().find_map(|error| match error {
+                        Error::Api(error) if error.code() == EtcdErrorCode::KeyNotFound => {
+                            error.index
+                        }
+                        _ => None,
+                    });
+
+                    match current_index {
+                        Some(current_index) => current_index,
+                        None => return Err(WatchError::Other(errors)),
+                    }
+                }
+            };
+
+            loop {
+                let options = WatchOptions {
+                    index: Some(index + 1),
+                    ..Default::default()
+                };
+
+                match watch(client, key, options).await {
+                    Ok(response) => {
+                        if response.data.current_value() == Some(expected) {
+                            return Ok(response);
+                        }
+
+                        index = response.data.node.modified_index.unwrap_or(index + 1);
+                    }
+                    Err(WatchError::Outdated { .. }) => break,
+                    Err(error) => return Err(error),
+                }
+            }
+        }
+    };
+
+    match timeout {
+        Some(duration) => tokio::time::timeout(duration, work)
+            .await
+            .unwrap_or(Err(WatchError::Timeout)),
+        None => work.await,
+    }
+}
+
+/// Watches a node for changes and returns the new value as soon as a change takes place.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the node to watch.
+/// * options: Options to customize the behavior of the operation.
+///
+/// # Errors
+///
+/// Fails with `WatchError::Outdated` if `options.index` is too old and has been flushed out of
+/// etcd's internal store of the most recent change events. The error carries the current etcd
+/// index, one past which can be used as the new `options.index` on a subsequent `watch`.
+///
+/// Fails if a timeout is specified and the duration lapses without a response from the etcd
+/// cluster.
+pub async fn watch<C>(
+    client: &Client<C>,
+    key: &str,
+    options: WatchOptions,
+) -> Result<Response<KeyValueInfo>, WatchError>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    let work = raw_get(
+        client,
+        key,
+        InternalGetOptions {
+            recursive: options.recursive,
+            wait_index: options.index,
+            wait: true,
+            ..Default::default()
+        },
+    )
+    .map_err(|errors| {
+        let current_index = errors.iter().find_map(|error| match error {
+            Error::Api(error) if error.code() == EtcdErrorCode::EventIndexCleared => error.index,
+            _ => None,
+        });
+
+        match current_index {
+            Some(current_index) => WatchError::Outdated { current_index },
+            None => WatchError::Other(errors),
+        }
+    });
+
+    if let Some(duration) = options.timeout {
+        timeout(duration, work).err_into().map(flatten_result).await
+    } else {
+        work.await
+    }
+}
+
+/// Watches a node for changes in a background task, sending every change to a bounded channel.
+///
+/// Like `kv::changes_since`, each change is returned as the `Response` that `kv::watch` would have
+/// returned for it. The task watches with `kv::watch_stream`, so the same options apply and
+/// changes are never skipped. When the channel is full, the task waits for the receiver to take a
+/// change before it watches for the next one, so a slow consumer holds back the watch rather than
+/// letting changes pile up in memory. Changes made in the meantime are reported once the task
+/// resumes, or are skipped ahead of if they have been cleared from etcd's event history.
+///
+/// The task runs until the handle is dropped or stopped, the receiver is dropped, or the watch is
+/// cancelled with `Client::cancel_all_watches`. The receiver ends once the task stops.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API calls.
+/// * key: The name of the node to watch.
+/// * options: Options to customize the behavior of the operation. The timeout, if given, applies
+/// to each change individually.
+/// * buffer: The number of changes the channel holds before the task waits for the receiver.
+///
+/// # Errors
+///
+/// Sends an error for each failed watch. The task continues after an error, retrying from the
+/// same index.
+///
+/// # Panics
+///
+/// Panics if `buffer` is zero.
+pub fn watch_channel<C>(
+    client: &Client<C>,
+    key: &str,
+    options: WatchOptions,
+    buffer: usize,
+) -> (
+    WatchHandle,
+    mpsc::Receiver<Result<Response<KeyValueInfo>, WatchError>>,
+)
+where
+    C: Clone + Connect + Sync + Send + 'static,
+{
+    let (mut sender, receiver) = mpsc::channel(buffer);
+    let mut changes = watch_stream(client, key, options)
+        .map(|result| result.map(|event| Response::new(event.info, event.cluster_info)))
+        .boxed();
+
+    let task = async move {
+        while let Some(change) = changes.next().await {
+            if sender.send(change).await.is_err() {
+                break;
+            }
+        }
+    };
+
+    let (producer, registration) = AbortHandle::new_pair();
+    tokio::spawn(Abortable::new(task, registration));
+
+    (WatchHandle { producer }, receiver)
+}
+
+/// Watches a node for changes, returning a stream of every change as it takes place.
+///
+/// Unlike `kv::watch`, which returns a single change, the stream keeps watching after each change,
+/// starting the next watch at the index just after the previous change so that no changes are
+/// skipped.
+///
+/// Each `WatchEvent` includes the gap between its modified index and the previous event's. etcd
+/// indexes are shared by every key in the cluster, so a gap greater than one means that other
+/// changes were made in between, which were either to nodes outside the watched key or were
+/// coalesced and not reported individually. A gap of exactly one guarantees that nothing was
+/// missed. Callers that only need the latest state can ignore the gap, while callers that need
+/// to see every change, such as audit logs, can treat a large gap as a signal to resynchronize
+/// by reading the node in full.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API calls.
+/// * key: The name of the node to watch.
+/// * options: Options to customize the behavior of the operation. The timeout, if given, applies
+/// to each change individually.
+///
+/// If the index to watch from has been cleared from etcd's event history, the node is read again
+/// to find the current etcd index and the stream resumes from there, rather than yielding an
+/// error. The `index_gap` of the next event then covers the changes that were skipped.
+///
+/// The stream never ends on its own. It ends when it is dropped, or when it is cancelled with
+/// `Client::cancel_all_watches`.
+///
+/// # Errors
+///
+/// Yields an error for each failed watch. The stream continues after an error, retrying from the
+/// same index.
+pub fn watch_stream<C>(
+    client: &Client<C>,
+    key: &str,
+    options: WatchOptions,
+) -> impl Stream<Item = Result<WatchEvent, WatchError>> + Send
+where
+    C: Clone + Connect + Sync + Send + 'static,
+{
+    let (guard, registration) = client.register_watch();
+    let previous_index = options.index.map(|index| index.saturating_sub(1));
+    let state = (
+        client.clone(),
+        key.to_owned(),
+        options,
+        previous_index,
+        guard,
+    );
+
+    let events = stream::unfold(state, |state| async move {
+        let (client, key, mut options, mut previous_index, guard) = state;
+
+        let result = loop {
+            match watch(&client, &key, options).await {
+                Err(WatchError::Outdated { current_index }) => {
+                    options.index = Some(current_index + 1);
+                    previous_index = previous_index.or(Some(current_index));
+                }
+                // A compatible server may not report the current index with the error.
+                Err(WatchError::Other(errors))
+                    if has_api_error(&errors, EtcdErrorCode::EventIndexCleared) =>
+                {
+                    match current_index(&client, &key).await {
+                        Some(index) => {
+                            options.index = Some(index + 1);
+                            previous_index = previous_index.or(Some(index));
+                        }
+                        None => break Err(WatchError::Other(errors)),
+                    }
+                }
+                result => break result,
+            }
+        };
+
+        let result = result.map(|response| {
+            let modified_index = response.data.node.modified_index.unwrap_or(0);
+            let index_gap = previous_index.map_or(0, |index| modified_index.saturating_sub(index));
+
+            options.index = Some(modified_index + 1);
+            previous_index = Some(modified_index);
+
+            WatchEvent {
+                info: response.data,
+                cluster_info: response.cluster_info,
+                index_gap,
+            }
+        });
+
+        Some((result, (client, key, options, previous_index, guard)))
+    });
+
+    Abortable::new(events, registration)
+}
+
+/// The outcome of trying to claim the oldest item in a queue.
+enum Claim {
+    /// The key and value of the claimed item.
+    Item(String, String),
+    /// No item could be claimed. Holds the etcd index at which the queue was listed, if known.
+    Empty(Option<u64>),
+}
+
+/// A node that `kv::get_paged` has yet to yield, or a directory it has yet to read.
+enum PagedNode {
+    Dir(String),
+    Leaf(Node),
+}
+
+/// Constructs the full URL for an API call.
+fn build_url(endpoint: &Uri, path: &str) -> String {
+    format!("{}v2/keys{}", endpoint, path)
+}
+
+/// Encodes the fields of a form as a request body.
+fn encode_form(fields: &[(String, String)]) -> String {
+    let mut serializer = Serializer::new(String::new());
+    serializer.extend_pairs(fields);
+    serializer.finish()
+}
+
+/// Rejects a response from a member that hasn't caught up to the given etcd index yet, so that
+/// the read is retried against the next member.
+fn check_read_index<T>(
+    response: Response<T>,
+    required_index: Option<u64>,
+) -> Result<Response<T>, Error> {
+    match (required_index, response.cluster_info.etcd_index) {
+        (Some(required), Some(actual)) if actual < required => {
+            Err(Error::StaleRead { required, actual })
+        }
+        _ => Ok(response),
+    }
+}
+
+/// Reads the current etcd index by getting a node, which may not exist.
+///
+/// Returns `None` if the index could not be read.
+async fn current_index<C>(client: &Client<C>, key: &str) -> Option<u64>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    match get(client, key, GetOptions::default()).await {
+        Ok(response) => response.cluster_info.etcd_index,
+        Err(errors) => missing_key_index(&errors),
+    }
+}
+
+/// Claims the oldest item in a queue directory as `kv::claim_oldest` does, returning the etcd
+/// index at which the queue was last listed if no item could be claimed.
+async fn try_claim_oldest<C>(
+    client: &Client<C>,
+    queue_dir: &str,
+    claim_ttl: u64,
+) -> Result<Claim, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+{
+    let options = GetOptions {
+        sort: true,
+        ..Default::default()
+    };
+    let claimed_dir = format!("{}/_claimed", queue_dir.trim_end_matches('/'));
+
+    loop {
+        let response = match get(client, queue_dir, options).await {
+            Ok(response) => response,
+            Err(ref errors) if has_api_error(errors, EtcdErrorCode::KeyNotFound) => {
+                return Ok(Claim::Empty(missing_key_index(errors)));
+            }
+            Err(errors) => return Err(errors),
+        };
+        let index = response.cluster_info.etcd_index;
+
+        let items = response
+            .data
+            .node
+            .nodes
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|node| node.dir != Some(true));
+
+        // Only an item removed by another consumer means the listing is out of date. Items that
+        // are merely being claimed by another consumer would be listed again.
+        let mut lost_race = false;
+
+        for item in items {
+            let key = item.key.unwrap_or_default();
+            let value = item.value.unwrap_or_default();
+            let name = key.rsplit('/').next().unwrap_or_default();
+            let marker = format!("{}/{}", claimed_dir, name);
+
+            match create(client, &marker, &value, Some(claim_ttl)).await {
+                Ok(_) => {}
+                Err(ref errors) if has_api_error(errors, EtcdErrorCode::KeyAlreadyExists) => {
+                    continue
+                }
+                Err(errors) => return Err(errors),
+            }
+
+            match compare_and_delete(client, &key, None, item.modified_index).await {
+                Ok(_) => return Ok(Claim::Item(key, value)),
+                Err(ref errors)
+                    if has_api_error(errors, EtcdErrorCode::CompareFailed)
+                        || has_api_error(errors, EtcdErrorCode::KeyNotFound) =>
+                {
+                    lost_race = true;
+                }
+                Err(errors) => return Err(errors),
+            }
+        }
+
+        if !lost_race {
+            return Ok(Claim::Empty(index));
+        }
+    }
+}
+
+/// Collects the key of every node in a tree that has no children.
+fn collect_leaf_keys(node: &Node, out: &mut Vec<String>) {
+    match node.nodes {
+        Some(ref nodes) if !nodes.is_empty() => {
+            for node in nodes {
+                collect_leaf_keys(node, out);
+            }
+        }
+        _ => out.extend(node.key.clone()),
+    }
+}
+
+/// Maps the key of every node in a tree to its value, or to `None` for directories.
+fn collect_values<'a>(node: &'a Node, out: &mut BTreeMap<&'a str, Option<&'a str>>) {
+    if let Some(ref key) = node.key {
+        let value = if node.dir == Some(true) {
+            None
+        } else {
+            Some(node.value.as_deref().unwrap_or(""))
+        };
+
+        out.insert(key, value);
+    }
+
+    for child in node.nodes.iter().flatten() {
+        collect_values(child, out);
+    }
+}
+
+/// Moves every node in a tree into a flat list, detaching each node from its children.
+fn flatten_nodes(nodes: Vec<Node>, out: &mut Vec<Node>) {
+    for mut node in nodes {
+        let children = node.nodes.take();
+
+        out.push(node);
+
+        if let Some(children) = children {
+            flatten_nodes(children, out);
+        }
+    }
+}
+
+/// Form-encodes request parameters for logging, optionally replacing any values with a
+/// placeholder.
+#[cfg(feature = "tracing")]
+fn loggable_form<'a, I>(pairs: I, redact_values: bool) -> String
+where
+    I: Iterator<Item = (&'a str, &'a str)>,
+{
+    let mut serializer = Serializer::new(String::new());
+
+    for (name, value) in pairs {
+        if redact_values && (name == "value" || name == "prevValue") {
+            serializer.append_pair(name, "[redacted]");
+        } else {
+            serializer.append_pair(name, value);
+        }
+    }
+
+    serializer.finish()
+}
+
+/// Computes how long a node has left before it expires as of `now`, preferring its expiration
+/// timestamp to its reported TTL unless the two disagree.
+fn remaining_ttl(node: &Node, now: SystemTime) -> Option<Duration> {
+    let reported = node.ttl_duration()?;
+    let from_clock = node
+        .expiration_time()
+        .map(|expiration| expiration.duration_since(now).unwrap_or_default());
+
+    // etcd reports the TTL rounded up to a whole second, and the response took some time to
+    // arrive, so the clock-based value is trusted as long as it's within a couple of seconds.
+    match from_clock {
+        Some(remaining)
+            if remaining <= reported + Duration::from_secs(1)
+                && remaining + Duration::from_secs(2) >= reported =>
+        {
+            Some(remaining)
+        }
+        _ => Some(reported),
+    }
+}
+
+/// Parses an RFC 3339 timestamp such as `2013-12-04T12:01:21.874888581-08:00`, as used by etcd for
+/// node expirations.
+fn parse_rfc3339(timestamp: &str) -> Option<SystemTime> {
+    let number = |range: std::ops::Range<usize>| -> Option<i64> {
+        let digits = timestamp.get(range)?;
+
+        if digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            digits.parse().ok()
+        } else {
+            None
+        }
+    };
+
+    let bytes = timestamp.as_bytes();
+
+    if bytes.len() < 20
+        || bytes[4] != b'-'
+        || bytes[7] != b'-'
+        || (bytes[10] != b'T' && bytes[10] != b't')
+        || bytes[13] != b':'
+        || bytes[16] != b':'
+    {
+        return None;
+    }
+
+    let year = number(0..4)?;
+    let month = number(5..7)?;
+    let day = number(8..10)?;
+    let hour = number(11..13)?;
+    let minute = number(14..16)?;
+    let second = number(17..19)?;
+
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+
+    let mut rest = &timestamp[19..];
+    let mut nanos = 0u32;
+
+    if rest.starts_with('.') {
+        let digits = rest[1..].bytes().take_while(u8::is_ascii_digit).count();
+
+        if digits == 0 {
+            return None;
+        }
+
+        for (i, byte) in rest[1..=digits].bytes().enumerate() {
+            if i < 9 {
+                nanos = nanos * 10 + u32::from(byte - b'0');
+            }
+        }
+
+        for _ in digits..9 {
+            nanos *= 10;
+        }
+
+        rest = &rest[digits + 1..];
+    }
+
+    let offset = match rest {
+        "Z" | "z" => 0,
+        _ if rest.len() == 6 && rest.as_bytes()[3] == b':' => {
+            let sign = match rest.as_bytes()[0] {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return None,
+            };
+            let hours: i64 = rest[1..3].parse().ok()?;
+            let minutes: i64 = rest[4..6].parse().ok()?;
+
+            sign * (hours * 3600 + minutes * 60)
+        }
+        _ => return None,
+    };
+
+    // Converts the civil date to days since the Unix epoch.
+    let (year, month) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let seconds = days * 86400 + hour * 3600 + minute * 60 + second - offset;
+
+    if seconds < 0 {
+        return None;
+    }
+
+    Some(UNIX_EPOCH + Duration::new(seconds as u64, nanos))
+}
+
+/// Converts the result of a conditional operation into whether the condition held, treating an
+/// etcd API error with the given error code as the condition not holding.
+fn into_bool_response(
+    result: Result<Response<KeyValueInfo>, Vec<Error>>,
+    code: EtcdErrorCode,
+) -> Result<Response<bool>, Vec<Error>> {
+    let errors = match result {
+        Ok(response) => {
+            return Ok(Response {
+                data: true,
+                cluster_info: response.cluster_info,
+                headers: response.headers,
+                is_stale: response.is_stale,
+                status: response.status,
+            })
+        }
+        Err(errors) => errors,
+    };
+
+    let failed_at = errors.iter().find_map(|error| match error {
+        Error::Api(error) if error.code() == code => Some(error.index),
+        _ => None,
+    });
+
+    match failed_at {
+        Some(index) => {
+            let cluster_info = ClusterInfo {
+                etcd_index: index,
+                ..Default::default()
+            };
+
+            Ok(Response::new(false, cluster_info))
+        }
+        None => Err(errors),
+    }
+}
+
+/// Returns the hidden form of a key, with an underscore prepended to its last path segment.
+fn hidden_key(key: &str) -> String {
+    let key = key.trim_end_matches('/');
+
+    if is_hidden_key(key) {
+        return key.to_owned();
+    }
+
+    match key.rfind('/') {
+        Some(slash) => format!("{}_{}", &key[..=slash], &key[slash + 1..]),
+        None => format!("_{}", key),
+    }
+}
+
+/// Checks whether any of the errors is an etcd API error with the given error code.
+fn has_api_error(errors: &[Error], code: EtcdErrorCode) -> bool {
+    errors.iter().any(|error| is_api_error(error, code))
+}
+
+/// Checks whether an error is an etcd API error with the given error code.
+fn is_api_error(error: &Error, code: EtcdErrorCode) -> bool {
+    match error {
+        Error::Api(error) => error.code() == code,
+        _ => false,
+    }
+}
+
+/// Checks whether a key is hidden, i.e. whether its last path segment starts with an underscore.
+fn is_hidden_key(key: &str) -> bool {
+    key.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .is_some_and(|name| name.starts_with('_'))
+}
+
+/// Returns the etcd index reported by a "key not found" error among the given errors, if any.
+fn missing_key_index(errors: &[Error]) -> Option<u64> {
+    errors.iter().find_map(|error| match error {
+        Error::Api(error) if error.code() == EtcdErrorCode::KeyNotFound => error.index,
+        _ => None,
+    })
+}
+
+/// Spawns a task on the Tokio runtime that renews a key with `renew` until renewal fails for good.
+///
+/// The key is renewed every third of `ttl`, with random jitter so that many clients started at
+/// once don't renew in lockstep. A renewal that fails only with retryable errors is retried sooner,
+/// until the key's TTL has run out since the last successful renewal. Any other error stops the
+/// task straight away. The task never waits less than `MIN_RENEWAL_INTERVAL` between renewals.
+///
+/// Returns a flag that stays true while the key is being renewed, a future that resolves once
+/// renewal stops, and a handle to abort the task.
+pub(crate) fn spawn_renewal<F, Fut>(
+    ttl: u64,
+    mut renew: F,
+) -> (Arc<AtomicBool>, Shared<Receiver<()>>, AbortHandle)
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(), Vec<Error>>> + Send,
+{
+    let alive = Arc::new(AtomicBool::new(true));
+    let (lost_sender, lost) = channel();
+    let (renewal, registration) = AbortHandle::new_pair();
+
+    let task = {
+        let alive = alive.clone();
+
+        async move {
+            let ttl_duration = Duration::from_secs(ttl);
+            let interval = ttl_duration / 3;
+            let mut expires_at = Instant::now() + ttl_duration;
+            let mut delay = interval;
+
+            loop {
+                delay_for(jittered(delay).max(MIN_RENEWAL_INTERVAL)).await;
+
+                let sent_at = Instant::now();
+
+                match renew().await {
+                    Ok(()) => {
+                        expires_at = sent_at + ttl_duration;
+                        delay = interval;
+                    }
+                    Err(ref errors) if errors.iter().all(Error::is_retryable) => {
+                        if Instant::now() >= expires_at {
+                            break;
+                        }
+
+                        delay = interval / 3;
+                    }
+                    Err(_) => break,
+                }
+            }
+
+            alive.store(false, Ordering::SeqCst);
+
+            let _ = lost_sender.send(());
+        }
+    };
+
+    tokio::spawn(Abortable::new(task, registration));
+
+    (alive, lost.shared(), renewal)
+}
+
+/// Returns a random duration between half the given duration and the full duration.
+fn jittered(duration: Duration) -> Duration {
+    // Each `RandomState` is seeded differently, which is random enough to spread load.
+    let random = RandomState::new().build_hasher().finish();
+
+    duration / 2 + duration / 2 * (random % 1024) as u32 / 1024
+}
+
+/// Resets the TTL of a key-value pair without changing its value, as `kv::refresh` does, but only
+/// if the pair currently has the given value.
+pub(crate) async fn refresh_if_value<C>(
     client: &Client<C>,
     key: &str,
-    ttl: Option<u64>,
+    current_value: &str,
+    ttl: u64,
 ) -> Result<Response<KeyValueInfo>, Vec<Error>>
 where
     C: Clone + Connect + Sync + Send,
@@ -527,67 +3613,69 @@ where
     raw_set(
         client,
         key,
-        SetOptions {
-            dir: Some(true),
-            prev_exist: Some(true),
-            ttl: ttl,
+        InternalSetOptions {
+            conditions: Some(ComparisonConditions {
+                value: Some(current_value),
+                modified_index: None,
+            }),
+            refresh: true,
+            ttl: Some(ttl),
             ..Default::default()
         },
     )
     .await
 }
 
-fn flatten_result<T, E>(res: Result<Result<T, E>, E>) -> Result<T, E> {
-    res.and_then(|n: Result<T, E>| n)
-}
-
-/// Watches a node for changes and returns the new value as soon as a change takes place.
-///
-/// # Parameters
-///
-/// * client: A `Client` to use to make the API call.
-/// * key: The name of the node to watch.
-/// * options: Options to customize the behavior of the operation.
-///
-/// # Errors
-///
-/// Fails if `options.index` is too old and has been flushed out of etcd's internal store of the
-/// most recent change events. In this case, the key should be queried for its latest
-/// "modified index" value and that should be used as the new `options.index` on a subsequent
-/// `watch`.
+/// Replaces a comparison against an empty value, which etcd rejects as a missing `prevValue`, with
+/// a comparison against the modified index the node has while its value is empty.
 ///
-/// Fails if a timeout is specified and the duration lapses without a response from the etcd
-/// cluster.
-pub async fn watch<C>(
+/// Any other conditions are returned as they are. If the node's value isn't empty, or it isn't at
+/// the expected modified index, the comparison fails here with etcd's own error.
+async fn resolve_empty_prev_value<'a, C>(
     client: &Client<C>,
     key: &str,
-    options: WatchOptions,
-) -> Result<Response<KeyValueInfo>, WatchError>
+    conditions: ComparisonConditions<'a>,
+) -> Result<ComparisonConditions<'a>, Vec<Error>>
 where
     C: Clone + Connect + Sync + Send,
 {
-    let work = raw_get(
-        client,
-        key,
-        InternalGetOptions {
-            recursive: options.recursive,
-            wait_index: options.index,
-            wait: true,
-            ..Default::default()
-        },
-    )
-    .map_err(|errors| WatchError::Other(errors));
+    if conditions.value != Some("") {
+        return Ok(conditions);
+    }
 
-    if let Some(duration) = options.timeout {
-        timeout(duration, work).err_into().map(flatten_result).await
+    let options = GetOptions {
+        strong_consistency: true,
+        ..Default::default()
+    };
+    let response = get(client, key, options).await?;
+    let node = response.data.node;
+    let stored = node.value.unwrap_or_default();
+
+    let cause = if !stored.is_empty() {
+        Some(format!("[ != {}]", stored))
     } else {
-        work.await
+        match (conditions.modified_index, node.modified_index) {
+            (Some(expected), Some(actual)) if expected != actual => {
+                Some(format!("[{} != {}]", expected, actual))
+            }
+            _ => None,
+        }
+    };
+
+    if let Some(cause) = cause {
+        return Err(vec![Error::Api(ApiError {
+            cause: Some(cause),
+            error_code: EtcdErrorCode::CompareFailed.as_u64(),
+            error_index: None,
+            index: response.cluster_info.etcd_index,
+            message: "Compare failed".to_owned(),
+        })]);
     }
-}
 
-/// Constructs the full URL for an API call.
-fn build_url(endpoint: &Uri, path: &str) -> String {
-    format!("{}v2/keys{}", endpoint, path)
+    Ok(ComparisonConditions {
+        modified_index: node.modified_index,
+        value: None,
+    })
 }
 
 /// Handles all delete operations.
@@ -599,6 +3687,8 @@ async fn raw_delete<C>(
 where
     C: Clone + Connect + Sync + Send,
 {
+    client.ensure_leader().await?;
+
     let mut query_pairs = HashMap::new();
 
     if options.recursive.is_some() {
@@ -609,9 +3699,12 @@ where
         query_pairs.insert("dir", format!("{}", options.dir.unwrap()));
     }
 
-    if options.conditions.is_some() {
-        let conditions = options.conditions.unwrap();
+    let conditions = match options.conditions {
+        Some(conditions) => Some(resolve_empty_prev_value(client, key, conditions).await?),
+        None => None,
+    };
 
+    if let Some(conditions) = conditions {
         if conditions.is_empty() {
             return Err(vec![Error::InvalidConditions]);
         }
@@ -630,8 +3723,20 @@ where
 
     let http_client = client.http_client().clone();
     let key = key.to_string();
+    #[cfg(feature = "tracing")]
+    let redact_values = client.redact_values();
+
+    let result = client.first_ok(false, move |endpoint| {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            uri = %build_url(endpoint, &key),
+            query = %loggable_form(
+                query_pairs.iter().map(|(name, value)| (*name, value.as_str())),
+                redact_values,
+            ),
+            "sending delete request to etcd",
+        );
 
-    let result = first_ok(client.endpoints().to_vec(), move |endpoint| {
         let url = ready(
             Url::parse_with_params(&build_url(endpoint, &key), query_pairs.clone())
                 .map_err(Error::from),
@@ -641,49 +3746,96 @@ where
 
         let http_client = http_client.clone();
 
-        let response = uri.and_then(move |uri| http_client.delete(uri).map_err(Error::from));
+        let response = uri.and_then(move |uri| http_client.delete(uri));
 
         response.and_then(move |response| {
-            let status = response.status();
-            let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::aggregate(response.into_body())
-                .err_into()
-                .map_ok(BufExt::reader);
-
-            body.and_then(move |body| {
-                ready(if status == StatusCode::OK {
-                    match serde_json::from_reader::<_, KeyValueInfo>(body) {
-                        Ok(data) => Ok(Response { data, cluster_info }),
-                        Err(error) => Err(Error::Serialization(error)),
-                    }
-                } else {
-                    match serde_json::from_reader::<_, ApiError>(body) {
-                        Ok(error) => Err(Error::Api(error)),
-                        Err(error) => Err(Error::Serialization(error)),
-                    }
-                })
+            ready(if response.status() == StatusCode::OK {
+                response
+                    .json::<KeyValueInfo>()
+                    .map(|data| response.into_response(data))
+            } else {
+                Err(response.api_error_or_status())
             })
         })
     });
 
-    result.await
+    let response = result.await?;
+
+    client.record_write(&response.cluster_info);
+
+    Ok(response)
+}
+
+/// Handles get operations with `GetOptions`, retrying a failed strongly consistent read without
+/// quorum if `GetOptions::fallback_to_stale` is set.
+async fn get_with_fallback<C, T>(
+    client: &Client<C>,
+    key: &str,
+    options: GetOptions,
+) -> Result<Response<T>, Vec<Error>>
+where
+    C: Clone + Connect + Sync + Send,
+    T: DeserializeOwned + Send,
+{
+    let result = raw_get(
+        client,
+        key,
+        InternalGetOptions {
+            recursive: options.recursive,
+            sort: Some(options.sort),
+            strong_consistency: options.strong_consistency,
+            ..Default::default()
+        },
+    )
+    .await;
+
+    match result {
+        Err(ref errors)
+            if options.strong_consistency
+                && options.fallback_to_stale
+                && (has_api_error(errors, EtcdErrorCode::RaftInternal)
+                    || has_api_error(errors, EtcdErrorCode::LeaderElection)) =>
+        {
+            let mut response = raw_get(
+                client,
+                key,
+                InternalGetOptions {
+                    recursive: options.recursive,
+                    sort: Some(options.sort),
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+            response.is_stale = true;
+
+            Ok(response)
+        }
+        result => result,
+    }
 }
 
-/// Handles all get operations.
-async fn raw_get<C>(
+/// Handles all get operations, deserializing the response body as `T`.
+async fn raw_get<C, T>(
     client: &Client<C>,
     key: &str,
     options: InternalGetOptions,
-) -> Result<Response<KeyValueInfo>, Vec<Error>>
+) -> Result<Response<T>, Vec<Error>>
 where
     C: Clone + Connect + Sync + Send,
+    T: DeserializeOwned + Send,
 {
     let mut query_pairs = HashMap::new();
 
     query_pairs.insert("recursive", format!("{}", options.recursive));
 
-    if options.sort.is_some() {
-        query_pairs.insert("sorted", format!("{}", options.sort.unwrap()));
+    // etcd doesn't sort unless asked to, so there's no need to send `sorted=false`.
+    if options.sort == Some(true) {
+        query_pairs.insert("sorted", "true".to_owned());
+    }
+
+    if options.strong_consistency {
+        query_pairs.insert("quorum", "true".to_owned());
     }
 
     if options.wait {
@@ -696,129 +3848,645 @@ where
 
     let http_client = client.http_client().clone();
     let key = key.to_string();
+    let wait = options.wait;
+    let required_index = if options.wait {
+        None
+    } else {
+        client.required_read_index()
+    };
 
-    first_ok(client.endpoints().to_vec(), move |endpoint| {
-        let url = ready(
-            Url::parse_with_params(&build_url(endpoint, &key), query_pairs.clone())
-                .map_err(Error::from),
-        );
-
-        let uri = url.and_then(|url| ready(Uri::from_str(url.as_str()).map_err(Error::from)));
+    client
+        .first_ok(true, move |endpoint| {
+            let url = ready(
+                Url::parse_with_params(&build_url(endpoint, &key), query_pairs.clone())
+                    .map_err(Error::from),
+            );
 
-        let http_client = http_client.clone();
+            let uri = url.and_then(|url| ready(Uri::from_str(url.as_str()).map_err(Error::from)));
 
-        let response = uri.and_then(move |uri| http_client.get(uri).map_err(Error::from));
+            let http_client = http_client.clone();
 
-        response.and_then(|response| {
-            let status = response.status();
-            let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::aggregate(response.into_body())
-                .err_into()
-                .map_ok(BufExt::reader);
+            let response = uri.and_then(move |uri| {
+                if wait {
+                    http_client.watch(uri)
+                } else {
+                    http_client.get(uri)
+                }
+            });
 
-            body.and_then(move |body| {
-                ready(if status == StatusCode::OK {
-                    match serde_json::from_reader::<_, KeyValueInfo>(body) {
-                        Ok(data) => Ok(Response { data, cluster_info }),
-                        Err(error) => Err(Error::Serialization(error)),
-                    }
+            response.and_then(move |response| {
+                ready(if response.status() == StatusCode::OK {
+                    response
+                        .json::<T>()
+                        .map(|data| response.into_response(data))
+                        .and_then(|response| check_read_index(response, required_index))
                 } else {
-                    match serde_json::from_reader::<_, ApiError>(body) {
-                        Ok(error) => Err(Error::Api(error)),
-                        Err(error) => Err(Error::Serialization(error)),
-                    }
+                    Err(response.api_error_or_status())
                 })
             })
         })
-    })
-    .await
+        .await
 }
 
 /// Handles all set operations.
 async fn raw_set<C>(
     client: &Client<C>,
     key: &str,
-    options: SetOptions<'_>,
+    mut options: InternalSetOptions<'_>,
 ) -> Result<Response<KeyValueInfo>, Vec<Error>>
 where
     C: Clone + Connect + Sync + Send,
 {
-    let mut http_options = vec![];
+    client.check_ttl(options.ttl).map_err(|error| vec![error])?;
+    client.ensure_leader().await?;
+
+    let conditions = match options.conditions.take() {
+        Some(conditions) => Some(resolve_empty_prev_value(client, key, conditions).await?),
+        None => None,
+    };
+
+    if let Some(ref conditions) = conditions {
+        if conditions.is_empty() {
+            return Err(vec![Error::InvalidConditions]);
+        }
+    }
+
+    let http_options = set_form(&options, conditions.as_ref());
+    let http_client = client.http_client().clone();
+    let key = key.to_string();
+    let create_in_order = options.create_in_order;
+    // Setting a value unconditionally, or only if the node already exists, has the same outcome
+    // however many times it is done. Creating a node or comparing against it does not, since a
+    // repeat would fail against the node the first attempt left behind.
+    let idempotent = !create_in_order && conditions.is_none() && options.prev_exist != Some(false);
+    #[cfg(feature = "tracing")]
+    let redact_values = client.redact_values();
+
+    let response = client
+        .first_ok(idempotent, move |endpoint| {
+            let body = encode_form(&http_options);
+            let url = build_url(endpoint, &key);
+
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                uri = %url,
+                body = %loggable_form(
+                    http_options.iter().map(|(name, value)| (name.as_str(), value.as_str())),
+                    redact_values,
+                ),
+                "sending set request to etcd",
+            );
+
+            let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
+
+            let http_client = http_client.clone();
+
+            let response = uri.and_then(move |uri| {
+                if create_in_order {
+                    http_client.post(uri, body)
+                } else {
+                    http_client.put(uri, body)
+                }
+            });
+
+            response.and_then(|response| {
+                ready(match response.status() {
+                    StatusCode::CREATED | StatusCode::OK => response
+                        .json::<KeyValueInfo>()
+                        .map(|data| response.into_response(data)),
+                    _ => Err(response.api_error_or_status()),
+                })
+            })
+        })
+        .await?;
+
+    client.record_write(&response.cluster_info);
+
+    Ok(response)
+}
+
+/// Builds the form fields of a set request from its options and resolved conditions.
+fn set_form(
+    options: &InternalSetOptions<'_>,
+    conditions: Option<&ComparisonConditions<'_>>,
+) -> Vec<(String, String)> {
+    let mut fields = vec![];
 
     if let Some(ref value) = options.value {
-        http_options.push(("value".to_owned(), value.to_string()));
+        fields.push(("value".to_owned(), value.to_string()));
     }
 
     if let Some(ref ttl) = options.ttl {
-        http_options.push(("ttl".to_owned(), ttl.to_string()));
+        fields.push(("ttl".to_owned(), ttl.to_string()));
     }
 
     if let Some(ref dir) = options.dir {
-        http_options.push(("dir".to_owned(), dir.to_string()));
+        fields.push(("dir".to_owned(), dir.to_string()));
     }
 
     if let Some(ref prev_exist) = options.prev_exist {
-        http_options.push(("prevExist".to_owned(), prev_exist.to_string()));
+        fields.push(("prevExist".to_owned(), prev_exist.to_string()));
     }
 
-    if let Some(ref conditions) = options.conditions {
-        if conditions.is_empty() {
-            return Err(vec![Error::InvalidConditions]);
-        }
+    if options.refresh {
+        fields.push(("refresh".to_owned(), "true".to_owned()));
+    }
 
+    if let Some(conditions) = conditions {
         if let Some(ref modified_index) = conditions.modified_index {
-            http_options.push(("prevIndex".to_owned(), modified_index.to_string()));
+            fields.push(("prevIndex".to_owned(), modified_index.to_string()));
         }
 
         if let Some(ref value) = conditions.value {
-            http_options.push(("prevValue".to_owned(), value.to_string()));
+            fields.push(("prevValue".to_owned(), value.to_string()));
         }
     }
 
-    let http_client = client.http_client().clone();
-    let key = key.to_string();
-    let create_in_order = options.create_in_order;
+    fields
+}
 
-    first_ok(client.endpoints().to_vec(), move |endpoint| {
-        let mut serializer = Serializer::new(String::new());
-        serializer.extend_pairs(http_options.clone());
-        let body = serializer.finish();
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::time::{Duration, UNIX_EPOCH};
 
-        let url = build_url(endpoint, &key);
-        let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
+    use hyper::Uri;
+    use url::Url;
 
-        let http_client = http_client.clone();
+    use super::{build_url, parse_rfc3339, remaining_ttl, KeyValueInfo, Node};
 
-        let response = uri.and_then(move |uri| {
-            if create_in_order {
-                http_client.post(uri, body).map_err(Error::from)
-            } else {
-                http_client.put(uri, body).map_err(Error::from)
+    #[test]
+    fn test_parse_rfc3339() {
+        assert_eq!(parse_rfc3339("1970-01-01T00:00:00Z"), Some(UNIX_EPOCH));
+        assert_eq!(
+            parse_rfc3339("2013-12-04T12:01:21.874888581-08:00"),
+            Some(UNIX_EPOCH + Duration::new(1_386_187_281, 874_888_581))
+        );
+        assert_eq!(
+            parse_rfc3339("2020-02-29T23:59:59.5+01:00"),
+            Some(UNIX_EPOCH + Duration::new(1_583_017_199, 500_000_000))
+        );
+        assert_eq!(parse_rfc3339("2020-02-29 23:59:59Z"), None);
+        assert_eq!(parse_rfc3339("not a timestamp"), None);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_loggable_form() {
+        let pairs = vec![
+            ("value", "secret"),
+            ("prevIndex", "7"),
+            ("prevValue", "old"),
+        ];
+
+        assert_eq!(
+            super::loggable_form(pairs.clone().into_iter(), true),
+            "value=%5Bredacted%5D&prevIndex=7&prevValue=%5Bredacted%5D"
+        );
+        assert_eq!(
+            super::loggable_form(pairs.into_iter(), false),
+            "value=secret&prevIndex=7&prevValue=old"
+        );
+    }
+
+    #[test]
+    fn test_action_unknown() {
+        let action: super::Action = serde_json::from_str("\"cas\"").unwrap();
+
+        assert_eq!(action, super::Action::Unknown("cas".to_owned()));
+        assert_eq!(serde_json::to_string(&action).unwrap(), "\"cas\"");
+
+        let action: super::Action = serde_json::from_str("\"compareAndSwap\"").unwrap();
+
+        assert_eq!(action, super::Action::CompareAndSwap);
+    }
+
+    #[test]
+    fn test_node_invalid_utf8() {
+        let mut body = b"{\"key\":\"/foo\",\"value\":\"a".to_vec();
+        body.push(0xff);
+        body.extend_from_slice(b"b\"}");
+
+        let node: super::Node = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(node.value.as_deref(), Some("a\u{fffd}b"));
+        assert_eq!(node.value_bytes(), Some(&b"a\xffb"[..]));
+
+        let node: super::Node = serde_json::from_str("{\"value\":\"caf\\u00e9\"}").unwrap();
+
+        assert_eq!(node.value.as_deref(), Some("caf\u{e9}"));
+        assert_eq!(node.raw_value, None);
+        assert_eq!(node.value_bytes(), Some("caf\u{e9}".as_bytes()));
+    }
+
+    #[test]
+    fn test_diff() {
+        let old: super::Node = serde_json::from_str(
+            r#"{"key":"/config","dir":true,"nodes":[
+                {"key":"/config/a","value":"1"},
+                {"key":"/config/b","value":"2"},
+                {"key":"/config/c","dir":true,"nodes":[{"key":"/config/c/d","value":"3"}]},
+                {"key":"/config/e","value":"4"}
+            ]}"#,
+        )
+        .unwrap();
+        let new: super::Node = serde_json::from_str(
+            r#"{"key":"/config","dir":true,"nodes":[
+                {"key":"/config/a","value":"1"},
+                {"key":"/config/b","value":"20"},
+                {"key":"/config/c","value":"5"},
+                {"key":"/config/e","dir":true},
+                {"key":"/config/f","value":"6"}
+            ]}"#,
+        )
+        .unwrap();
+
+        let diff = super::diff(&old, &new);
+
+        assert_eq!(diff.added, vec!["/config/f".to_owned()]);
+        assert_eq!(diff.removed, vec!["/config/c/d".to_owned()]);
+        assert_eq!(
+            diff.changed,
+            vec![
+                ("/config/b".to_owned(), "2".to_owned(), "20".to_owned()),
+                ("/config/c".to_owned(), "".to_owned(), "5".to_owned()),
+                ("/config/e".to_owned(), "4".to_owned(), "".to_owned()),
+            ]
+        );
+        assert!(super::diff(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn test_flatten() {
+        let node: super::Node = serde_json::from_str(
+            r#"{"key":"/app","dir":true,"nodes":[
+                {"key":"/app/name","value":"web"},
+                {"key":"/app/db","dir":true,"nodes":[
+                    {"key":"/app/db/host","value":"db.local","ttl":30},
+                    {"key":"/app/db/replicas","dir":true,"nodes":[
+                        {"key":"/app/db/replicas/1","value":"db1.local"}
+                    ]},
+                    {"key":"/app/db/empty","dir":true}
+                ]}
+            ]}"#,
+        )
+        .unwrap();
+
+        let flattened = node.flatten();
+
+        assert_eq!(flattened.len(), 3);
+        assert_eq!(flattened["/app/name"], "web");
+        assert_eq!(flattened["/app/db/host"], "db.local");
+        assert_eq!(flattened["/app/db/replicas/1"], "db1.local");
+
+        let flattened = node.flatten_with_ttl();
+
+        assert_eq!(flattened.len(), 3);
+        assert_eq!(flattened["/app/name"], ("web".to_owned(), None));
+        assert_eq!(flattened["/app/db/host"], ("db.local".to_owned(), Some(30)));
+        assert_eq!(
+            flattened["/app/db/replicas/1"],
+            ("db1.local".to_owned(), None)
+        );
+    }
+
+    #[test]
+    fn test_value_changes() {
+        let created: super::KeyValueInfo = serde_json::from_str(
+            r#"{"action":"create","node":{"key":"/foo","value":"one","modifiedIndex":1}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(created.previous_value(), None);
+        assert_eq!(created.current_value(), Some("one"));
+        assert!(created.value_changed());
+
+        let updated: super::KeyValueInfo = serde_json::from_str(
+            r#"{"action":"set","node":{"key":"/foo","value":"two","modifiedIndex":2},
+                "prevNode":{"key":"/foo","value":"one","modifiedIndex":1}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(updated.previous_value(), Some("one"));
+        assert_eq!(updated.current_value(), Some("two"));
+        assert!(updated.value_changed());
+
+        let unchanged: super::KeyValueInfo = serde_json::from_str(
+            r#"{"action":"set","node":{"key":"/foo","value":"two","modifiedIndex":3},
+                "prevNode":{"key":"/foo","value":"two","modifiedIndex":2}}"#,
+        )
+        .unwrap();
+
+        assert!(!unchanged.value_changed());
+
+        let deleted: super::KeyValueInfo = serde_json::from_str(
+            r#"{"action":"delete","node":{"key":"/foo","modifiedIndex":4},
+                "prevNode":{"key":"/foo","value":"two","modifiedIndex":3}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(deleted.previous_value(), Some("two"));
+        assert_eq!(deleted.current_value(), None);
+        assert!(deleted.value_changed());
+    }
+
+    #[test]
+    fn test_deletion_reason() {
+        use super::{DeletionReason, KeyValueInfo};
+
+        let info = |action: &str| -> KeyValueInfo {
+            serde_json::from_str(&format!(
+                r#"{{"action":"{}","node":{{"key":"/foo"}}}}"#,
+                action
+            ))
+            .unwrap()
+        };
+
+        assert_eq!(
+            info("compareAndDelete").deletion_reason(),
+            Some(DeletionReason::CompareAndDeleted)
+        );
+        assert_eq!(
+            info("delete").deletion_reason(),
+            Some(DeletionReason::Deleted)
+        );
+        assert_eq!(
+            info("expire").deletion_reason(),
+            Some(DeletionReason::Expired)
+        );
+        assert_eq!(info("set").deletion_reason(), None);
+        assert_eq!(info("compareAndSwap").deletion_reason(), None);
+    }
+
+    #[test]
+    fn test_options_builders() {
+        use super::{GetOptions, SetOptions, WatchOptions};
+
+        let options = GetOptions::builder()
+            .recursive(true)
+            .sorted(true)
+            .quorum(true)
+            .build();
+
+        assert_eq!(
+            options,
+            GetOptions {
+                recursive: true,
+                sort: true,
+                strong_consistency: true,
+                fallback_to_stale: false,
             }
-        });
+        );
+        assert_eq!(GetOptions::builder().build(), GetOptions::default());
 
-        response.and_then(|response| {
-            let status = response.status();
-            let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::aggregate(response.into_body())
-                .err_into()
-                .map_ok(BufExt::reader);
-
-            body.and_then(move |body| {
-                ready(match status {
-                    StatusCode::CREATED | StatusCode::OK => {
-                        match serde_json::from_reader::<_, KeyValueInfo>(body) {
-                            Ok(data) => Ok(Response { data, cluster_info }),
-                            Err(error) => Err(Error::Serialization(error)),
-                        }
-                    }
-                    _ => match serde_json::from_reader::<_, ApiError>(body) {
-                        Ok(error) => Err(Error::Api(error)),
-                        Err(error) => Err(Error::Serialization(error)),
-                    },
-                })
-            })
-        })
-    })
-    .await
+        let options = WatchOptions::builder()
+            .index(7)
+            .recursive(true)
+            .timeout(Duration::from_secs(5))
+            .build();
+
+        assert_eq!(
+            options,
+            WatchOptions {
+                index: Some(7),
+                recursive: true,
+                timeout: Some(Duration::from_secs(5)),
+            }
+        );
+
+        let options = SetOptions::builder()
+            .prev_exist(true)
+            .prev_index(3)
+            .prev_value("old")
+            .ttl(60)
+            .build();
+
+        assert_eq!(
+            options,
+            SetOptions {
+                prev_exist: Some(true),
+                prev_index: Some(3),
+                prev_value: Some("old".to_owned()),
+                ttl: Some(60),
+            }
+        );
+        assert_eq!(SetOptions::builder().build(), SetOptions::default());
+    }
+
+    #[test]
+    fn test_ttl_duration() {
+        let node: super::Node = serde_json::from_str(r#"{"key":"/foo","ttl":30}"#).unwrap();
+        assert_eq!(node.ttl_duration(), Some(Duration::from_secs(30)));
+
+        let node: super::Node = serde_json::from_str(r#"{"key":"/foo","ttl":-1}"#).unwrap();
+        assert_eq!(node.ttl_duration(), Some(Duration::from_secs(0)));
+
+        let node: super::Node = serde_json::from_str(r#"{"key":"/foo"}"#).unwrap();
+        assert_eq!(node.ttl_duration(), None);
+    }
+
+    #[test]
+    fn test_remaining_ttl() {
+        let node = |json: &str| serde_json::from_str::<Node>(json).unwrap();
+        let now = UNIX_EPOCH + Duration::from_secs(100);
+
+        assert_eq!(
+            remaining_ttl(
+                &node(r#"{"expiration":"1970-01-01T00:01:49.5Z","ttl":10}"#),
+                now
+            ),
+            Some(Duration::from_millis(9500))
+        );
+        // A clock that disagrees with the reported TTL isn't trusted.
+        assert_eq!(
+            remaining_ttl(
+                &node(r#"{"expiration":"1970-01-01T00:03:20Z","ttl":10}"#),
+                now
+            ),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            remaining_ttl(
+                &node(r#"{"expiration":"1970-01-01T00:01:30Z","ttl":10}"#),
+                now
+            ),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            remaining_ttl(&node(r#"{"ttl":10}"#), now),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(remaining_ttl(&node(r#"{"key":"/foo"}"#), now), None);
+    }
+
+    #[test]
+    fn test_changed_key_relative_to() {
+        let info: KeyValueInfo =
+            serde_json::from_str(r#"{"action":"set","node":{"key":"/config/db/host"}}"#).unwrap();
+
+        assert_eq!(info.changed_key_relative_to("/config"), Some("db/host"));
+        assert_eq!(info.changed_key_relative_to("/config/"), Some("db/host"));
+        assert_eq!(info.changed_key_relative_to("config"), Some("db/host"));
+        assert_eq!(info.changed_key_relative_to("/config/db"), Some("host"));
+        assert_eq!(info.changed_key_relative_to("/config/db/host"), Some(""));
+        assert_eq!(info.changed_key_relative_to("/"), Some("config/db/host"));
+        assert_eq!(info.changed_key_relative_to("/conf"), None);
+        assert_eq!(info.changed_key_relative_to("/other"), None);
+    }
+
+    #[test]
+    fn test_build_url_ipv6() {
+        let request_uri = |endpoint: &str| {
+            let endpoint: Uri = endpoint.parse().unwrap();
+            let url = Url::parse_with_params(&build_url(&endpoint, "/foo"), &[("sorted", "true")]);
+
+            Uri::from_str(url.unwrap().as_str()).unwrap()
+        };
+
+        let uri = request_uri("http://[::1]:2379/");
+        assert_eq!(uri.host(), Some("[::1]"));
+        assert_eq!(uri.port_u16(), Some(2379));
+        assert_eq!(uri.path(), "/v2/keys/foo");
+        assert_eq!(uri.query(), Some("sorted=true"));
+
+        let uri = request_uri("https://[2001:db8::1]/etcd/");
+        assert_eq!(uri.authority().unwrap().as_str(), "[2001:db8::1]");
+        assert_eq!(uri.path(), "/etcd/v2/keys/foo");
+    }
+
+    #[test]
+    fn test_indexes() {
+        let info: super::KeyValueInfo = serde_json::from_str(
+            r#"{"action":"set","node":{"key":"/foo","value":"bar","createdIndex":3,
+                "modifiedIndex":7}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(info.created_index(), Some(3));
+        assert_eq!(info.modified_index(), Some(7));
+
+        let info: super::KeyValueInfo =
+            serde_json::from_str(r#"{"action":"get","node":{"key":"/"}}"#).unwrap();
+
+        assert_eq!(info.created_index(), None);
+        assert_eq!(info.modified_index(), None);
+    }
+
+    #[test]
+    fn test_hidden_keys() {
+        use super::{hidden_key, Node};
+
+        assert_eq!(hidden_key("/app/lock"), "/app/_lock");
+        assert_eq!(hidden_key("/app/_lock"), "/app/_lock");
+        assert_eq!(hidden_key("/app/lock/"), "/app/_lock");
+        assert_eq!(hidden_key("/_app/lock"), "/_app/_lock");
+        assert_eq!(hidden_key("lock"), "_lock");
+
+        let node = |key: &str| -> Node {
+            serde_json::from_str(&format!(r#"{{"key":"{}"}}"#, key)).unwrap()
+        };
+
+        assert!(node("/app/_lock").is_hidden());
+        assert!(node("/_app/").is_hidden());
+        assert!(!node("/app/lock").is_hidden());
+        assert!(!node("/_app/lock").is_hidden());
+        assert!(!node("/").is_hidden());
+    }
+
+    #[test]
+    fn test_preview_set() {
+        use hyper::Method;
+
+        use super::preview_set;
+
+        let (method, url, body) =
+            preview_set("http://etcd:2379", "/foo", "bar baz&", Some(60)).unwrap();
+
+        assert_eq!(method, Method::PUT);
+        assert_eq!(url, "http://etcd:2379/v2/keys/foo");
+        assert_eq!(body, "value=bar+baz%26&ttl=60");
+
+        let (_, url, body) = preview_set("https://etcd/prefix", "/foo", "", None).unwrap();
+
+        assert_eq!(url, "https://etcd/prefix/v2/keys/foo");
+        assert_eq!(body, "value=");
+
+        assert!(preview_set("not a url", "/foo", "bar", None).is_err());
+    }
+
+    #[test]
+    fn test_set_form_conditions() {
+        use super::set_form;
+        use crate::options::{ComparisonConditions, SetOptions};
+
+        let options = SetOptions {
+            value: Some("new"),
+            ..Default::default()
+        };
+        let conditions = ComparisonConditions {
+            modified_index: Some(7),
+            value: Some("old"),
+        };
+
+        assert!(!conditions.is_empty());
+        assert_eq!(
+            set_form(&options, Some(&conditions)),
+            vec![
+                ("value".to_owned(), "new".to_owned()),
+                ("prevIndex".to_owned(), "7".to_owned()),
+                ("prevValue".to_owned(), "old".to_owned()),
+            ]
+        );
+
+        for (modified_index, value) in vec![(Some(7), None), (None, Some("old"))] {
+            let conditions = ComparisonConditions {
+                modified_index,
+                value,
+            };
+
+            assert!(!conditions.is_empty());
+            assert_eq!(set_form(&options, Some(&conditions)).len(), 2);
+        }
+
+        let conditions = ComparisonConditions {
+            modified_index: None,
+            value: None,
+        };
+
+        assert!(conditions.is_empty());
+    }
+
+    #[test]
+    fn test_deleted_keys() {
+        let info: super::KeyValueInfo = serde_json::from_str(
+            r#"{"action":"delete","node":{"key":"/app","dir":true,"modifiedIndex":9},
+                "prevNode":{"key":"/app","dir":true,"nodes":[
+                    {"key":"/app/name","value":"web"},
+                    {"key":"/app/db","dir":true,"nodes":[{"key":"/app/db/host","value":"db"}]},
+                    {"key":"/app/empty","dir":true}
+                ]}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            info.deleted_keys(),
+            vec!["/app/name", "/app/db/host", "/app/empty"]
+        );
+
+        let info: super::KeyValueInfo = serde_json::from_str(
+            r#"{"action":"expire","node":{"key":"/lock","modifiedIndex":3},
+                "prevNode":{"key":"/lock","value":"owner"}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(info.deleted_keys(), vec!["/lock"]);
+
+        let info: super::KeyValueInfo = serde_json::from_str(
+            r#"{"action":"set","node":{"key":"/lock","value":"owner"},
+                "prevNode":{"key":"/lock","value":"other"}}"#,
+        )
+        .unwrap();
+
+        assert!(info.deleted_keys().is_empty());
+    }
 }
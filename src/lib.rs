@@ -21,7 +21,8 @@
 //! passing a `Client` reference to the functions in the `auth`, `kv`, `members`, and `stats`
 //! modules. These modules contain functions for API calls to the authentication and authorization
 //! API, the primary key-value store API, the cluster membership API, and statistics API,
-//! respectively.
+//! respectively. The `election` and `queue` modules build leader election and work queues on top of
+//! the key-value API.
 //!
 //! # Examples
 //!
@@ -66,22 +67,66 @@
 //!
 //! # Cargo features
 //!
-//! Crate `etcd` has one Cargo feature, `tls`, which adds HTTPS support via the `Client::https`
-//! constructor. This feature is enabled by default.
+//! Crate `etcd` has the following Cargo features:
+//!
+//! * `compression`: Asks etcd for gzip or deflate compressed responses, which are decompressed
+//! transparently. This reduces the amount of data transferred for large responses, such as
+//! recursive gets of big directories.
+//! * `metrics`: Records the metrics described below via the `metrics` crate, to be exported by
+//! whichever recorder the application installs, such as a Prometheus exporter.
+//! * `test-util`: Adds the `mock` module, an in-memory stand-in for etcd for testing code built on
+//! this crate without a running etcd.
+//! * `tls`: Adds HTTPS support via the `Client::https` constructor. This feature is enabled by
+//! default.
+//! * `tracing`: Emits spans and events via the `tracing` crate describing every request made to
+//! etcd, with the endpoint, status and elapsed time, and `debug` events describing the requests
+//! made by write operations. See `Client::set_redact_values`.
+//!
+//! # Metrics
+//!
+//! With the `metrics` feature enabled, the client records:
+//!
+//! * `etcd_client_requests_total`: A counter of HTTP requests made to cluster members, labeled by
+//! `endpoint` (the scheme and authority of the member), `method`, and `status` (the HTTP status
+//! code, or `error` if the request failed without a usable response).
+//! * `etcd_client_request_duration_seconds`: A histogram of the time taken by each HTTP request,
+//! including reading the response, labeled by `endpoint` and `method`.
+//! * `etcd_client_request_errors_total`: A counter of HTTP requests that failed without a usable
+//! response, either because none arrived or because the one that did couldn't be read or
+//! followed, labeled by `endpoint`, `kind` (`connection`, `decompression`, `http`, `redirect`,
+//! `timeout`, or `other`), and `phase`, the stage at which a connection error occurred
+//! (`connect`, `tls_handshake`, `headers`, or `body`, and `none` for other kinds).
+//! * `etcd_client_operations_total`: A counter of API calls that try cluster members until one
+//! succeeds, labeled by `outcome`: `success` or `failure`.
+//! * `etcd_client_operation_retries_total`: A counter of times such an API call was retried after
+//! every member failed. See `Client::set_retry_policy`.
+//! * `etcd_client_endpoint_fallbacks_total`: A counter of times a member failed while others
+//! were still to be tried or, with `Concurrency::Parallel`, still in flight.
 #![deny(missing_debug_implementations, missing_docs, warnings)]
 
-pub use crate::client::{BasicAuth, Client, ClusterInfo, Health, Response};
-pub use crate::error::{ApiError, Error};
+pub use crate::client::{
+    AuthMethod, BasicAuth, Client, ClientBuilder, ClientConfig, ClusterHealth, ClusterInfo,
+    Concurrency, EndpointStrategy, Health, MemberHealth, MemberVersion, NewConnector, Response,
+    RetryPolicy,
+};
+pub use crate::connector::PinnedConnector;
+pub use crate::error::{ApiError, ConnectionPhase, Error, EtcdErrorCode};
 pub use crate::version::VersionInfo;
 
 pub mod auth;
+pub mod election;
 pub mod kv;
 pub mod members;
+#[cfg(feature = "test-util")]
+pub mod mock;
+pub mod queue;
 pub mod stats;
 
 mod client;
+mod connector;
 mod error;
 mod first_ok;
 mod http;
 mod options;
 mod version;
+mod watch_registry;
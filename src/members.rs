@@ -4,7 +4,6 @@
 
 use std::str::FromStr;
 
-use bytes::buf::BufExt;
 use futures::future::ready;
 use futures::TryFutureExt;
 use hyper::client::connect::Connect;
@@ -12,9 +11,8 @@ use hyper::{StatusCode, Uri};
 use serde_derive::{Deserialize, Serialize};
 use serde_json;
 
-use crate::client::{Client, ClusterInfo, Response};
-use crate::error::{ApiError, Error};
-use crate::first_ok::first_ok;
+use crate::client::{Client, Response};
+use crate::error::Error;
 
 /// An etcd server that is a member of a cluster.
 #[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
@@ -52,10 +50,7 @@ struct ListResponse {
 ///
 /// * client: A `Client` to use to make the API call.
 /// * peer_urls: URLs exposing this cluster member's peer API.
-pub async fn add<C>(
-    client: &Client<C>,
-    peer_urls: Vec<String>,
-) -> Result<Response<()>, Vec<Error>>
+pub async fn add<C>(client: &Client<C>, peer_urls: Vec<String>) -> Result<Response<()>, Vec<Error>>
 where
     C: Clone + Connect + Sync + Send,
 {
@@ -68,37 +63,25 @@ where
 
     let http_client = client.http_client().clone();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
-        let url = build_url(member, "");
-        let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
+    client
+        .first_ok(false, move |member| {
+            let url = build_url(member, "");
+            let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
 
-        let body = body.clone();
-        let http_client = http_client.clone();
+            let body = body.clone();
+            let http_client = http_client.clone();
 
-        let response = uri.and_then(move |uri| http_client.post(uri, body).map_err(Error::from));
+            let response = uri.and_then(move |uri| http_client.post(uri, body));
 
-        response.and_then(|response| {
-            let status = response.status();
-            let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::aggregate(response.into_body())
-                .map_ok(BufExt::reader)
-                .err_into();
-
-            body.and_then(move |body| async move{
-                if status == StatusCode::CREATED {
-                    Ok(Response {
-                        data: (),
-                        cluster_info,
-                    })
+            response.and_then(|response| {
+                ready(if response.status() == StatusCode::CREATED {
+                    Ok(response.into_response(()))
                 } else {
-                    match serde_json::from_reader::<_, ApiError>(body) {
-                        Ok(error) => Err(Error::Api(error)),
-                        Err(error) => Err(Error::Serialization(error)),
-                    }
-                }
+                    Err(response.api_error_or_status())
+                })
             })
         })
-    }).await
+        .await
 }
 
 /// Deletes a member from the cluster.
@@ -107,45 +90,30 @@ where
 ///
 /// * client: A `Client` to use to make the API call.
 /// * id: The unique identifier of the member to delete.
-pub async fn delete<C>(
-    client: &Client<C>,
-    id: String,
-) -> Result<Response<()>, Vec<Error>>
+pub async fn delete<C>(client: &Client<C>, id: String) -> Result<Response<()>, Vec<Error>>
 where
     C: Clone + Connect + Sync + Send,
 {
     let http_client = client.http_client().clone();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
-        let url = build_url(member, &format!("/{}", id));
-        let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
-
-        let http_client = http_client.clone();
+    client
+        .first_ok(false, move |member| {
+            let url = build_url(member, &format!("/{}", id));
+            let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
 
-        let response = uri.and_then(move |uri| http_client.delete(uri).map_err(Error::from));
+            let http_client = http_client.clone();
 
-        response.and_then(|response| {
-            let status = response.status();
-            let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::aggregate(response.into_body())
-                .map_ok(BufExt::reader)
-                .err_into();
+            let response = uri.and_then(move |uri| http_client.delete(uri));
 
-            body.and_then(move |body| async move {
-                if status == StatusCode::NO_CONTENT {
-                    Ok(Response {
-                        data: (),
-                        cluster_info,
-                    })
+            response.and_then(|response| {
+                ready(if response.status() == StatusCode::NO_CONTENT {
+                    Ok(response.into_response(()))
                 } else {
-                    match serde_json::from_reader::<_, ApiError>(body) {
-                        Ok(error) => Err(Error::Api(error)),
-                        Err(error) => Err(Error::Serialization(error)),
-                    }
-                }
+                    Err(response.api_error_or_status())
+                })
             })
         })
-    }).await
+        .await
 }
 
 /// Lists the members of the cluster.
@@ -153,47 +121,32 @@ where
 /// # Parameters
 ///
 /// * client: A `Client` to use to make the API call.
-pub async fn list<C>(
-    client: &Client<C>,
-) -> Result<Response<Vec<Member>>, Vec<Error>>
+pub async fn list<C>(client: &Client<C>) -> Result<Response<Vec<Member>>, Vec<Error>>
 where
     C: Clone + Connect + Sync + Send,
 {
     let http_client = client.http_client().clone();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
-        let url = build_url(member, "");
-        let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
+    client
+        .first_ok(true, move |member| {
+            let url = build_url(member, "");
+            let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
 
-        let http_client = http_client.clone();
+            let http_client = http_client.clone();
 
-        let response = uri.and_then(move |uri| http_client.get(uri).map_err(Error::from));
+            let response = uri.and_then(move |uri| http_client.get(uri));
 
-        response.and_then(|response| {
-            let status = response.status();
-            let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::aggregate(response.into_body())
-                .map_ok(BufExt::reader)
-                .err_into();
-
-            body.and_then(move |body| async move {
-                if status == StatusCode::OK {
-                    match serde_json::from_reader::<_, ListResponse>(body) {
-                        Ok(data) => Ok(Response {
-                            data: data.members,
-                            cluster_info,
-                        }),
-                        Err(error) => Err(Error::Serialization(error)),
-                    }
+            response.and_then(|response| {
+                ready(if response.status() == StatusCode::OK {
+                    response
+                        .json::<ListResponse>()
+                        .map(|data| response.into_response(data.members))
                 } else {
-                    match serde_json::from_reader::<_, ApiError>(body) {
-                        Ok(error) => Err(Error::Api(error)),
-                        Err(error) => Err(Error::Serialization(error)),
-                    }
-                }
+                    Err(response.api_error_or_status())
+                })
             })
         })
-    }).await
+        .await
 }
 
 /// Updates the peer URLs of a member of the cluster.
@@ -220,37 +173,25 @@ where
 
     let http_client = client.http_client().clone();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
-        let url = build_url(member, &format!("/{}", id));
-        let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
-
-        let body = body.clone();
-        let http_client = http_client.clone();
+    client
+        .first_ok(true, move |member| {
+            let url = build_url(member, &format!("/{}", id));
+            let uri = ready(Uri::from_str(url.as_str()).map_err(Error::from));
 
-        let response = uri.and_then(move |uri| http_client.put(uri, body).map_err(Error::from));
+            let body = body.clone();
+            let http_client = http_client.clone();
 
-        response.and_then(|response| {
-            let status = response.status();
-            let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::aggregate(response.into_body())
-                .err_into()
-                .map_ok(BufExt::reader);
+            let response = uri.and_then(move |uri| http_client.put(uri, body));
 
-            body.and_then(move |body| async move{
-                if status == StatusCode::NO_CONTENT {
-                    Ok(Response {
-                        data: (),
-                        cluster_info,
-                    })
+            response.and_then(|response| {
+                ready(if response.status() == StatusCode::NO_CONTENT {
+                    Ok(response.into_response(()))
                 } else {
-                    match serde_json::from_reader::<_, ApiError>(body) {
-                        Ok(error) => Err(Error::Api(error)),
-                        Err(error) => Err(Error::Serialization(error)),
-                    }
-                }
+                    Err(response.api_error_or_status())
+                })
             })
         })
-    }).await
+        .await
 }
 
 /// Constructs the full URL for an API call.
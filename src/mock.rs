@@ -0,0 +1,333 @@
+//! An in-memory stand-in for etcd, for testing code built on this crate without a running etcd.
+//!
+//! `MockEtcd` answers requests with canned responses, queued in order with `MockEtcd::respond`,
+//! and records every request it receives so that tests can assert on them afterwards. Requests
+//! never leave the process: `MockConnector` is a hyper connector whose connections are served by
+//! the mock directly, so it can be passed to `Client::custom` like any other connector. A
+//! connector can also route some endpoints to other mocks or refuse connections to them, to
+//! simulate a cluster of several members.
+//!
+//! This module is only available with the `test-util` Cargo feature enabled.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use etcd::kv::{self, GetOptions};
+//! use etcd::mock::MockEtcd;
+//! use hyper::{Method, StatusCode};
+//! use tokio::runtime::Runtime;
+//!
+//! fn main() {
+//!     let etcd = MockEtcd::new();
+//!     etcd.respond(
+//!         StatusCode::OK,
+//!         r#"{"action":"get","node":{"key":"/foo","value":"bar","createdIndex":1}}"#,
+//!     );
+//!
+//!     let client = etcd.client();
+//!
+//!     let work = async {
+//!         let response = kv::get(&client, "/foo", GetOptions::default()).await.unwrap();
+//!         assert_eq!(response.data.node.value.unwrap(), "bar");
+//!     };
+//!
+//!     Runtime::new().unwrap().block_on(work);
+//!
+//!     let requests = etcd.requests();
+//!     assert_eq!(requests[0].method, Method::GET);
+//!     assert_eq!(requests[0].uri.path(), "/v2/keys/foo");
+//! }
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, ErrorKind};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use futures::future::{ready, Ready};
+use hyper::client::connect::{Connected, Connection};
+use hyper::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use hyper::{Body, Client as Hyper, Method, Request, Response, StatusCode, Uri};
+use tokio::io::{duplex, AsyncRead, AsyncWrite, DuplexStream};
+use tower_service::Service;
+use url::form_urlencoded;
+
+use crate::client::Client;
+use crate::connector::host_and_port;
+
+/// The endpoint used by clients created with `MockEtcd::client`.
+pub const MOCK_ENDPOINT: &str = "http://etcd.mock:2379";
+
+/// The size of the buffer in each direction of an in-memory connection.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// An in-memory stand-in for an etcd cluster member.
+///
+/// Cloning a `MockEtcd` yields a handle to the same mock, so responses queued and requests
+/// recorded through any clone are shared.
+#[derive(Clone, Debug, Default)]
+pub struct MockEtcd {
+    state: Arc<Mutex<MockState>>,
+}
+
+/// The responses still to be sent and the requests received by a `MockEtcd`.
+#[derive(Debug, Default)]
+struct MockState {
+    requests: Vec<MockRequest>,
+    responses: VecDeque<(StatusCode, HeaderMap, String)>,
+}
+
+impl MockEtcd {
+    /// Constructs a new mock with no queued responses.
+    pub fn new() -> MockEtcd {
+        MockEtcd::default()
+    }
+
+    /// Queues a JSON response to be sent for the next request that doesn't already have one.
+    ///
+    /// Responses are sent in the order they are queued. A request received once the queue is
+    /// empty is answered with a 500 status code and an etcd API error with error code 300.
+    pub fn respond<B>(&self, status: StatusCode, body: B) -> &MockEtcd
+    where
+        B: Into<String>,
+    {
+        self.respond_with_headers(status, HeaderMap::new(), body)
+    }
+
+    /// Queues a response with the given headers, such as `X-Etcd-Index` or `Location`, to be sent
+    /// for the next request that doesn't already have one.
+    ///
+    /// The Content-Type header is set to `application/json` unless `headers` contains one.
+    pub fn respond_with_headers<B>(
+        &self,
+        status: StatusCode,
+        headers: HeaderMap,
+        body: B,
+    ) -> &MockEtcd
+    where
+        B: Into<String>,
+    {
+        let mut state = self.state.lock().unwrap();
+
+        state.responses.push_back((status, headers, body.into()));
+
+        self
+    }
+
+    /// Returns a connector whose connections are served by this mock.
+    pub fn connector(&self) -> MockConnector {
+        MockConnector {
+            etcd: self.clone(),
+            routes: HashMap::new(),
+        }
+    }
+
+    /// Constructs a client whose requests are served by this mock, using `MOCK_ENDPOINT` as its
+    /// only endpoint.
+    pub fn client(&self) -> Client<MockConnector> {
+        let hyper = Hyper::builder().build(self.connector());
+
+        Client::custom(hyper, &[MOCK_ENDPOINT], None).unwrap()
+    }
+
+    /// Returns the requests received so far, in the order they were received.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Returns the number of queued responses that haven't been sent yet.
+    pub fn pending_responses(&self) -> usize {
+        self.state.lock().unwrap().responses.len()
+    }
+
+    /// Records a request and returns the next queued response for it.
+    fn handle(&self, request: MockRequest) -> Response<Body> {
+        let mut state = self.state.lock().unwrap();
+
+        state.requests.push(request);
+
+        // etcd's "internal error" error code.
+        let (status, headers, body) = state.responses.pop_front().unwrap_or_else(|| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                HeaderMap::new(),
+                r#"{"errorCode":300,"message":"No response queued in MockEtcd"}"#.to_owned(),
+            )
+        });
+
+        let mut response = Response::new(Body::from(body));
+        *response.status_mut() = status;
+        *response.headers_mut() = headers;
+        response
+            .headers_mut()
+            .entry(CONTENT_TYPE)
+            .or_insert_with(|| HeaderValue::from_static("application/json"));
+
+        response
+    }
+}
+
+/// A request received by a `MockEtcd`.
+#[derive(Clone, Debug)]
+pub struct MockRequest {
+    /// The HTTP method of the request.
+    pub method: Method,
+    /// The URI of the request, including its query string.
+    pub uri: Uri,
+    /// The headers of the request.
+    pub headers: HeaderMap,
+    /// The body of the request.
+    pub body: String,
+}
+
+impl MockRequest {
+    /// Returns the parameters in the request's query string.
+    pub fn query(&self) -> HashMap<String, String> {
+        let query = self.uri.query().unwrap_or("");
+
+        form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect()
+    }
+
+    /// Returns the form-encoded parameters in the request's body, as sent by write operations.
+    pub fn form(&self) -> HashMap<String, String> {
+        form_urlencoded::parse(self.body.as_bytes())
+            .into_owned()
+            .collect()
+    }
+}
+
+/// A hyper connector whose connections are served in memory by a `MockEtcd`.
+///
+/// Connections are served by the mock the connector was created from, whatever their destination,
+/// unless `MockConnector::route` or `MockConnector::refuse` says otherwise for the destination's
+/// host and port.
+#[derive(Clone, Debug)]
+pub struct MockConnector {
+    etcd: MockEtcd,
+    /// The mocks serving connections to particular hosts and ports, or `None` for those whose
+    /// connections are refused.
+    routes: HashMap<String, Option<MockEtcd>>,
+}
+
+impl MockConnector {
+    /// Serves connections to the host and port of the given endpoint with another mock, such as
+    /// one standing in for a different cluster member.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the endpoint is an invalid URL.
+    pub fn route(mut self, endpoint: &str, etcd: &MockEtcd) -> MockConnector {
+        self.routes.insert(authority(endpoint), Some(etcd.clone()));
+        self
+    }
+
+    /// Refuses connections to the host and port of the given endpoint, as if the cluster member
+    /// there were down.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the endpoint is an invalid URL.
+    pub fn refuse(mut self, endpoint: &str) -> MockConnector {
+        self.routes.insert(authority(endpoint), None);
+        self
+    }
+}
+
+impl Service<Uri> for MockConnector {
+    type Response = MockStream;
+    type Error = io::Error;
+    type Future = Ready<Result<MockStream, io::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, destination: Uri) -> Self::Future {
+        let etcd = match self.routes.get(&host_and_port(&destination)) {
+            Some(Some(etcd)) => etcd.clone(),
+            Some(None) => {
+                return ready(Err(io::Error::new(
+                    ErrorKind::ConnectionRefused,
+                    "connection refused by MockConnector",
+                )));
+            }
+            None => self.etcd.clone(),
+        };
+
+        let (client, server) = duplex(BUFFER_SIZE);
+
+        let service = service_fn(move |request: Request<Body>| {
+            let etcd = etcd.clone();
+
+            async move {
+                let (parts, body) = request.into_parts();
+                let body = hyper::body::to_bytes(body).await?;
+
+                let request = MockRequest {
+                    method: parts.method,
+                    uri: parts.uri,
+                    headers: parts.headers,
+                    body: String::from_utf8_lossy(&body).into_owned(),
+                };
+
+                Ok::<_, hyper::Error>(etcd.handle(request))
+            }
+        });
+
+        tokio::spawn(async move {
+            let _ = Http::new().serve_connection(server, service).await;
+        });
+
+        ready(Ok(MockStream { inner: client }))
+    }
+}
+
+/// Returns the host and port of an endpoint.
+fn authority(endpoint: &str) -> String {
+    host_and_port(&endpoint.parse().expect("valid endpoint"))
+}
+
+/// The client side of an in-memory connection to a `MockEtcd`.
+#[derive(Debug)]
+pub struct MockStream {
+    inner: DuplexStream,
+}
+
+impl Connection for MockStream {
+    fn connected(&self) -> Connected {
+        Connected::new()
+    }
+}
+
+impl AsyncRead for MockStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for MockStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
@@ -1,4 +1,7 @@
 /// Possible conditions for "compare and delete" and "compare and swap" operations.
+///
+/// When both conditions are given, both are sent and etcd performs the operation only if both
+/// match.
 #[derive(Debug)]
 pub struct ComparisonConditions<'a> {
     /// The etcd modified index the key must have before the operation is performed.
@@ -9,6 +12,9 @@ pub struct ComparisonConditions<'a> {
 
 impl<'a> ComparisonConditions<'a> {
     /// Returns a boolean indicating whether or not both conditions are unset.
+    ///
+    /// A single condition is enough for an operation to be conditional, so this is false if
+    /// either one is set.
     pub fn is_empty(&self) -> bool {
         self.modified_index.is_none() && self.value.is_none()
     }
@@ -51,6 +57,8 @@ pub struct SetOptions<'a> {
     pub dir: Option<bool>,
     /// Whether or not the key being operated on must already exist.
     pub prev_exist: Option<bool>,
+    /// Whether or not to only reset the TTL of the key, leaving its value untouched.
+    pub refresh: bool,
     /// Time to live in seconds.
     pub ttl: Option<u64>,
     /// New value for the key.
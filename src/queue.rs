@@ -0,0 +1,108 @@
+//! A first-in, first-out work queue built on the key-value API.
+//!
+//! Items are pushed with `kv::create_in_order`, so each one is a key-value pair in the queue's
+//! directory whose key is larger than the keys of all items pushed before it. Items are popped in
+//! that order, each by exactly one consumer, so several producers and consumers can share a queue.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use etcd::queue::Queue;
+//! use etcd::Client;
+//! use tokio::runtime::Runtime;
+//!
+//! fn main() {
+//!     let client = Client::new(&["http://etcd.example.com:2379"], None).unwrap();
+//!     let queue = Queue::new(&client, "/jobs");
+//!
+//!     let work = async {
+//!         queue.push("resize image 42").await.unwrap();
+//!
+//!         while let Some((key, value)) = queue.pop().await.unwrap() {
+//!             println!("processing {} ({})", value, key);
+//!         }
+//!     };
+//!
+//!     Runtime::new().unwrap().block_on(work);
+//! }
+//! ```
+
+use hyper::client::connect::Connect;
+
+use crate::client::Client;
+use crate::error::Error;
+use crate::kv;
+
+/// The number of seconds for which a record of each popped item is kept, unless changed with
+/// `Queue::set_claim_ttl`.
+const DEFAULT_CLAIM_TTL: u64 = 60;
+
+/// A first-in, first-out queue stored in an etcd directory.
+#[derive(Clone, Debug)]
+pub struct Queue<C>
+where
+    C: Clone + Connect + Sync + Send + 'static,
+{
+    claim_ttl: u64,
+    client: Client<C>,
+    dir: String,
+}
+
+impl<C> Queue<C>
+where
+    C: Clone + Connect + Sync + Send + 'static,
+{
+    /// Constructs a handle to the queue stored in the given directory. The directory is created
+    /// when the first item is pushed.
+    ///
+    /// # Parameters
+    ///
+    /// * client: A `Client` to use to make the API calls.
+    /// * dir: The name of the directory that holds the queue.
+    pub fn new(client: &Client<C>, dir: &str) -> Self {
+        Queue {
+            claim_ttl: DEFAULT_CLAIM_TTL,
+            client: client.clone(),
+            dir: dir.to_owned(),
+        }
+    }
+
+    /// Returns the name of the directory that holds the queue.
+    pub fn dir(&self) -> &str {
+        &self.dir
+    }
+
+    /// Sets the number of seconds for which a record of each popped item is kept. See
+    /// `Queue::pop`.
+    ///
+    /// The default is 60 seconds.
+    pub fn set_claim_ttl(&mut self, claim_ttl: u64) {
+        self.claim_ttl = claim_ttl;
+    }
+
+    /// Adds an item to the end of the queue, returning the key generated for it.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the queue's directory already exists as a key-value pair.
+    pub async fn push(&self, value: &str) -> Result<String, Vec<Error>> {
+        kv::enqueue(&self.client, &self.dir, value, None)
+            .await
+            .map(|(key, _)| key)
+    }
+
+    /// Removes the item at the front of the queue, returning its key and value, or `None` if the
+    /// queue is empty.
+    ///
+    /// Items are popped with `kv::claim_oldest`, so when several consumers pop concurrently, every
+    /// item is returned to exactly one of them. A consumer that loses the race for an item moves
+    /// on to the next one. The record of the claim expires after the queue's claim TTL.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the queue's directory is a key-value pair, or if the queue can't be read or an
+    /// item can't be claimed for any reason other than another consumer claiming it first.
+    pub async fn pop(&self) -> Result<Option<(String, String)>, Vec<Error>> {
+        kv::claim_oldest(&self.client, &self.dir, self.claim_ttl).await
+    }
+}
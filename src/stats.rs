@@ -1,4 +1,8 @@
 //! etcd's statistics API.
+//!
+//! The v2 API doesn't expose the cluster's Raft timing configuration: the heartbeat interval and
+//! election timeout are command-line flags of each member, and neither these statistics nor any
+//! other v2 endpoint reports them.
 
 use std::collections::HashMap;
 
@@ -179,7 +183,8 @@ pub fn self_stats<C>(
 where
     C: Clone + Connect + Sync + Send,
 {
-    let futures = client.endpoints().iter().map(|endpoint| {
+    let endpoints = client.endpoints();
+    let futures = endpoints.iter().map(|endpoint| {
         let url = build_url(&endpoint, "v2/stats/self");
         let uri = ready(url.parse()).err_into();
 
@@ -199,7 +204,8 @@ pub fn store_stats<C>(
 where
     C: Clone + Connect + Sync + Send,
 {
-    let futures = client.endpoints().iter().map(|endpoint| {
+    let endpoints = client.endpoints();
+    let futures = endpoints.iter().map(|endpoint| {
         let url = build_url(&endpoint, "v2/stats/store");
         let uri = ready(url.parse()).err_into();
 
@@ -213,3 +219,92 @@ where
 fn build_url(endpoint: &Uri, path: &str) -> String {
     format!("{}{}", endpoint, path)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{LeaderStats, SelfStats, StoreStats};
+
+    #[test]
+    fn test_leader_stats() {
+        let body = r#"{
+            "followers": {
+                "6e3bd23ae5f1eae0": {
+                    "counts": {"fail": 0, "success": 745},
+                    "latency": {
+                        "average": 0.017039507382550306,
+                        "current": 0.000138,
+                        "maximum": 1.007649,
+                        "minimum": 0,
+                        "standardDeviation": 0.05289178277920594
+                    }
+                }
+            },
+            "leader": "924e2e83e93f2560"
+        }"#;
+
+        let stats: LeaderStats = serde_json::from_str(body).unwrap();
+        let follower = &stats.followers["6e3bd23ae5f1eae0"];
+
+        assert_eq!(stats.leader, "924e2e83e93f2560");
+        assert_eq!(follower.counts.success, 745);
+        assert_eq!(follower.latency.minimum, 0.0);
+        assert_eq!(follower.latency.standard_deviation, 0.05289178277920594);
+    }
+
+    #[test]
+    fn test_self_stats() {
+        let body = r#"{
+            "id": "eca0338f4ea31566",
+            "leaderInfo": {
+                "leader": "8a69d5f6b7814500",
+                "startTime": "2014-10-24T13:15:51.186620747-07:00",
+                "uptime": "10m59.322358947s"
+            },
+            "name": "node3",
+            "recvAppendRequestCnt": 5944,
+            "recvBandwidthRate": 570.6254930219969,
+            "recvPkgRate": 9.00892789741075,
+            "sendAppendRequestCnt": 0,
+            "startTime": "2014-10-24T13:15:50.072007085-07:00",
+            "state": "StateFollower"
+        }"#;
+
+        let stats: SelfStats = serde_json::from_str(body).unwrap();
+
+        assert_eq!(stats.leader_info.id, "8a69d5f6b7814500");
+        assert_eq!(stats.received_append_request_count, 5944);
+        assert_eq!(stats.received_package_rate, Some(9.00892789741075));
+        assert_eq!(stats.sent_bandwidth_rate, None);
+        assert_eq!(stats.state, "StateFollower");
+    }
+
+    #[test]
+    fn test_store_stats() {
+        let body = r#"{
+            "compareAndSwapFail": 1,
+            "compareAndSwapSuccess": 2,
+            "compareAndDeleteSuccess": 3,
+            "compareAndDeleteFail": 4,
+            "createFail": 5,
+            "createSuccess": 6,
+            "deleteFail": 7,
+            "deleteSuccess": 8,
+            "expireCount": 9,
+            "getsFail": 10,
+            "getsSuccess": 11,
+            "setsFail": 12,
+            "setsSuccess": 13,
+            "updateFail": 14,
+            "updateSuccess": 15,
+            "watchers": 16
+        }"#;
+
+        let stats: StoreStats = serde_json::from_str(body).unwrap();
+
+        assert_eq!(stats.compare_and_swap_success, 2);
+        assert_eq!(stats.get_fail, 10);
+        assert_eq!(stats.get_success, 11);
+        assert_eq!(stats.set_success, 13);
+        assert_eq!(stats.watchers, 16);
+    }
+}
@@ -0,0 +1,102 @@
+//! Tracking of a client's active watches, so that `Client::cancel_all_watches` can stop them.
+//!
+//! Each watch registers itself when it starts and is deregistered when its stream is dropped.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use futures::future::{AbortHandle, AbortRegistration};
+
+/// Keeps track of the active watch streams started by a client so they can be cancelled together.
+#[derive(Debug, Default)]
+pub struct WatchRegistry {
+    handles: Mutex<HashMap<u64, AbortHandle>>,
+    next_id: AtomicU64,
+}
+
+impl WatchRegistry {
+    /// Registers a new watch, returning a guard that deregisters it when dropped and a
+    /// registration to abort the watch's stream with.
+    pub fn register(self: &Arc<Self>) -> (WatchGuard, AbortRegistration) {
+        let (handle, registration) = AbortHandle::new_pair();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+
+        self.handles.lock().unwrap().insert(id, handle);
+
+        let guard = WatchGuard {
+            id,
+            registry: self.clone(),
+        };
+
+        (guard, registration)
+    }
+
+    /// Returns the number of registered watches.
+    pub fn len(&self) -> usize {
+        self.handles.lock().unwrap().len()
+    }
+
+    /// Aborts every registered watch.
+    pub fn cancel_all(&self) {
+        for (_, handle) in self.handles.lock().unwrap().drain() {
+            handle.abort();
+        }
+    }
+}
+
+/// Deregisters a watch from a `WatchRegistry` when dropped.
+#[derive(Debug)]
+pub struct WatchGuard {
+    id: u64,
+    registry: Arc<WatchRegistry>,
+}
+
+impl Drop for WatchGuard {
+    fn drop(&mut self) {
+        self.registry.handles.lock().unwrap().remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures::executor::block_on;
+    use futures::stream::{self, Abortable, StreamExt};
+
+    use super::WatchRegistry;
+
+    #[test]
+    fn test_cancel_all() {
+        let registry = Arc::new(WatchRegistry::default());
+
+        let (guard, registration) = registry.register();
+        let mut watch = Abortable::new(stream::repeat(()), registration);
+        assert_eq!(registry.len(), 1);
+
+        assert_eq!(block_on(watch.next()), Some(()));
+
+        registry.cancel_all();
+        assert_eq!(registry.len(), 0);
+        assert_eq!(block_on(watch.next()), None);
+
+        drop(guard);
+        assert_eq!(registry.len(), 0);
+    }
+
+    #[test]
+    fn test_deregister_on_drop() {
+        let registry = Arc::new(WatchRegistry::default());
+
+        let (first, _) = registry.register();
+        let (second, _) = registry.register();
+        assert_eq!(registry.len(), 2);
+
+        drop(first);
+        assert_eq!(registry.len(), 1);
+
+        drop(second);
+        assert_eq!(registry.len(), 0);
+    }
+}
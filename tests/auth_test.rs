@@ -1,17 +1,22 @@
 use etcd::auth::{self, AuthChange, NewUser, Role, RoleUpdate, UserUpdate};
-use etcd::{BasicAuth, Client};
+use etcd::{AuthMethod, BasicAuth, ClientConfig};
+use hyper::client::HttpConnector;
 use tokio::runtime::Runtime;
 
 #[test]
 fn auth() {
-    let client = Client::new(&["http://etcd:2379"], None).unwrap();
+    let config = ClientConfig::new(&["http://etcd:2379"]);
+    let client = config.build::<HttpConnector>().unwrap();
 
     let basic_auth = BasicAuth {
         username: "root".into(),
         password: "secret".into(),
     };
 
-    let authed_client = Client::new(&["http://etcd:2379"], Some(basic_auth)).unwrap();
+    let authed_client = config
+        .with_auth(AuthMethod::Basic(basic_auth))
+        .build::<HttpConnector>()
+        .unwrap();
 
     let root_user = NewUser::new("root", "secret");
 
@@ -36,6 +41,13 @@ fn auth() {
         rkt_role.grant_kv_write_permission("/rkt/*");
         auth::create_role(&authed_client, rkt_role).await.unwrap();
 
+        let results =
+            auth::grant_read_to_roles(&authed_client, "/shared/*", &["rkt", "missing"]).await;
+        assert_eq!(results.len(), 2);
+        let role = &results[0].as_ref().unwrap().data;
+        assert!(role.kv_read_permissions().contains(&"/shared/*".to_owned()));
+        assert!(results[1].is_err());
+
         let mut rkt_user = NewUser::new("rkt", "secret");
         rkt_user.add_role("rkt");
         let response = auth::create_user(&authed_client, rkt_user).await.unwrap();
@@ -51,6 +63,14 @@ fn auth() {
             .await
             .unwrap();
 
+        let response = auth::change_password(&authed_client, "rkt", "secret3")
+            .await
+            .unwrap();
+        let role_names = response.data.role_names();
+        assert_eq!(role_names.len(), 2);
+        assert!(role_names.contains(&"root".to_owned()));
+        assert!(role_names.contains(&"rkt".to_owned()));
+
         let response = auth::get_role(&authed_client, "rkt").await.unwrap();
         let role = response.data;
         assert!(role.kv_read_permissions().contains(&"/rkt/*".to_owned()));
@@ -1,4 +1,20 @@
-use futures::{FutureExt, TryStreamExt};
+use std::io::ErrorKind;
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
+
+use etcd::kv::{self, GetOptions};
+use etcd::mock::{MockConnector, MockEtcd};
+use etcd::{
+    AuthMethod, Client, ClientBuilder, ClientConfig, Concurrency, ConnectionPhase,
+    EndpointStrategy, Error, PinnedConnector, RetryPolicy,
+};
+use futures::{FutureExt, StreamExt, TryStreamExt};
+use hyper::client::HttpConnector;
+use hyper::header::{
+    HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE, HOST, LOCATION, USER_AGENT,
+};
+use hyper::{Method, StatusCode};
+use tokio::runtime::Runtime;
 
 use crate::test::TestClient;
 
@@ -8,16 +24,17 @@ mod test;
 fn health() {
     let mut client = TestClient::no_destructor();
 
-    let work = client.health().try_collect::<Vec<_>>().then(|responses| {
-        async {
+    let work = client
+        .health()
+        .try_collect::<Vec<_>>()
+        .then(|responses| async {
             for response in responses.unwrap() {
                 assert_eq!(response.data.health, "true");
             }
 
             let ret: Result<(), ()> = Ok(());
             ret
-        }
-    });
+        });
 
     client.run(work);
 }
@@ -25,8 +42,10 @@ fn health() {
 fn versions() {
     let mut client = TestClient::no_destructor();
 
-    let work = client.versions().try_collect::<Vec<_>>().then(|responses| {
-        async {
+    let work = client
+        .versions()
+        .try_collect::<Vec<_>>()
+        .then(|responses| async {
             for response in responses.unwrap() {
                 assert_eq!(response.data.cluster_version, "2.3.0");
                 assert_eq!(response.data.server_version, "2.3.8");
@@ -34,8 +53,757 @@ fn versions() {
 
             let ret: Result<(), ()> = Ok(());
             ret
+        });
+
+    client.run(work);
+}
+
+#[test]
+fn version() {
+    let etcd = MockEtcd::new();
+    etcd.respond(
+        StatusCode::OK,
+        r#"{"etcdserver":"2.3.8","etcdcluster":"2.3.0"}"#,
+    );
+    let connector = etcd.connector().refuse("http://a:2379");
+    let client = mock_client(connector, &["http://a:2379", "http://b:2379"]);
+
+    let work = async {
+        let response = client.version().await.unwrap();
+
+        assert_eq!(response.data.cluster_version, "2.3.0");
+        assert_eq!(response.data.server_version, "2.3.8");
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    let requests = etcd.requests();
+
+    assert_eq!(requests[0].method, Method::GET);
+    assert_eq!(requests[0].uri.path(), "/version");
+    assert_eq!(requests[0].headers[HOST], "b:2379");
+}
+
+#[test]
+fn raw_request() {
+    let client = TestClient::no_destructor();
+
+    let work = async {
+        let (status, body, _) = client
+            .raw_request(Method::GET, "/version", None)
+            .await
+            .unwrap();
+
+        assert_eq!(status, StatusCode::OK);
+        assert!(String::from_utf8(body.to_vec())
+            .unwrap()
+            .contains("etcdserver"));
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn connection_refused() {
+    let client = Client::new(&["http://127.0.0.1:1"], None).unwrap();
+
+    let work = async {
+        let errors = kv::get(&client, "/test/foo", GetOptions::default())
+            .await
+            .unwrap_err();
+
+        match errors[0] {
+            Error::Connection { phase, kind, .. } => {
+                assert_eq!(phase, ConnectionPhase::Connect);
+                assert_eq!(kind, ErrorKind::ConnectionRefused);
+            }
+            ref error => panic!("unexpected error: {:?}", error),
         }
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn request_timeout() {
+    // The listener accepts connections but never responds.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let mut client = Client::new(&[&endpoint], None).unwrap();
+    client.set_request_timeout(Some(Duration::from_millis(200)));
+
+    let work = async {
+        let started = Instant::now();
+        let errors = kv::get(&client, "/test/foo", GetOptions::default())
+            .await
+            .unwrap_err();
+        let elapsed = started.elapsed();
+
+        match errors[0] {
+            Error::Timeout => {}
+            ref error => panic!("unexpected error: {:?}", error),
+        }
+
+        assert!(elapsed >= Duration::from_millis(200));
+        assert!(elapsed < Duration::from_secs(2));
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn parallel_concurrency() {
+    // The listener accepts connections but never responds.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let mut client = Client::new(&[&endpoint, "http://etcd:2379"], None).unwrap();
+    client.set_concurrency(Concurrency::Parallel);
+
+    let work = async {
+        let (status, _, _) = client
+            .raw_request(Method::GET, "/version", None)
+            .await
+            .unwrap();
+
+        assert_eq!(status, StatusCode::OK);
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn retry_policy() {
+    let raft_error = r#"{"errorCode":300,"message":"Raft Internal Error","index":1}"#;
+    let etcd = MockEtcd::new();
+    etcd.respond(StatusCode::INTERNAL_SERVER_ERROR, raft_error);
+    etcd.respond(StatusCode::INTERNAL_SERVER_ERROR, raft_error);
+    etcd.respond(
+        StatusCode::OK,
+        r#"{"action":"get","node":{"key":"/foo","value":"bar","modifiedIndex":1}}"#,
+    );
+    let mut client = etcd.client();
+    client.set_retry_policy(RetryPolicy {
+        max_attempts: 3,
+        base_delay: Duration::from_millis(10),
+        ..Default::default()
     });
 
-    client.run(work);
+    let work = async {
+        let response = kv::get(&client, "/foo", GetOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(response.data.node.value.unwrap(), "bar");
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    assert_eq!(etcd.requests().len(), 3);
+}
+
+#[test]
+fn retry_policy_not_retryable() {
+    let etcd = MockEtcd::new();
+    etcd.respond(
+        StatusCode::NOT_FOUND,
+        r#"{"errorCode":100,"message":"Key not found","index":1}"#,
+    );
+    let mut client = etcd.client();
+    client.set_retry_policy(RetryPolicy {
+        max_attempts: 3,
+        base_delay: Duration::from_millis(10),
+        ..Default::default()
+    });
+
+    let work = async {
+        let errors = kv::get(&client, "/foo", GetOptions::default())
+            .await
+            .unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+
+        match errors[0] {
+            Error::Api(ref error) => assert_eq!(error.error_code, 100),
+            ref error => panic!("unexpected error: {:?}", error),
+        }
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn retry_policy_non_idempotent() {
+    let etcd = MockEtcd::new();
+    etcd.respond(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        r#"{"errorCode":300,"message":"Raft Internal Error","index":1}"#,
+    );
+    etcd.respond(
+        StatusCode::CREATED,
+        r#"{"action":"create","node":{"key":"/foo","value":"bar","modifiedIndex":1}}"#,
+    );
+    let mut client = etcd.client();
+    client.set_retry_policy(RetryPolicy {
+        max_attempts: 3,
+        base_delay: Duration::from_millis(10),
+        ..Default::default()
+    });
+
+    let work = async {
+        let errors = kv::create(&client, "/foo", "bar", None).await.unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+
+        match errors[0] {
+            Error::Api(ref error) => assert_eq!(error.error_code, 300),
+            ref error => panic!("unexpected error: {:?}", error),
+        }
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    assert_eq!(etcd.requests().len(), 1);
+}
+
+#[test]
+fn default_headers() {
+    let etcd = MockEtcd::new();
+    etcd.respond(
+        StatusCode::OK,
+        r#"{"action":"set","node":{"key":"/foo","value":"bar","modifiedIndex":1}}"#,
+    );
+    let mut client = etcd.client();
+
+    let mut headers = HeaderMap::new();
+    headers.insert("X-Request-Id", HeaderValue::from_static("abc123"));
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+    client.set_default_headers(headers);
+
+    let work = async {
+        kv::set(&client, "/foo", "bar", None).await.unwrap();
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    let request = &etcd.requests()[0];
+    let content_types: Vec<_> = request.headers.get_all(CONTENT_TYPE).iter().collect();
+
+    assert_eq!(request.headers["x-request-id"], "abc123");
+    assert_eq!(content_types, ["application/x-www-form-urlencoded"]);
+}
+
+#[test]
+fn bearer_auth() {
+    let etcd = MockEtcd::new();
+    etcd.respond(
+        StatusCode::OK,
+        r#"{"action":"get","node":{"key":"/foo","value":"bar","modifiedIndex":1}}"#,
+    );
+    let mut client = etcd.client();
+    client.set_auth(AuthMethod::Bearer("secret".to_owned()));
+
+    let work = async {
+        kv::get(&client, "/foo", GetOptions::default())
+            .await
+            .unwrap();
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    assert_eq!(etcd.requests()[0].headers[AUTHORIZATION], "Bearer secret");
+}
+
+#[test]
+fn client_builder() {
+    let client = ClientBuilder::new(&["http://a:2379"])
+        .auth(AuthMethod::Bearer("secret".to_owned()))
+        .pool_max_idle_per_host(1)
+        .pool_idle_timeout(Some(Duration::from_secs(1)))
+        .http2_keep_alive_interval(Some(Duration::from_secs(10)))
+        .user_agent(HeaderValue::from_static("inventory-service/1.4"))
+        .build()
+        .unwrap();
+
+    assert_eq!(client.auth(), &AuthMethod::Bearer("secret".to_owned()));
+    assert_eq!(client.user_agent(), "inventory-service/1.4");
+
+    match ClientBuilder::new(&[]).build() {
+        Err(Error::NoEndpoints) => {}
+        result => panic!("unexpected result: {:?}", result.map(|_| ())),
+    }
+}
+
+#[test]
+fn client_config() {
+    let mut config = ClientConfig::new(&["http://a:2379", "http://b:2379"]);
+    config.endpoint_strategy = EndpointStrategy::RoundRobin;
+    config.request_timeout = Some(Duration::from_secs(5));
+
+    let client = config.build::<HttpConnector>().unwrap();
+    let authed_client = config
+        .with_auth(AuthMethod::Bearer("secret".to_owned()))
+        .build::<HttpConnector>()
+        .unwrap();
+
+    assert_eq!(client.auth(), &AuthMethod::None);
+    assert_eq!(
+        authed_client.auth(),
+        &AuthMethod::Bearer("secret".to_owned())
+    );
+
+    for client in &[client, authed_client] {
+        assert_eq!(client.endpoint_strategy(), EndpointStrategy::RoundRobin);
+        assert_eq!(client.request_timeout(), Some(Duration::from_secs(5)));
+    }
+}
+
+#[test]
+fn cluster_health() {
+    let healthy = MockEtcd::new();
+    healthy.respond(StatusCode::OK, r#"{"health":"true"}"#);
+    let unhealthy = MockEtcd::new();
+    unhealthy.respond(StatusCode::OK, r#"{"health":"false"}"#);
+    let connector = healthy
+        .connector()
+        .route("http://b:2379", &unhealthy)
+        .refuse("http://c:2379");
+    let client = mock_client(
+        connector,
+        &["http://a:2379", "http://b:2379", "http://c:2379"],
+    );
+
+    let work = async {
+        let health = client.cluster_health().await;
+
+        assert_eq!(health.members.len(), 3);
+        assert_eq!(health.members[0].endpoint.to_string(), "http://a:2379/");
+        assert!(health.members[0].is_healthy());
+        assert!(!health.members[1].is_healthy());
+        assert!(health.members[1].result.is_ok());
+        assert!(!health.members[2].is_healthy());
+        assert!(health.members[2].result.is_err());
+        assert_eq!(health.healthy_count(), 1);
+        assert!(!health.is_healthy());
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn cluster_healthy() {
+    let first = MockEtcd::new();
+    first.respond(StatusCode::OK, r#"{"health":"true"}"#);
+    let second = MockEtcd::new();
+    second.respond(StatusCode::OK, r#"{"health":"true"}"#);
+    let connector = first
+        .connector()
+        .route("http://b:2379", &second)
+        .refuse("http://c:2379");
+    let client = mock_client(
+        connector,
+        &["http://a:2379", "http://b:2379", "http://c:2379"],
+    );
+
+    let work = async {
+        assert!(client.cluster_healthy().await.unwrap());
+
+        let connector = MockEtcd::new().connector().refuse("http://a:2379");
+        let client = mock_client(connector, &["http://a:2379"]);
+        let errors = client.cluster_healthy().await.unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn strong_consistency() {
+    let body = r#"{"action":"get","node":{"key":"/foo","value":"bar","modifiedIndex":1}}"#;
+    let etcd = MockEtcd::new();
+    etcd.respond(StatusCode::OK, body);
+    etcd.respond(StatusCode::OK, body);
+    let client = etcd.client();
+
+    let work = async {
+        let options = GetOptions {
+            strong_consistency: true,
+            ..Default::default()
+        };
+
+        kv::get(&client, "/foo", options).await.unwrap();
+        kv::get(&client, "/foo", GetOptions::default())
+            .await
+            .unwrap();
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    let requests = etcd.requests();
+
+    assert_eq!(requests[0].method, Method::GET);
+    assert_eq!(requests[0].uri.path(), "/v2/keys/foo");
+    assert_eq!(requests[0].query()["quorum"], "true");
+    assert!(!requests[1].query().contains_key("quorum"));
+    assert!(!requests[1].query().contains_key("sorted"));
+}
+
+#[test]
+fn from_endpoints() {
+    let endpoint = "http://127.0.0.1:1".to_owned();
+
+    assert!(Client::from_endpoint(&endpoint, None).is_ok());
+    assert!(Client::from_endpoints(vec![endpoint.clone(), endpoint.clone()], None).is_ok());
+    assert!(Client::from_endpoints(["http://127.0.0.1:1"].iter(), None).is_ok());
+
+    match Client::from_endpoints(vec![endpoint.as_str(), "http://bad host"], None) {
+        Err(Error::InvalidEndpoint { ref endpoint, .. }) => assert_eq!(endpoint, "http://bad host"),
+        result => panic!("unexpected result: {:?}", result.map(|_| ())),
+    }
+
+    match Client::from_endpoints(Vec::<String>::new(), None) {
+        Err(Error::NoEndpoints) => {}
+        result => panic!("unexpected result: {:?}", result.map(|_| ())),
+    }
+}
+
+#[test]
+fn redirect() {
+    let leader = MockEtcd::new();
+    leader.respond(
+        StatusCode::OK,
+        r#"{"action":"set","node":{"key":"/foo","value":"bar","modifiedIndex":1}}"#,
+    );
+    let follower = MockEtcd::new();
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        LOCATION,
+        HeaderValue::from_static("http://b:2379/v2/keys/foo"),
+    );
+    follower.respond_with_headers(StatusCode::TEMPORARY_REDIRECT, headers.clone(), "");
+    follower.respond_with_headers(StatusCode::TEMPORARY_REDIRECT, headers, "");
+    let connector = follower.connector().route("http://b:2379", &leader);
+    let mut client = mock_client(connector, &["http://a:2379"]);
+
+    let work = async {
+        let errors = kv::set(&client, "/foo", "bar", None).await.unwrap_err();
+
+        match errors[0] {
+            Error::Redirect { ref location } => assert_eq!(location, "http://b:2379/v2/keys/foo"),
+            ref error => panic!("unexpected error: {:?}", error),
+        }
+
+        client.set_follow_redirects(true);
+
+        let response = kv::set(&client, "/foo", "bar", None).await.unwrap();
+
+        assert_eq!(response.data.node.value.unwrap(), "bar");
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    let requests = leader.requests();
+
+    assert_eq!(requests[0].method, Method::PUT);
+    assert_eq!(requests[0].uri.path(), "/v2/keys/foo");
+}
+
+#[test]
+fn redirect_to_other_host() {
+    let leader = MockEtcd::new();
+    leader.respond(
+        StatusCode::OK,
+        r#"{"action":"set","node":{"key":"/foo","value":"bar","modifiedIndex":1}}"#,
+    );
+    let follower = MockEtcd::new();
+    let mut same_host = HeaderMap::new();
+    same_host.insert(LOCATION, HeaderValue::from_static("/v2/keys/foo"));
+    follower.respond_with_headers(StatusCode::TEMPORARY_REDIRECT, same_host, "");
+    let mut other_host = HeaderMap::new();
+    other_host.insert(
+        LOCATION,
+        HeaderValue::from_static("http://b:2379/v2/keys/foo"),
+    );
+    follower.respond_with_headers(StatusCode::PERMANENT_REDIRECT, other_host, "");
+    let connector = follower.connector().route("http://b:2379", &leader);
+    let mut client = mock_client(connector, &["http://a:2379"]);
+    client.set_auth(AuthMethod::Bearer("secret".to_owned()));
+    client.set_follow_redirects(true);
+
+    let work = async {
+        kv::set(&client, "/foo", "bar", None).await.unwrap();
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    let follower_requests = follower.requests();
+    let leader_requests = leader.requests();
+
+    assert_eq!(follower_requests.len(), 2);
+    assert_eq!(follower_requests[1].headers[AUTHORIZATION], "Bearer secret");
+    assert_eq!(leader_requests[0].method, Method::PUT);
+    assert!(!leader_requests[0].headers.contains_key(AUTHORIZATION));
+}
+
+#[test]
+fn redirect_not_followed() {
+    let follower = MockEtcd::new();
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        LOCATION,
+        HeaderValue::from_static("http://b:2379/v2/keys/foo"),
+    );
+    follower.respond_with_headers(StatusCode::SEE_OTHER, headers.clone(), "");
+    follower.respond_with_headers(StatusCode::TEMPORARY_REDIRECT, headers, "");
+    let leader = MockEtcd::new();
+    let connector = follower.connector().route("http://b:2379", &leader);
+    let mut client = mock_client(connector, &["http://a:2379"]);
+    client.set_follow_redirects(true);
+
+    let work = async {
+        let errors = kv::set(&client, "/foo", "bar", None).await.unwrap_err();
+
+        match errors[0] {
+            Error::Redirect { .. } => {}
+            ref error => panic!("unexpected error: {:?}", error),
+        }
+
+        let errors = kv::get(&client, "/foo", GetOptions::default())
+            .await
+            .unwrap_err();
+
+        match errors[0] {
+            Error::Redirect { .. } => {}
+            ref error => panic!("unexpected error: {:?}", error),
+        }
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    assert!(leader.requests().is_empty());
+}
+
+#[test]
+fn refresh_endpoints() {
+    let seed = MockEtcd::new();
+    seed.respond(
+        StatusCode::OK,
+        r#"{"members":[{"id":"1","name":"a","peerURLs":[],"clientURLs":["http://b:2379"]}]}"#,
+    );
+    let member = MockEtcd::new();
+    member.respond(
+        StatusCode::OK,
+        r#"{"action":"get","node":{"key":"/foo","value":"bar","modifiedIndex":1}}"#,
+    );
+    let connector = seed.connector().route("http://b:2379", &member);
+    let client = mock_client(connector, &["http://a:2379"]);
+
+    let work = async {
+        client.refresh_endpoints().await.unwrap();
+
+        kv::get(&client, "/foo", GetOptions::default())
+            .await
+            .unwrap();
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    let requests = member.requests();
+
+    assert_eq!(requests[0].method, Method::GET);
+    assert_eq!(requests[0].uri.path(), "/v2/keys/foo");
+}
+
+#[test]
+fn user_agent() {
+    let body = r#"{"action":"get","node":{"key":"/foo","value":"bar","modifiedIndex":1}}"#;
+    let etcd = MockEtcd::new();
+    etcd.respond(StatusCode::OK, body);
+    etcd.respond(StatusCode::OK, body);
+    let default_client = etcd.client();
+    let mut custom_client = etcd.client();
+    custom_client.set_user_agent(HeaderValue::from_static("inventory-service/1.4"));
+
+    let work = async {
+        kv::get(&default_client, "/foo", GetOptions::default())
+            .await
+            .unwrap();
+        kv::get(&custom_client, "/foo", GetOptions::default())
+            .await
+            .unwrap();
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    let requests = etcd.requests();
+    let expected = format!("rust-etcd/{}", env!("CARGO_PKG_VERSION"));
+
+    assert_eq!(requests[0].headers[USER_AGENT], expected.as_str());
+    assert_eq!(requests[1].headers[USER_AGENT], "inventory-service/1.4");
+}
+
+#[test]
+fn proxy_error_page() {
+    let page = "<html><body><h1>502 Bad Gateway</h1></body></html>";
+    let etcd = MockEtcd::new();
+    etcd.respond(StatusCode::BAD_GATEWAY, page);
+    let client = etcd.client();
+
+    let work = async {
+        let errors = kv::get(&client, "/foo", GetOptions::default())
+            .await
+            .unwrap_err();
+
+        match errors[0] {
+            Error::UnexpectedStatus { status, ref body } => {
+                assert_eq!(status, StatusCode::BAD_GATEWAY);
+                assert_eq!(body.as_deref(), Some(page));
+            }
+            ref error => panic!("unexpected error: {:?}", error),
+        }
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn member_streams_with_unreachable_member() {
+    let etcd = MockEtcd::new();
+    etcd.respond(StatusCode::OK, r#"{"health":"true"}"#);
+    etcd.respond(
+        StatusCode::OK,
+        r#"{"etcdserver":"2.3.8","etcdcluster":"2.3.0"}"#,
+    );
+    let connector = etcd.connector().refuse("http://a:2379");
+    let client = mock_client(connector, &["http://a:2379", "http://b:2379"]);
+
+    let work = async {
+        let results: Vec<_> = client.health().collect().await;
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results.iter().filter(|result| result.is_err()).count(), 1);
+        assert!(results.iter().any(|result| match result {
+            Ok(response) => response.data.health == "true",
+            Err(_) => false,
+        }));
+
+        let results: Vec<_> = client.versions().collect().await;
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results.iter().filter(|result| result.is_err()).count(), 1);
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn cluster_versions() {
+    let etcd = MockEtcd::new();
+    etcd.respond(
+        StatusCode::OK,
+        r#"{"etcdserver":"2.3.8","etcdcluster":"2.3.0"}"#,
+    );
+    let connector = etcd.connector().refuse("http://a:2379");
+    let client = mock_client(connector, &["http://a:2379", "http://b:2379"]);
+
+    let work = async {
+        let members = client.cluster_versions().await;
+
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[0].endpoint.to_string(), "http://a:2379/");
+        assert!(members[0].result.is_err());
+        assert_eq!(members[1].endpoint.to_string(), "http://b:2379/");
+        assert_eq!(
+            members[1].result.as_ref().unwrap().data.server_version,
+            "2.3.8"
+        );
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn ipv6_endpoint() {
+    let etcd = MockEtcd::new();
+    etcd.respond(
+        StatusCode::OK,
+        r#"{"action":"get","node":{"key":"/foo","value":"bar","modifiedIndex":1}}"#,
+    );
+    let client = mock_client(etcd.connector(), &["http://[::1]:2379"]);
+
+    let work = async {
+        let response = kv::get(&client, "/foo", GetOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(response.data.node.value.unwrap(), "bar");
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    let requests = etcd.requests();
+
+    assert_eq!(requests[0].uri.path(), "/v2/keys/foo");
+    assert_eq!(requests[0].headers[HOST], "[::1]:2379");
+}
+
+#[test]
+fn pinned_endpoint() {
+    let resolved = MockEtcd::new();
+    let pinned = MockEtcd::new();
+    pinned.respond(
+        StatusCode::OK,
+        r#"{"action":"get","node":{"key":"/foo","value":"bar","modifiedIndex":1}}"#,
+    );
+    let address = "127.0.0.1:2379".parse().unwrap();
+    let connector = PinnedConnector::new(
+        resolved.connector().route("http://127.0.0.1:2379", &pinned),
+        &[("http://etcd.invalid:2379", address)],
+    )
+    .unwrap();
+    let hyper = hyper::Client::builder().build(connector);
+    let client = Client::custom(hyper, &["http://etcd.invalid:2379"], None).unwrap();
+
+    let work = async {
+        let response = kv::get(&client, "/foo", GetOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(response.data.node.value.unwrap(), "bar");
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    assert!(resolved.requests().is_empty());
+    assert_eq!(pinned.requests()[0].headers[HOST], "etcd.invalid:2379");
+}
+
+#[test]
+fn response_status_and_headers() {
+    let etcd = MockEtcd::new();
+    let mut headers = HeaderMap::new();
+    headers.insert("X-Etcd-Index", HeaderValue::from_static("7"));
+    etcd.respond_with_headers(
+        StatusCode::CREATED,
+        headers,
+        r#"{"action":"set","node":{"key":"/foo","value":"bar","modifiedIndex":1}}"#,
+    );
+    let client = etcd.client();
+
+    let work = async {
+        let response = kv::set(&client, "/foo", "bar", None).await.unwrap();
+
+        assert_eq!(response.status, StatusCode::CREATED);
+        assert_eq!(response.headers[CONTENT_TYPE], "application/json");
+        assert_eq!(response.headers["x-etcd-index"], "7");
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+/// Constructs a client of the given endpoints whose connections are made by a mock connector.
+fn mock_client(connector: MockConnector, endpoints: &[&str]) -> Client<MockConnector> {
+    let hyper = hyper::Client::builder().build(connector);
+
+    Client::custom(hyper, endpoints, None).unwrap()
 }
@@ -0,0 +1,163 @@
+use std::time::Duration;
+
+use etcd::election;
+use etcd::kv;
+use etcd::mock::MockEtcd;
+use etcd::Error;
+use hyper::{Method, StatusCode};
+use tokio::runtime::Runtime;
+use tokio::time::{delay_for, timeout};
+
+use crate::test::TestClient;
+
+mod test;
+
+#[test]
+fn campaign_and_resign() {
+    let client = TestClient::new();
+
+    let work = async {
+        let leader = election::campaign(&client, "/test/leader", "a", 3)
+            .await
+            .unwrap();
+        assert!(leader.is_leader());
+
+        let response = kv::get(&client, "/test/leader", Default::default())
+            .await
+            .unwrap();
+        assert_eq!(response.data.node.value.unwrap(), "a");
+
+        let lost = leader.lost();
+        leader.resign().await.unwrap();
+        timeout(Duration::from_secs(1), lost).await.unwrap();
+
+        let leader = election::campaign(&client, "/test/leader", "b", 3)
+            .await
+            .unwrap();
+        assert!(leader.is_leader());
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn leadership_survives_ttl() {
+    let client = TestClient::new();
+
+    let work = async {
+        let leader = election::campaign(&client, "/test/leader", "a", 3)
+            .await
+            .unwrap();
+
+        let lost = timeout(Duration::from_secs(5), leader.lost()).await;
+        assert!(lost.is_err());
+        assert!(leader.is_leader());
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn leadership_lost() {
+    let client = TestClient::new();
+
+    let work = async {
+        let leader = election::campaign(&client, "/test/leader", "a", 3)
+            .await
+            .unwrap();
+
+        kv::set(&client, "/test/leader", "b", None).await.unwrap();
+
+        timeout(Duration::from_secs(3), leader.lost())
+            .await
+            .unwrap();
+        assert!(!leader.is_leader());
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn renewal_refreshes_without_notifying_watchers() {
+    let etcd = MockEtcd::new();
+    etcd.respond(
+        StatusCode::CREATED,
+        r#"{"action":"create","node":{"key":"/leader","value":"a","ttl":3,"modifiedIndex":1}}"#,
+    );
+    etcd.respond(
+        StatusCode::OK,
+        r#"{"action":"compareAndSwap","node":{"key":"/leader","value":"a","ttl":3,
+            "modifiedIndex":2}}"#,
+    );
+    let client = etcd.client();
+
+    let work = async {
+        let leader = election::campaign(&client, "/leader", "a", 3)
+            .await
+            .unwrap();
+
+        delay_for(Duration::from_millis(1500)).await;
+
+        assert!(leader.is_leader());
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    let requests = etcd.requests();
+    let form = requests[1].form();
+
+    assert_eq!(requests[1].method, Method::PUT);
+    assert_eq!(form["refresh"], "true");
+    assert_eq!(form["prevValue"], "a");
+    assert!(!form.contains_key("value"));
+}
+
+#[test]
+fn campaign_fails_on_watch_error() {
+    let etcd = MockEtcd::new();
+    etcd.respond(
+        StatusCode::PRECONDITION_FAILED,
+        r#"{"errorCode":105,"message":"Key already exists","cause":"/leader","index":5}"#,
+    );
+    etcd.respond(
+        StatusCode::UNAUTHORIZED,
+        r#"{"errorCode":110,"message":"The request requires user authentication","index":5}"#,
+    );
+    let client = etcd.client();
+
+    let work = async {
+        let errors = election::campaign(&client, "/leader", "a", 3)
+            .await
+            .unwrap_err();
+
+        match errors[0] {
+            Error::Api(ref error) => assert_eq!(error.error_code, 110),
+            ref error => panic!("unexpected error: {:?}", error),
+        }
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    assert_eq!(etcd.requests().len(), 2);
+}
+
+#[test]
+fn campaign_zero_ttl() {
+    let etcd = MockEtcd::new();
+    let client = etcd.client();
+
+    let work = async {
+        let errors = election::campaign(&client, "/leader", "a", 0)
+            .await
+            .unwrap_err();
+
+        match errors[0] {
+            Error::ZeroTtl => {}
+            ref error => panic!("expected ZeroTtl, got {:?}", error),
+        }
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    assert!(etcd.requests().is_empty());
+}
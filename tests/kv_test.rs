@@ -1,11 +1,21 @@
 #![allow(unused_imports)]
+use std::collections::HashMap;
 use std::thread::{sleep, spawn};
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
-use etcd::kv::{self, Action, GetOptions, KeyValueInfo, WatchError, WatchOptions};
+use etcd::kv::{
+    self, Action, AtomicOpOutcome, DirEntry, GetOptions, KeyValueInfo, LeadershipEvent, SetOptions,
+    WatchError, WatchEvent, WatchOptions,
+};
+use etcd::mock::MockEtcd;
 use etcd::{Error, Response};
 use futures::channel::oneshot::channel;
-use futures::future::{join_all, Future, TryFutureExt};
+use futures::future::{join, join_all, Future, TryFutureExt};
+use futures::stream::StreamExt;
+use hyper::StatusCode;
+use serde_json::Value;
+use tokio::runtime::Runtime;
+use tokio::time::{delay_for, timeout};
 
 use crate::test::TestClient;
 
@@ -710,3 +720,1632 @@ fn watch_recursive() {
     child.join().ok().unwrap();
 }
 */
+
+#[test]
+fn get_at_index() {
+    let client = TestClient::new();
+
+    let work = async {
+        let response = kv::set(&client, "/test/foo", "bar", None).await.unwrap();
+        let index = response.data.node.modified_index.unwrap();
+
+        kv::set(&client, "/test/foo", "baz", None).await.unwrap();
+
+        let response = kv::get_at_index(&client, "/test/foo", index).await.unwrap();
+        let node = response.data.node;
+
+        assert_eq!(node.modified_index, Some(index));
+        assert_eq!(node.value.unwrap(), "bar");
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn get_at_index_compacted() {
+    let etcd = MockEtcd::new();
+    etcd.respond(
+        StatusCode::NOT_FOUND,
+        r#"{"errorCode":100,"message":"Key not found","index":5000}"#,
+    );
+    etcd.respond(
+        StatusCode::BAD_REQUEST,
+        r#"{"errorCode":401,"message":"The event in requested index is outdated and cleared",
+            "index":5000}"#,
+    );
+    let client = etcd.client();
+
+    let work = async {
+        let errors = kv::get_at_index(&client, "/foo", 10).await.unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+
+        match errors[0] {
+            Error::Compacted(ref error) => assert_eq!(error.error_code, 401),
+            ref error => panic!("expected Compacted, got {:?}", error),
+        }
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn get_at_index_future_index() {
+    let etcd = MockEtcd::new();
+    etcd.respond(
+        StatusCode::NOT_FOUND,
+        r#"{"errorCode":100,"message":"Key not found","index":5}"#,
+    );
+    let client = etcd.client();
+
+    let work = async {
+        let errors = kv::get_at_index(&client, "/foo", 10).await.unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+
+        match errors[0] {
+            Error::FutureIndex {
+                requested: 10,
+                current: 5,
+            } => {}
+            ref error => panic!("expected FutureIndex, got {:?}", error),
+        }
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    assert_eq!(etcd.requests().len(), 1);
+}
+
+#[test]
+fn campaign() {
+    let client = TestClient::new();
+
+    let work = async {
+        let mut leader = Box::pin(kv::campaign(&client, "/test/leader", "a", 60));
+        let mut follower = Box::pin(kv::campaign(&client, "/test/leader", "b", 60));
+
+        assert_eq!(
+            leader.next().await.unwrap().unwrap(),
+            LeadershipEvent::Acquired
+        );
+
+        let response = kv::get(&client, "/test/leader", GetOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(response.data.node.value.unwrap(), "a");
+
+        let contested = timeout(Duration::from_millis(500), follower.next()).await;
+        assert!(contested.is_err());
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn campaign_ends_on_permanent_error() {
+    let etcd = MockEtcd::new();
+    etcd.respond(
+        StatusCode::UNAUTHORIZED,
+        r#"{"errorCode":110,"message":"The request requires user authentication","index":5}"#,
+    );
+    let client = etcd.client();
+
+    let work = async {
+        let results: Vec<_> = kv::campaign(&client, "/leader", "a", 60).collect().await;
+
+        assert_eq!(results.len(), 1);
+
+        match results[0].as_ref().unwrap_err()[0] {
+            Error::Api(ref error) => assert_eq!(error.error_code, 110),
+            ref error => panic!("unexpected error: {:?}", error),
+        }
+
+        let results: Vec<_> = kv::campaign(&client, "/leader", "a", 0).collect().await;
+
+        assert_eq!(results.len(), 1);
+
+        match results[0].as_ref().unwrap_err()[0] {
+            Error::ZeroTtl => {}
+            ref error => panic!("expected ZeroTtl, got {:?}", error),
+        }
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    assert_eq!(etcd.requests().len(), 1);
+}
+
+#[test]
+fn untracked_cluster_info() {
+    let mut client = TestClient::new();
+    client.set_track_cluster_info(false);
+
+    let work = async {
+        let response = kv::set(&client, "/test/foo", "bar", None).await.unwrap();
+
+        assert_eq!(response.cluster_info.etcd_index, None);
+        assert_eq!(response.cluster_info.cluster_id, None);
+        assert!(response.data.node.modified_index.is_some());
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn claim_oldest() {
+    let client = TestClient::new();
+
+    let work = async {
+        kv::create_in_order(&client, "/test/queue", "first", None)
+            .await
+            .unwrap();
+        kv::create_in_order(&client, "/test/queue", "second", None)
+            .await
+            .unwrap();
+
+        let (_, value) = kv::claim_oldest(&client, "/test/queue", 60)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(value, "first");
+
+        let (_, value) = kv::claim_oldest(&client, "/test/queue", 60)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(value, "second");
+
+        let claimed = kv::claim_oldest(&client, "/test/queue", 60).await.unwrap();
+        assert!(claimed.is_none());
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn claim_oldest_skips_claimed_items() {
+    let client = TestClient::new();
+
+    let work = async {
+        let (key, _) = kv::enqueue(&client, "/test/queue", "first", None)
+            .await
+            .unwrap();
+        kv::enqueue(&client, "/test/queue", "second", None)
+            .await
+            .unwrap();
+
+        let name = key.rsplit('/').next().unwrap();
+        kv::create(
+            &client,
+            &format!("/test/queue/_claimed/{}", name),
+            "first",
+            None,
+        )
+        .await
+        .unwrap();
+
+        let (_, value) = kv::claim_oldest(&client, "/test/queue", 60)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(value, "second");
+
+        let claimed = kv::claim_oldest(&client, "/test/queue", 60).await.unwrap();
+        assert!(claimed.is_none());
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn changed_since() {
+    let client = TestClient::new();
+
+    let work = async {
+        kv::set(&client, "/test/dir/old", "a", None).await.unwrap();
+        let response = kv::set(&client, "/test/dir/unchanged", "b", None)
+            .await
+            .unwrap();
+        let index = response.data.node.modified_index.unwrap();
+
+        kv::set(&client, "/test/dir/old", "c", None).await.unwrap();
+        kv::set(&client, "/test/dir/nested/new", "d", None)
+            .await
+            .unwrap();
+
+        let mut keys: Vec<String> = kv::changed_since(&client, "/test/dir", index)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|node| node.dir != Some(true))
+            .map(|node| node.key.unwrap())
+            .collect();
+        keys.sort();
+
+        assert_eq!(keys, vec!["/test/dir/nested/new", "/test/dir/old"]);
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn effective_ttl() {
+    let client = TestClient::new();
+
+    let work = async {
+        let response = kv::set(&client, "/test/foo", "bar", Some(60))
+            .await
+            .unwrap();
+        let ttl = response.data.effective_ttl().unwrap();
+
+        assert!(ttl <= Duration::from_secs(60));
+        assert!(ttl >= Duration::from_secs(55));
+
+        let response = kv::create(&client, "/test/baz", "qux", Some(60))
+            .await
+            .unwrap();
+        let expiration = response.data.node.expiration_time().unwrap();
+        let remaining = expiration.duration_since(SystemTime::now()).unwrap();
+
+        assert!(remaining <= Duration::from_secs(60));
+        assert!(remaining >= Duration::from_secs(55));
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn copy_subtree() {
+    let client = TestClient::new();
+
+    let work = async {
+        kv::set(&client, "/test/current/a", "1", None)
+            .await
+            .unwrap();
+        kv::set(&client, "/test/current/nested/b", "2", None)
+            .await
+            .unwrap();
+        kv::create_dir(&client, "/test/current/empty", None)
+            .await
+            .unwrap();
+
+        let copied = kv::copy_subtree(&client, "/test/current", "/test/candidate", false)
+            .await
+            .unwrap();
+        assert_eq!(copied, 2);
+
+        let response = kv::get(&client, "/test/candidate/nested/b", GetOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(response.data.node.value.unwrap(), "2");
+
+        let response = kv::get(&client, "/test/candidate/empty", GetOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(response.data.node.dir, Some(true));
+
+        let errors = kv::copy_subtree(&client, "/test/current", "/test/candidate", false)
+            .await
+            .unwrap_err();
+        match errors[0] {
+            Error::DestinationNotEmpty => {}
+            ref error => panic!("expected DestinationNotEmpty, got {:?}", error),
+        }
+
+        let copied = kv::copy_subtree(&client, "/test/current", "/test/candidate", true)
+            .await
+            .unwrap();
+        assert_eq!(copied, 2);
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn drain_in_order() {
+    let client = TestClient::new();
+
+    let work = async {
+        kv::create_in_order(&client, "/test/queue", "first", None)
+            .await
+            .unwrap();
+        kv::create_in_order(&client, "/test/queue", "second", None)
+            .await
+            .unwrap();
+
+        let values: Vec<String> = kv::drain_in_order(&client, "/test/queue", 60, false)
+            .map(|item| item.unwrap().1)
+            .collect()
+            .await;
+
+        assert_eq!(values, vec!["first", "second"]);
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn drain_in_order_watches_from_empty_listing() {
+    let etcd = MockEtcd::new();
+    etcd.respond(
+        StatusCode::NOT_FOUND,
+        r#"{"errorCode":100,"message":"Key not found","cause":"/queue","index":5}"#,
+    );
+    etcd.respond(
+        StatusCode::OK,
+        r#"{"action":"create","node":{"key":"/queue/6","value":"job","modifiedIndex":6}}"#,
+    );
+    etcd.respond(
+        StatusCode::OK,
+        r#"{"action":"get","node":{"key":"/queue","dir":true,"nodes":[
+            {"key":"/queue/6","value":"job","modifiedIndex":6}]}}"#,
+    );
+    etcd.respond(
+        StatusCode::CREATED,
+        r#"{"action":"create","node":{"key":"/queue/_claimed/6","value":"job","modifiedIndex":7}}"#,
+    );
+    etcd.respond(
+        StatusCode::OK,
+        r#"{"action":"compareAndDelete","node":{"key":"/queue/6","modifiedIndex":8}}"#,
+    );
+    let client = etcd.client();
+
+    let work = async {
+        let items: Vec<_> = kv::drain_in_order(&client, "/queue", 60, true)
+            .take(1)
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        assert_eq!(items, vec![("/queue/6".to_owned(), "job".to_owned())]);
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    // An item enqueued right after the empty listing, at index 6, must be seen by the watch.
+    let requests = etcd.requests();
+
+    assert_eq!(requests.len(), 5);
+    assert_eq!(requests[1].query()["wait"], "true");
+    assert_eq!(requests[1].query()["waitIndex"], "6");
+}
+
+#[test]
+fn copy_subtree_keeps_dir_ttls() {
+    let etcd = MockEtcd::new();
+    etcd.respond(
+        StatusCode::OK,
+        r#"{"action":"get","node":{"key":"/current","dir":true,"nodes":[
+            {"key":"/current/db","dir":true,"ttl":30,"nodes":[
+                {"key":"/current/db/host","value":"db.local"}]}]}}"#,
+    );
+    etcd.respond(
+        StatusCode::CREATED,
+        r#"{"action":"set","node":{"key":"/candidate/db","dir":true,"ttl":30}}"#,
+    );
+    etcd.respond(
+        StatusCode::CREATED,
+        r#"{"action":"set","node":{"key":"/candidate/db/host","value":"db.local"}}"#,
+    );
+    let client = etcd.client();
+
+    let work = async {
+        let copied = kv::copy_subtree(&client, "/current", "/candidate", true)
+            .await
+            .unwrap();
+
+        assert_eq!(copied, 1);
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    // The non-empty directory is created with its TTL before its child is copied.
+    let requests = etcd.requests();
+
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[1].uri.path(), "/v2/keys/candidate/db");
+    assert_eq!(requests[1].form()["dir"], "true");
+    assert_eq!(requests[1].form()["ttl"], "30");
+    assert_eq!(requests[2].uri.path(), "/v2/keys/candidate/db/host");
+}
+
+#[test]
+fn delete_if() {
+    let client = TestClient::new();
+
+    let work = async {
+        let response = kv::set(&client, "/test/foo", "bar", None).await.unwrap();
+        let stale_guard = response.data.delete_guard().unwrap();
+
+        let response = kv::set(&client, "/test/foo", "baz", None).await.unwrap();
+        let guard = response.data.delete_guard().unwrap();
+
+        assert!(kv::delete_if(&client, "/test/foo", stale_guard)
+            .await
+            .is_err());
+
+        let response = kv::delete_if(&client, "/test/foo", guard).await.unwrap();
+        assert_eq!(response.data.action, Action::CompareAndDelete);
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn watch_stream() {
+    let client = TestClient::new();
+
+    let work = async {
+        let response = kv::set(&client, "/test/foo", "0", None).await.unwrap();
+        let index = response.data.node.modified_index.unwrap();
+
+        kv::set(&client, "/test/foo", "1", None).await.unwrap();
+        kv::set(&client, "/test/other", "x", None).await.unwrap();
+        kv::set(&client, "/test/foo", "2", None).await.unwrap();
+
+        let options = WatchOptions {
+            index: Some(index + 1),
+            ..Default::default()
+        };
+        let events: Vec<WatchEvent> = kv::watch_stream(&client, "/test/foo", options)
+            .take(2)
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(events[0].info.node.value.as_deref(), Some("1"));
+        assert_eq!(events[0].index_gap, 1);
+        assert_eq!(events[1].info.node.value.as_deref(), Some("2"));
+        assert_eq!(events[1].index_gap, 2);
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn require_leader() {
+    let mut client = TestClient::new();
+    client.set_require_leader(true);
+
+    let work = async {
+        kv::set(&client, "/test/foo", "bar", None).await.unwrap();
+        kv::delete(&client, "/test/foo", false).await.unwrap();
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn get_fallback_to_stale() {
+    let client = TestClient::new();
+
+    let work = async {
+        kv::set(&client, "/test/foo", "bar", None).await.unwrap();
+
+        let options = GetOptions {
+            strong_consistency: true,
+            fallback_to_stale: true,
+            ..Default::default()
+        };
+        let response = kv::get(&client, "/test/foo", options).await.unwrap();
+
+        assert_eq!(response.data.node.value.unwrap(), "bar");
+        assert!(!response.is_stale);
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn cancel_all_watches() {
+    let mut client = TestClient::new();
+    client.set_track_watches(true);
+
+    let work = async {
+        let mut watch = Box::pin(kv::watch_stream(
+            &client,
+            "/test/foo",
+            WatchOptions::default(),
+        ));
+        assert_eq!(client.active_watches(), 1);
+
+        client.cancel_all_watches();
+
+        assert!(watch.next().await.is_none());
+        assert_eq!(client.active_watches(), 0);
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn read_your_writes() {
+    let mut client = TestClient::new();
+    client.set_read_your_writes(true);
+
+    let work = async {
+        let set = kv::set(&client, "/test/foo", "bar", None).await.unwrap();
+        let written_index = set.cluster_info.etcd_index.unwrap();
+
+        let get = kv::get(&client, "/test/foo", GetOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(get.data.node.value.unwrap(), "bar");
+        assert!(get.cluster_info.etcd_index.unwrap() >= written_index);
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn reset_state() {
+    let mut client = TestClient::new();
+    client.set_read_your_writes(true);
+    client.set_require_leader(true);
+
+    let work = async {
+        kv::set(&client, "/test/foo", "bar", None).await.unwrap();
+
+        client.reset_state();
+
+        let response = kv::get(&client, "/test/foo", GetOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(response.data.node.value.unwrap(), "bar");
+
+        kv::set(&client, "/test/foo", "baz", None).await.unwrap();
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn ttl_bounds() {
+    let mut client = TestClient::new();
+    client.set_ttl_bounds(Some(10), Some(300));
+    client.set_allow_no_ttl(false);
+
+    let work = async {
+        for ttl in &[None, Some(0), Some(5), Some(301)] {
+            match kv::set(&client, "/test/foo", "bar", *ttl).await {
+                Err(ref errors) => match errors[0] {
+                    Error::TtlOutOfBounds {
+                        min,
+                        max,
+                        requested,
+                    } => {
+                        assert_eq!(min, Some(10));
+                        assert_eq!(max, Some(300));
+                        assert_eq!(requested, ttl.filter(|&ttl| ttl != 0));
+                    }
+                    ref error => panic!("unexpected error: {:?}", error),
+                },
+                Ok(_) => panic!("expected TTL of {:?} to be rejected", ttl),
+            }
+        }
+
+        kv::set(&client, "/test/foo", "bar", Some(60))
+            .await
+            .unwrap();
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn watch_stream_resumes_after_index_cleared() {
+    let client = TestClient::new();
+
+    let work = async {
+        let response = kv::set(&client, "/test/foo", "0", None).await.unwrap();
+        let index = response.data.node.modified_index.unwrap();
+
+        // etcd only keeps the last 1000 change events.
+        for i in 0..1000 {
+            kv::set(&client, "/test/other", &i.to_string(), None)
+                .await
+                .unwrap();
+        }
+
+        let options = WatchOptions {
+            index: Some(index),
+            timeout: Some(Duration::from_secs(5)),
+            ..Default::default()
+        };
+        let mut events = Box::pin(kv::watch_stream(&client, "/test/foo", options));
+
+        let watch = events.next();
+        let write = async {
+            delay_for(Duration::from_millis(100)).await;
+            kv::set(&client, "/test/foo", "1", None).await.unwrap();
+        };
+        let (event, _) = join(watch, write).await;
+
+        let event = event.unwrap().unwrap();
+        assert_eq!(event.info.node.value.as_deref(), Some("1"));
+        assert!(event.index_gap > 1);
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn typed_values() {
+    let client = TestClient::new();
+
+    let work = async {
+        kv::set_typed(&client, "/test/foo", &vec![1, 2, 3], None)
+            .await
+            .unwrap();
+
+        let response = kv::get_typed::<_, Vec<u32>>(&client, "/test/foo", GetOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(response.data, vec![1, 2, 3]);
+
+        match kv::get_typed::<_, String>(&client, "/test/foo", GetOptions::default()).await {
+            Err(ref errors) => match errors[0] {
+                Error::Serialization(_) => {}
+                ref error => panic!("unexpected error: {:?}", error),
+            },
+            Ok(_) => panic!("expected the value not to deserialize"),
+        }
+
+        kv::create_dir(&client, "/test/dir", None).await.unwrap();
+
+        match kv::get_typed::<_, String>(&client, "/test/dir", GetOptions::default()).await {
+            Err(ref errors) => match errors[0] {
+                Error::MissingValue => {}
+                ref error => panic!("unexpected error: {:?}", error),
+            },
+            Ok(_) => panic!("expected a directory to have no value"),
+        }
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn refresh() {
+    let client = TestClient::new();
+
+    let work = async {
+        kv::set(&client, "/test/foo", "bar", Some(10))
+            .await
+            .unwrap();
+
+        let response = kv::refresh(&client, "/test/foo", 100).await.unwrap();
+        let node = response.data.node;
+
+        assert_eq!(response.data.action, Action::Update);
+        assert_eq!(node.value.unwrap(), "bar");
+        assert_eq!(node.ttl.unwrap(), 100);
+
+        match kv::refresh(&client, "/test/missing", 100).await {
+            Err(ref errors) => match errors[0] {
+                Error::Api(ref error) => assert_eq!(error.error_code, 100),
+                ref error => panic!("unexpected error: {:?}", error),
+            },
+            Ok(_) => panic!("expected refreshing a missing key to fail"),
+        }
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn exists() {
+    let client = TestClient::new();
+
+    let work = async {
+        let response = kv::exists(&client, "/test/foo").await.unwrap();
+        assert!(!response.data);
+        assert!(response.cluster_info.etcd_index.is_some());
+
+        kv::set(&client, "/test/foo", "bar", None).await.unwrap();
+
+        let response = kv::exists(&client, "/test/foo").await.unwrap();
+        assert!(response.data);
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn compare_and_swap_typed() {
+    let client = TestClient::new();
+
+    let work = async {
+        kv::set(&client, "/test/foo", "{ \"a\": 1,  \"b\": [1, 2] }", None)
+            .await
+            .unwrap();
+
+        let current: HashMap<String, Value> =
+            serde_json::from_str("{\"b\":[1,2],\"a\":1}").unwrap();
+        let mut new = current.clone();
+        new.insert("c".to_owned(), Value::Bool(true));
+
+        kv::compare_and_swap_typed(&client, "/test/foo", &new, None, &current)
+            .await
+            .unwrap();
+
+        match kv::compare_and_swap_typed(&client, "/test/foo", &new, None, &current).await {
+            Err(ref errors) => match errors[0] {
+                Error::Api(ref error) => assert_eq!(error.error_code, 101),
+                ref error => panic!("unexpected error: {:?}", error),
+            },
+            Ok(_) => panic!("expected the comparison to fail"),
+        }
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn get_many() {
+    let client = TestClient::new();
+
+    let work = async {
+        kv::set(&client, "/test/foo", "one", None).await.unwrap();
+        kv::set(&client, "/test/bar", "two", None).await.unwrap();
+
+        let keys = ["/test/foo", "/test/missing", "/test/bar"];
+        let results = kv::get_many(&client, &keys, GetOptions::default(), 2).await;
+
+        assert_eq!(results.len(), 3);
+
+        let (ref key, ref result) = results[0];
+        assert_eq!(key, "/test/foo");
+        assert_eq!(
+            result.as_ref().unwrap().data.node.value.as_ref().unwrap(),
+            "one"
+        );
+
+        let (ref key, ref result) = results[1];
+        assert_eq!(key, "/test/missing");
+        assert!(result.is_err());
+
+        let (ref key, ref result) = results[2];
+        assert_eq!(key, "/test/bar");
+        assert_eq!(
+            result.as_ref().unwrap().data.node.value.as_ref().unwrap(),
+            "two"
+        );
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn get_or_create() {
+    let client = TestClient::new();
+
+    let work = async {
+        let (response, created) = kv::get_or_create(&client, "/test/foo", "default", None)
+            .await
+            .unwrap();
+
+        assert!(created);
+        assert_eq!(response.data.action, Action::Create);
+        assert_eq!(response.data.node.value.unwrap(), "default");
+
+        kv::set(&client, "/test/foo", "bar", None).await.unwrap();
+
+        let (response, created) = kv::get_or_create(&client, "/test/foo", "default", None)
+            .await
+            .unwrap();
+
+        assert!(!created);
+        assert_eq!(response.data.action, Action::Get);
+        assert_eq!(response.data.node.value.unwrap(), "bar");
+
+        // "/test/foo" is not a directory, so this fails with something other than 105.
+        assert!(kv::get_or_create(&client, "/test/foo/bar", "default", None)
+            .await
+            .is_err());
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn delete_if_value() {
+    let client = TestClient::new();
+
+    let work = async {
+        kv::set(&client, "/test/foo", "bar", None).await.unwrap();
+
+        let response = kv::delete_if_value(&client, "/test/foo", "baz")
+            .await
+            .unwrap();
+
+        assert!(!response.data);
+        assert!(response.cluster_info.etcd_index.is_some());
+
+        let response = kv::delete_if_value(&client, "/test/foo", "bar")
+            .await
+            .unwrap();
+
+        assert!(response.data);
+        assert!(kv::delete_if_value(&client, "/test/foo", "bar")
+            .await
+            .is_err());
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn set_if_absent() {
+    let client = TestClient::new();
+
+    let work = async {
+        let response = kv::set_if_absent(&client, "/test/foo", "bar", None)
+            .await
+            .unwrap();
+
+        assert!(response.data);
+        assert!(response.cluster_info.etcd_index.is_some());
+
+        let response = kv::set_if_absent(&client, "/test/foo", "baz", None)
+            .await
+            .unwrap();
+
+        assert!(!response.data);
+        assert!(response.cluster_info.etcd_index.is_some());
+
+        let response = kv::get(&client, "/test/foo", GetOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(response.data.node.value.unwrap(), "bar");
+        assert!(kv::set_if_absent(&client, "/test/foo/bar", "baz", None)
+            .await
+            .is_err());
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn watch_outdated_index() {
+    let client = TestClient::new();
+
+    let work = async {
+        let response = kv::set(&client, "/test/foo", "0", None).await.unwrap();
+        let index = response.data.node.modified_index.unwrap();
+
+        // etcd only keeps the last 1000 change events.
+        for i in 0..1000 {
+            kv::set(&client, "/test/other", &i.to_string(), None)
+                .await
+                .unwrap();
+        }
+
+        let options = WatchOptions {
+            index: Some(index),
+            ..Default::default()
+        };
+
+        match kv::watch(&client, "/test/foo", options).await {
+            Err(WatchError::Outdated { current_index }) => assert!(current_index >= index + 1000),
+            Err(error) => panic!("unexpected error: {:?}", error),
+            Ok(_) => panic!("expected the index to be outdated"),
+        }
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn get_paged() {
+    let client = TestClient::new();
+
+    let work = async {
+        for key in &[
+            "/test/dir/a",
+            "/test/dir/b/c",
+            "/test/dir/b/d",
+            "/test/dir/e",
+        ] {
+            kv::set(&client, key, "value", None).await.unwrap();
+        }
+        kv::create_dir(&client, "/test/dir/f", None).await.unwrap();
+
+        let keys: Vec<String> = kv::get_paged(&client, "/test/dir", 3)
+            .map(|node| node.unwrap().key.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(
+            keys,
+            vec![
+                "/test/dir/a",
+                "/test/dir/b/c",
+                "/test/dir/b/d",
+                "/test/dir/e"
+            ]
+        );
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn get_paged_reads_lazily() {
+    let etcd = MockEtcd::new();
+    etcd.respond(
+        StatusCode::OK,
+        r#"{"action":"get","node":{"key":"/dir","dir":true,"nodes":[
+            {"key":"/dir/a","value":"1"},
+            {"key":"/dir/b","dir":true}]}}"#,
+    );
+    let client = etcd.client();
+
+    let work = async {
+        let keys: Vec<String> = kv::get_paged(&client, "/dir", 1)
+            .take(1)
+            .map(|node| node.unwrap().key.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(keys, vec!["/dir/a"]);
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    // The subdirectory is not read until the stream reaches it.
+    assert_eq!(etcd.requests().len(), 1);
+}
+
+#[test]
+fn get_sorted() {
+    let client = TestClient::new();
+
+    let work = async {
+        for key in &["/test/c", "/test/a/x", "/test/b", "/test/aa", "/test/d/y"] {
+            kv::set(&client, key, "value", None).await.unwrap();
+        }
+
+        let options = GetOptions {
+            sort: true,
+            ..Default::default()
+        };
+        let response = kv::get(&client, "/test", options).await.unwrap();
+        let keys: Vec<String> = response
+            .data
+            .node
+            .nodes
+            .unwrap()
+            .into_iter()
+            .map(|node| node.key.unwrap())
+            .collect();
+
+        assert_eq!(
+            keys,
+            vec!["/test/a", "/test/aa", "/test/b", "/test/c", "/test/d"]
+        );
+
+        let options = GetOptions {
+            recursive: true,
+            sort: true,
+            ..Default::default()
+        };
+        let response = kv::get(&client, "/test", options).await.unwrap();
+        let nodes = response.data.node.nodes.unwrap();
+
+        assert_eq!(
+            nodes[0].nodes.as_ref().unwrap()[0].key.as_deref(),
+            Some("/test/a/x")
+        );
+        assert_eq!(
+            nodes[4].nodes.as_ref().unwrap()[0].key.as_deref(),
+            Some("/test/d/y")
+        );
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn atomic_op() {
+    let client = TestClient::new();
+
+    let work = async {
+        let response = kv::AtomicOp::set("/test/foo", "one")
+            .if_absent()
+            .execute(&client)
+            .await
+            .unwrap();
+
+        let index = match response.data {
+            AtomicOpOutcome::Applied(info) => info.node.modified_index.unwrap(),
+            AtomicOpOutcome::GuardFailed => panic!("expected the key to be absent"),
+        };
+
+        let op = kv::AtomicOp::set("/test/foo", "two").if_value("zero");
+        assert_eq!(
+            op.execute(&client).await.unwrap().data,
+            AtomicOpOutcome::GuardFailed
+        );
+
+        let op = kv::AtomicOp::set("/test/foo", "two").if_absent();
+        assert_eq!(
+            op.execute(&client).await.unwrap().data,
+            AtomicOpOutcome::GuardFailed
+        );
+
+        let response = kv::AtomicOp::set("/test/foo", "two")
+            .if_value("one")
+            .if_modified_index(index)
+            .ttl(60)
+            .execute(&client)
+            .await
+            .unwrap();
+
+        match response.data {
+            AtomicOpOutcome::Applied(info) => {
+                assert_eq!(info.node.value.as_deref(), Some("two"));
+                assert!(info.node.ttl.is_some());
+            }
+            AtomicOpOutcome::GuardFailed => panic!("expected the value to match"),
+        }
+
+        let op = kv::AtomicOp::delete("/test/foo").if_modified_index(index);
+        assert_eq!(
+            op.execute(&client).await.unwrap().data,
+            AtomicOpOutcome::GuardFailed
+        );
+
+        let op = kv::AtomicOp::delete("/test/foo").if_value("two");
+        assert!(op.execute(&client).await.unwrap().data != AtomicOpOutcome::GuardFailed);
+
+        let op = kv::AtomicOp::delete("/test/foo").if_value("two");
+        assert_eq!(
+            op.execute(&client).await.unwrap().data,
+            AtomicOpOutcome::GuardFailed
+        );
+
+        match kv::AtomicOp::delete("/test/foo").execute(&client).await {
+            Err(ref errors) => match errors[0] {
+                Error::InvalidConditions => {}
+                ref error => panic!("unexpected error: {:?}", error),
+            },
+            Ok(_) => panic!("expected the conditions to be invalid"),
+        }
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn changes_since() {
+    let client = TestClient::new();
+
+    let work = async {
+        let response = kv::set(&client, "/test/foo", "one", None).await.unwrap();
+        let index = response.data.node.modified_index.unwrap();
+
+        kv::set(&client, "/test/foo", "two", None).await.unwrap();
+        kv::set(&client, "/test/other", "ignored", None)
+            .await
+            .unwrap();
+        kv::set(&client, "/test/foo", "three", None).await.unwrap();
+
+        let mut changes = Box::pin(kv::changes_since(&client, "/test/foo", index, false));
+        let mut values = Vec::new();
+
+        for _ in 0..3 {
+            let response = changes.next().await.unwrap().unwrap();
+            values.push(response.data.node.value.unwrap());
+        }
+
+        assert_eq!(values, vec!["one", "two", "three"]);
+
+        let next = changes.next();
+        let write = async {
+            delay_for(Duration::from_millis(100)).await;
+            kv::set(&client, "/test/foo", "four", None).await.unwrap();
+        };
+        let (response, _) = join(next, write).await;
+
+        assert_eq!(response.unwrap().unwrap().data.node.value.unwrap(), "four");
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn ttl_remaining() {
+    let client = TestClient::new();
+
+    let work = async {
+        kv::set(&client, "/test/foo", "bar", Some(100))
+            .await
+            .unwrap();
+        kv::set(&client, "/test/bar", "baz", None).await.unwrap();
+
+        let remaining = kv::ttl_remaining(&client, "/test/foo").await.unwrap().data;
+        assert!(remaining.unwrap() > Duration::from_secs(90));
+        assert!(remaining.unwrap() <= Duration::from_secs(100));
+
+        let response = kv::ttl_remaining(&client, "/test/bar").await.unwrap();
+        assert_eq!(response.data, None);
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn list_dir() {
+    let client = TestClient::new();
+
+    let work = async {
+        kv::set(&client, "/test/dir/b", "bar", None).await.unwrap();
+        kv::set(&client, "/test/dir/a/c", "baz", None)
+            .await
+            .unwrap();
+
+        let response = kv::list_dir(&client, "/test/dir").await.unwrap();
+
+        assert_eq!(
+            response.data,
+            vec![
+                DirEntry {
+                    key: "/test/dir/a".to_owned(),
+                    is_dir: true,
+                    value: None,
+                },
+                DirEntry {
+                    key: "/test/dir/b".to_owned(),
+                    is_dir: false,
+                    value: Some("bar".to_owned()),
+                },
+            ]
+        );
+
+        match kv::list_dir(&client, "/test/dir/b").await {
+            Err(ref errors) => match errors[0] {
+                Error::NotADirectory => {}
+                ref error => panic!("unexpected error: {:?}", error),
+            },
+            Ok(_) => panic!("expected listing a key-value pair to fail"),
+        }
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn wait_for_value() {
+    let client = TestClient::new();
+
+    let work = async {
+        let response = kv::wait_for_value(&client, "/test/foo", "ready", None);
+        let writes = async {
+            delay_for(Duration::from_millis(100)).await;
+
+            for value in &["starting", "warming up", "ready"] {
+                kv::set(&client, "/test/foo", value, None).await.unwrap();
+            }
+        };
+
+        let (response, _) = join(response, writes).await;
+        let response = response.unwrap();
+        assert_eq!(response.data.node.value.unwrap(), "ready");
+
+        let response = kv::wait_for_value(&client, "/test/foo", "ready", None)
+            .await
+            .unwrap();
+        assert_eq!(response.data.action, Action::Get);
+
+        let timeout = Some(Duration::from_millis(100));
+        match kv::wait_for_value(&client, "/test/foo", "done", timeout).await {
+            Err(WatchError::Timeout) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn compare_against_empty_value() {
+    let client = TestClient::new();
+
+    let work = async {
+        kv::create(&client, "/test/foo", "", None).await.unwrap();
+
+        let response = kv::compare_and_swap(&client, "/test/foo", "bar", None, Some(""), None)
+            .await
+            .unwrap();
+        assert_eq!(response.data.action, Action::CompareAndSwap);
+        assert_eq!(response.data.node.value.unwrap(), "bar");
+
+        match kv::compare_and_swap(&client, "/test/foo", "baz", None, Some(""), None).await {
+            Err(ref errors) => match errors[0] {
+                Error::Api(ref error) => assert_eq!(error.error_code, 101),
+                ref error => panic!("unexpected error: {:?}", error),
+            },
+            Ok(_) => panic!("expected comparing a non-empty value against \"\" to fail"),
+        }
+
+        let response = kv::set(&client, "/test/foo", "", None).await.unwrap();
+        let index = response.data.node.modified_index;
+
+        match kv::compare_and_delete(&client, "/test/foo", Some(""), Some(index.unwrap() - 1)).await
+        {
+            Err(ref errors) => match errors[0] {
+                Error::Api(ref error) => assert_eq!(error.error_code, 101),
+                ref error => panic!("unexpected error: {:?}", error),
+            },
+            Ok(_) => panic!("expected comparing an outdated modified index to fail"),
+        }
+
+        let response = kv::compare_and_delete(&client, "/test/foo", Some(""), index)
+            .await
+            .unwrap();
+        assert_eq!(response.data.action, Action::CompareAndDelete);
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn get_raw() {
+    let client = TestClient::new();
+
+    let work = async {
+        kv::create(&client, "/test/foo", "bar", Some(60))
+            .await
+            .unwrap();
+
+        let response = kv::get_raw(&client, "/test/foo", GetOptions::default())
+            .await
+            .unwrap();
+        let data = response.data;
+
+        assert_eq!(data["action"], "get");
+        assert_eq!(data["node"]["key"], "/test/foo");
+        assert_eq!(data["node"]["value"], "bar");
+        assert!(data["node"]["ttl"].is_u64());
+        assert!(data["node"]["expiration"].is_string());
+        assert!(response.cluster_info.etcd_index.is_some());
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn enqueue() {
+    let client = TestClient::new();
+
+    let work = async {
+        let (first, response) = kv::enqueue(&client, "/test/queue", "a", None)
+            .await
+            .unwrap();
+        assert_eq!(response.data.node.key.unwrap(), first);
+        assert!(first.starts_with("/test/queue/"));
+
+        let (second, _) = kv::enqueue(&client, "/test/queue", "b", None)
+            .await
+            .unwrap();
+        assert!(first < second);
+
+        let response = kv::get(&client, &first, GetOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(response.data.node.value.unwrap(), "a");
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn set_and_get_bytes() {
+    let client = TestClient::new();
+
+    let work = async {
+        let data = [0u8, 159, 146, 150, 255];
+
+        kv::set_bytes(&client, "/test/foo", &data, None)
+            .await
+            .unwrap();
+
+        let response = kv::get(&client, "/test/foo", GetOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(response.data.node.value.unwrap(), "AJ+Slv8=");
+
+        let response = kv::get_bytes(&client, "/test/foo").await.unwrap();
+        assert_eq!(response.data, data);
+
+        kv::set(&client, "/test/foo", "not base64!", None)
+            .await
+            .unwrap();
+
+        match kv::get_bytes(&client, "/test/foo").await {
+            Err(ref errors) => match errors[0] {
+                Error::InvalidBase64(_) => {}
+                ref error => panic!("unexpected error: {:?}", error),
+            },
+            Ok(_) => panic!("expected decoding an invalid value to fail"),
+        }
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn keep_alive() {
+    let client = TestClient::new();
+
+    let work = async {
+        let session = kv::keep_alive(&client, "/test/session", "a", 2)
+            .await
+            .unwrap();
+        assert_eq!(session.key(), "/test/session");
+
+        delay_for(Duration::from_secs(3)).await;
+
+        assert!(session.is_alive());
+        let response = kv::get(&client, "/test/session", GetOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(response.data.node.value.unwrap(), "a");
+
+        let lost = session.lost();
+        kv::delete(&client, "/test/session", false).await.unwrap();
+        timeout(Duration::from_secs(3), lost).await.unwrap();
+        assert!(!session.is_alive());
+
+        let session = kv::keep_alive(&client, "/test/session", "b", 2)
+            .await
+            .unwrap();
+        session.cancel();
+
+        delay_for(Duration::from_secs(3)).await;
+
+        assert!(kv::get(&client, "/test/session", GetOptions::default())
+            .await
+            .is_err());
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn keep_alive_zero_ttl() {
+    let etcd = MockEtcd::new();
+    let client = etcd.client();
+
+    let work = async {
+        let errors = kv::keep_alive(&client, "/session", "a", 0)
+            .await
+            .unwrap_err();
+
+        match errors[0] {
+            Error::ZeroTtl => {}
+            ref error => panic!("expected ZeroTtl, got {:?}", error),
+        }
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    assert!(etcd.requests().is_empty());
+}
+
+#[test]
+fn increment() {
+    let client = TestClient::new();
+
+    let work = async {
+        let response = kv::increment(&client, "/test/counter", 5).await.unwrap();
+        assert_eq!(response.data, 5);
+
+        let response = kv::increment(&client, "/test/counter", -7).await.unwrap();
+        assert_eq!(response.data, -2);
+
+        let increments = (0..5).map(|_| kv::increment(&client, "/test/counter", 1));
+        for result in join_all(increments).await {
+            result.unwrap();
+        }
+
+        let response = kv::get(&client, "/test/counter", GetOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(response.data.node.value.unwrap(), "3");
+
+        kv::set(&client, "/test/counter", "three", None)
+            .await
+            .unwrap();
+
+        match kv::increment(&client, "/test/counter", 1).await {
+            Err(ref errors) => match errors[0] {
+                Error::InvalidCounter(ref value) => assert_eq!(value, "three"),
+                ref error => panic!("unexpected error: {:?}", error),
+            },
+            Ok(_) => panic!("expected incrementing a non-numeric value to fail"),
+        }
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn hidden_keys() {
+    let client = TestClient::new();
+
+    let work = async {
+        kv::set(&client, "/test/dir/visible", "foo", None)
+            .await
+            .unwrap();
+
+        let response = kv::set_hidden(&client, "/test/dir/lock", "bar", None)
+            .await
+            .unwrap();
+        let node = response.data.node;
+
+        assert_eq!(node.key.as_deref(), Some("/test/dir/_lock"));
+        assert!(node.is_hidden());
+
+        let response = kv::list_visible(&client, "/test/dir").await.unwrap();
+
+        assert_eq!(
+            response.data,
+            vec![DirEntry {
+                key: "/test/dir/visible".to_owned(),
+                is_dir: false,
+                value: Some("foo".to_owned()),
+            }]
+        );
+
+        let response = kv::get(&client, "/test/dir/_lock", GetOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(response.data.node.value.as_deref(), Some("bar"));
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn compare_and_swap_both_conditions() {
+    let client = TestClient::new();
+
+    let work = async {
+        let response = kv::create(&client, "/test/foo", "bar", None).await.unwrap();
+        let index = response.data.node.modified_index.unwrap();
+
+        for (value, modified_index) in vec![("bar", index + 1), ("baz", index)] {
+            let result = kv::compare_and_swap(
+                &client,
+                "/test/foo",
+                "qux",
+                None,
+                Some(value),
+                Some(modified_index),
+            )
+            .await;
+
+            match result {
+                Err(ref errors) => match errors[0] {
+                    Error::Api(ref error) => assert_eq!(error.error_code, 101),
+                    ref error => panic!("unexpected error: {:?}", error),
+                },
+                Ok(_) => panic!("expected a compare-and-swap with one mismatch to fail"),
+            }
+        }
+
+        let response = kv::get(&client, "/test/foo", GetOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(response.data.node.value.unwrap(), "bar");
+
+        let response =
+            kv::compare_and_swap(&client, "/test/foo", "qux", None, Some("bar"), Some(index))
+                .await
+                .unwrap();
+        assert_eq!(response.data.action, Action::CompareAndSwap);
+        assert_eq!(response.data.node.value.unwrap(), "qux");
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn watch_channel() {
+    let client = TestClient::new();
+
+    let work = async {
+        let response = kv::set(&client, "/test/foo", "0", None).await.unwrap();
+        let options = WatchOptions {
+            index: response.data.node.modified_index.map(|index| index + 1),
+            ..Default::default()
+        };
+        let (handle, mut changes) = kv::watch_channel(&client, "/test/foo", options, 1);
+
+        for value in &["1", "2", "3"] {
+            kv::set(&client, "/test/foo", value, None).await.unwrap();
+        }
+
+        for value in &["1", "2", "3"] {
+            let response = changes.recv().await.unwrap().unwrap();
+
+            assert_eq!(response.data.action, Action::Set);
+            assert_eq!(response.data.node.value.as_deref(), Some(*value));
+        }
+
+        handle.stop();
+
+        assert!(changes.recv().await.is_none());
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn deleted_keys() {
+    let client = TestClient::new();
+
+    let work = async {
+        kv::set(&client, "/test/dir/a", "1", None).await.unwrap();
+        kv::set(&client, "/test/dir/b/c", "2", None).await.unwrap();
+
+        let response = kv::delete(&client, "/test/dir/a", false).await.unwrap();
+
+        assert_eq!(response.data.deleted_keys(), vec!["/test/dir/a"]);
+
+        // etcd reports a deleted directory without its contents.
+        let response = kv::delete(&client, "/test/dir", true).await.unwrap();
+
+        assert_eq!(response.data.deleted_keys(), vec!["/test/dir"]);
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn set_with_options() {
+    let client = TestClient::new();
+
+    let work = async {
+        let options = SetOptions::builder().prev_exist(false).ttl(60).build();
+        let response = kv::set_with_options(&client, "/test/foo", "bar", options)
+            .await
+            .unwrap();
+        let index = response.data.node.modified_index.unwrap();
+
+        assert_eq!(response.data.action, Action::Create);
+        assert_eq!(response.data.node.ttl, Some(60));
+
+        let options = SetOptions::builder()
+            .prev_value("bar")
+            .prev_index(index + 1)
+            .build();
+
+        match kv::set_with_options(&client, "/test/foo", "baz", options).await {
+            Err(ref errors) => match errors[0] {
+                Error::Api(ref error) => assert_eq!(error.error_code, 101),
+                ref error => panic!("unexpected error: {:?}", error),
+            },
+            Ok(_) => panic!("expected a set with a mismatched index to fail"),
+        }
+
+        let options = SetOptions::builder()
+            .prev_value("bar")
+            .prev_index(index)
+            .build();
+        let response = kv::set_with_options(&client, "/test/foo", "baz", options)
+            .await
+            .unwrap();
+
+        assert_eq!(response.data.action, Action::CompareAndSwap);
+        assert_eq!(response.data.node.value.unwrap(), "baz");
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
@@ -0,0 +1,106 @@
+use etcd::kv::{self, GetOptions};
+use etcd::mock::MockEtcd;
+use etcd::{Client, Error};
+use hyper::header::{HeaderMap, HeaderValue};
+use hyper::{Method, StatusCode};
+use tokio::runtime::Runtime;
+
+#[test]
+fn canned_responses() {
+    let etcd = MockEtcd::new();
+    etcd.respond(
+        StatusCode::CREATED,
+        r#"{"action":"set","node":{"key":"/foo","value":"bar","createdIndex":1}}"#,
+    );
+    etcd.respond(
+        StatusCode::OK,
+        r#"{"action":"get","node":{"key":"/foo","value":"bar","createdIndex":1}}"#,
+    );
+
+    let client = etcd.client();
+
+    let work = async {
+        kv::set(&client, "/foo", "bar", Some(60)).await.unwrap();
+
+        let response = kv::get(&client, "/foo", GetOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(response.data.node.value.unwrap(), "bar");
+
+        let errors = kv::get(&client, "/foo", GetOptions::default())
+            .await
+            .unwrap_err();
+        match errors[0] {
+            Error::Api(ref error) => assert_eq!(error.error_code, 300),
+            ref error => panic!("expected an API error, got {:?}", error),
+        }
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    let requests = etcd.requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[0].method, Method::PUT);
+    assert_eq!(requests[0].uri.path(), "/v2/keys/foo");
+    assert_eq!(requests[0].headers["host"], "etcd.mock:2379");
+    assert_eq!(requests[0].form()["value"], "bar");
+    assert_eq!(requests[0].form()["ttl"], "60");
+    assert_eq!(requests[1].method, Method::GET);
+    assert_eq!(requests[1].query()["recursive"], "false");
+    assert_eq!(etcd.pending_responses(), 0);
+}
+
+#[test]
+fn response_headers() {
+    let etcd = MockEtcd::new();
+    let mut headers = HeaderMap::new();
+    headers.insert("X-Etcd-Index", HeaderValue::from_static("42"));
+    etcd.respond_with_headers(
+        StatusCode::OK,
+        headers,
+        r#"{"action":"get","node":{"key":"/foo","value":"bar","modifiedIndex":1}}"#,
+    );
+
+    let client = etcd.client();
+
+    let work = async {
+        let response = kv::get(&client, "/foo", GetOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(response.cluster_info.etcd_index, Some(42));
+        assert_eq!(response.headers["content-type"], "application/json");
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn routes() {
+    let first = MockEtcd::new();
+    let second = MockEtcd::new();
+    second.respond(
+        StatusCode::OK,
+        r#"{"action":"get","node":{"key":"/foo","value":"bar","modifiedIndex":1}}"#,
+    );
+
+    let connector = first
+        .connector()
+        .refuse("http://a:2379")
+        .route("http://b:2379", &second);
+    let hyper = hyper::Client::builder().build(connector);
+    let client = Client::custom(hyper, &["http://a:2379", "http://b:2379"], None).unwrap();
+
+    let work = async {
+        let response = kv::get(&client, "/foo", GetOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(response.data.node.value.unwrap(), "bar");
+    };
+
+    Runtime::new().unwrap().block_on(work);
+
+    assert!(first.requests().is_empty());
+    assert_eq!(second.requests().len(), 1);
+}
@@ -0,0 +1,52 @@
+use etcd::queue::Queue;
+use futures::future::join_all;
+use tokio::runtime::Runtime;
+
+use crate::test::TestClient;
+
+mod test;
+
+#[test]
+fn push_and_pop() {
+    let client = TestClient::new();
+    let queue = Queue::new(&client, "/test/queue");
+
+    let work = async {
+        assert_eq!(queue.pop().await.unwrap(), None);
+
+        let first = queue.push("a").await.unwrap();
+        let second = queue.push("b").await.unwrap();
+        assert!(first < second);
+
+        assert_eq!(queue.pop().await.unwrap(), Some((first, "a".to_owned())));
+        assert_eq!(queue.pop().await.unwrap(), Some((second, "b".to_owned())));
+        assert_eq!(queue.pop().await.unwrap(), None);
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
+
+#[test]
+fn concurrent_pops() {
+    let client = TestClient::new();
+    let queue = Queue::new(&client, "/test/queue");
+
+    let work = async {
+        for value in &["a", "b", "c"] {
+            queue.push(value).await.unwrap();
+        }
+
+        let pops = (0..5).map(|_| queue.pop());
+        let mut values: Vec<String> = join_all(pops)
+            .await
+            .into_iter()
+            .filter_map(|result| result.unwrap())
+            .map(|(_, value)| value)
+            .collect();
+        values.sort();
+
+        assert_eq!(values, vec!["a", "b", "c"]);
+    };
+
+    Runtime::new().unwrap().block_on(work);
+}
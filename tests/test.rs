@@ -1,6 +1,6 @@
 use std::fs::File;
 use std::io::Read;
-use std::ops::Deref;
+use std::ops::{Deref, DerefMut};
 
 use etcd::{kv, Client};
 use futures::{Future, FutureExt};
@@ -109,3 +109,12 @@ where
         &self.c
     }
 }
+
+impl<C> DerefMut for TestClient<C>
+where
+    C: Clone + Connect + Sync + Send + 'static,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.c
+    }
+}